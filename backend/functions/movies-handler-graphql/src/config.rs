@@ -0,0 +1,47 @@
+use crate::error::{Error, Result};
+use rusoto_core::Region;
+use std::str::FromStr;
+
+/// What happens to attributes of stored items this version does not know,
+/// e.g. ones written by a newer version of the function.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum UnknownAttributes {
+    /// They are dropped when the item is written again. Movie meta rows are updated in
+    /// place instead of being written again, they keep them.
+    Ignore,
+    /// They are written back unchanged when a movie or actor is updated.
+    #[default]
+    Keep,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub region: Region,
+    pub table_name: String,
+    pub unknown_attributes: UnknownAttributes,
+}
+
+impl Config {
+    /// Reads the configuration from the environment of the lambda function.
+    pub fn from_env() -> Result<Self> {
+        Ok(Config {
+            region: Region::from_str(&var("AWS_REGION")?)
+                .map_err(|e| Error::Configuration(e.to_string()))?,
+            table_name: var("TABLE_NAME")?,
+            unknown_attributes: match std::env::var("UNKNOWN_ATTRIBUTES").as_deref() {
+                Err(_) | Ok("keep") => UnknownAttributes::Keep,
+                Ok("ignore") => UnknownAttributes::Ignore,
+                Ok(other) => {
+                    return Err(Error::Configuration(format!(
+                        "UNKNOWN_ATTRIBUTES must be keep or ignore, not {}",
+                        other
+                    )))
+                }
+            },
+        })
+    }
+}
+
+fn var(name: &str) -> Result<String> {
+    std::env::var(name).map_err(|e| Error::Configuration(format!("{}: {}", name, e)))
+}
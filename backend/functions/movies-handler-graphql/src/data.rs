@@ -1,60 +1,339 @@
-use chrono::{DateTime, Utc};
-use juniper::GraphQLObject;
+use chrono::{DateTime, NaiveDate, Utc};
+use juniper::{GraphQLEnum, GraphQLObject};
+use rusoto_dynamodb::AttributeValue;
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::TryFrom};
+use uuid::Uuid;
 
-error_chain! {
-    types {
-        Error, ErrorKind, ResultExt, Result;
-    }
-
-    foreign_links {
-        SerdeDynamo(::serde_dynamodb::Error);
-        Var(::std::env::VarError);
-        ParseRegionError(::rusoto_core::region::ParseRegionError);
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, GraphQLObject)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct Movie {
-    #[graphql(name = "meta")]
     pub meta: MovieMetadata,
-    #[graphql(name = "roles")]
     pub roles: Vec<Role>,
+    pub crew: Vec<CrewCredit>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, GraphQLObject)]
 #[serde(rename_all = "snake_case")]
 pub struct MovieMetadata {
+    #[graphql(skip)]
+    pub id: Uuid,
+    #[graphql(skip)]
+    pub version: i32,
     #[graphql(name = "title")]
     pub title: String,
     #[graphql(name = "imdb_id")]
     pub imdb_id: Option<String>,
     #[graphql(name = "published_at")]
     pub published_at: DateTime<Utc>,
+    #[graphql(name = "genres")]
+    pub genres: Vec<String>,
+    #[graphql(name = "runtime_minutes")]
+    pub runtime_minutes: Option<i32>,
+    /// ISO 639-1 code of the language the movie was shot in like `en`.
+    #[graphql(name = "original_language")]
+    pub original_language: Option<String>,
+    /// ISO 3166-1 alpha-2 codes of the production countries like `US`.
+    #[graphql(name = "countries")]
+    pub countries: Vec<String>,
+    #[graphql(name = "synopsis")]
+    pub synopsis: Option<String>,
+    /// Age rating like `R` or `FSK 16`, in the rating system of the original country.
+    #[graphql(name = "age_rating")]
+    pub age_rating: Option<String>,
+    #[graphql(name = "directors")]
+    pub directors: Vec<String>,
+    #[graphql(name = "writers")]
+    pub writers: Vec<String>,
+    #[graphql(name = "poster_url")]
+    pub poster_url: Option<String>,
+    /// Releases in other countries, `published_at` is the first release.
+    #[graphql(name = "release_dates")]
+    pub release_dates: Vec<ReleaseDate>,
+    /// Aggregates of the ratings of the movie, only changed by ratings.
+    #[graphql(skip)]
+    pub ratings: RatingSummary,
+    /// Stored attributes unknown to this version, written back unchanged.
+    #[graphql(skip)]
+    #[serde(skip)]
+    pub unknown: HashMap<String, AttributeValue>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, GraphQLObject)]
+impl MovieMetadata {
+    /// A movie without any of the optional fields and ratings, not stored yet.
+    pub fn new(id: Uuid, title: String, published_at: DateTime<Utc>) -> Self {
+        MovieMetadata {
+            id,
+            version: 0,
+            title,
+            imdb_id: None,
+            published_at,
+            genres: vec![],
+            runtime_minutes: None,
+            original_language: None,
+            countries: vec![],
+            synopsis: None,
+            age_rating: None,
+            directors: vec![],
+            writers: vec![],
+            poster_url: None,
+            release_dates: vec![],
+            ratings: RatingSummary::default(),
+            unknown: HashMap::new(),
+        }
+    }
+}
+
+/// Ratings go from 1 to this.
+pub const MAX_RATING: i32 = 10;
+
+/// Aggregates of the ratings of a movie, one rating per user.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RatingSummary {
+    pub count: i32,
+    pub sum: i32,
+    /// Number of ratings by rating, the first entry counts the ratings of 1.
+    pub histogram: [i32; MAX_RATING as usize],
+}
+
+impl RatingSummary {
+    pub fn mean(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(f64::from(self.sum) / f64::from(self.count))
+        } else {
+            None
+        }
+    }
+
+    /// Counts a rating, replacing the previous rating of the same user if there is one.
+    /// Ratings out of range are not counted in the histogram.
+    pub fn rate(&mut self, rating: i32, previous: Option<i32>) {
+        if let Some(previous) = previous {
+            self.count -= 1;
+            self.sum -= previous;
+            if let Some(bucket) = self.bucket(previous) {
+                *bucket -= 1;
+            }
+        }
+        self.count += 1;
+        self.sum += rating;
+        if let Some(bucket) = self.bucket(rating) {
+            *bucket += 1;
+        }
+    }
+
+    fn bucket(&mut self, rating: i32) -> Option<&mut i32> {
+        let index = usize::try_from(rating - 1).ok()?;
+        self.histogram.get_mut(index)
+    }
+}
+
+/// The rating of a movie by a user, optionally with a text. Every user has one review
+/// per movie, rating again replaces it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Review {
+    pub movie_id: Uuid,
+    pub user_id: String,
+    pub version: i32,
+    pub rating: i32,
+    pub text: Option<String>,
+    pub reviewed_at: DateTime<Utc>,
+}
+
+/// Release of a movie in a country.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, GraphQLObject)]
+#[serde(rename_all = "snake_case")]
+pub struct ReleaseDate {
+    /// ISO 3166-1 alpha-2 code of the country.
+    #[graphql(name = "country")]
+    pub country: String,
+    #[graphql(name = "released_on")]
+    pub released_on: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct Role {
-    #[graphql(name = "actor")]
     pub actor: Actor,
-    #[graphql(name = "characters")]
+    pub credit: Credit,
+}
+
+/// How an actor is credited for the characters played in a movie.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Credit {
     pub characters: Vec<Character>,
+    /// Position in the cast list, lower positions are billed first.
+    pub billing_order: Option<i32>,
+    /// Name in the credits if it is not the name of the actor.
+    pub credited_as: Option<String>,
+    pub uncredited: bool,
+    pub voice: bool,
+    pub cameo: bool,
+    /// Stored attributes of the role unknown to this version, written back unchanged.
+    #[serde(skip)]
+    pub unknown: HashMap<String, AttributeValue>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, GraphQLObject)]
+/// A member of the crew of a movie. Crew members are not actors, they are identified
+/// by name within their department.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, GraphQLObject)]
 #[serde(rename_all = "snake_case")]
-pub struct Character {
+pub struct CrewCredit {
     #[graphql(name = "name")]
     pub name: String,
+    #[graphql(name = "department")]
+    pub department: Department,
+    /// The job within the department like `Screenplay`, if it is more specific.
+    #[graphql(name = "job")]
+    pub job: Option<String>,
+    /// Stored attributes unknown to this version, written back unchanged.
+    #[graphql(skip)]
+    #[serde(skip)]
+    pub unknown: HashMap<String, AttributeValue>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, GraphQLObject)]
+/// Departments of the crew, in the order they are listed in.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, GraphQLEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Department {
+    /// Directors.
+    #[graphql(name = "DIRECTING")]
+    Directing,
+    /// Writers.
+    #[graphql(name = "WRITING")]
+    Writing,
+    /// Composers.
+    #[graphql(name = "MUSIC")]
+    Music,
+    /// Cinematographers.
+    #[graphql(name = "CAMERA")]
+    Camera,
+}
+
+impl Department {
+    /// Name of the department in keys and attributes.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Department::Directing => "directing",
+            Department::Writing => "writing",
+            Department::Music => "music",
+            Department::Camera => "camera",
+        }
+    }
+}
+
+/// A character portrayed in movies and episodes. Characters may share a name like actors,
+/// roles naming such a character have to refer to it by id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Character {
+    pub id: Uuid,
+    /// Characters are written once and never changed, so stored characters are at
+    /// version 1 and new ones at version 0.
+    pub version: i32,
+    pub name: String,
+}
+
+impl Character {
+    /// A character of a role written before characters were stored on their own, the role
+    /// names it only. It has the nil id until it is linked to a character by its name.
+    pub fn unlinked(name: String) -> Self {
+        Character {
+            id: Uuid::nil(),
+            version: 0,
+            name,
+        }
+    }
+
+    pub fn is_linked(&self) -> bool {
+        !self.id.is_nil()
+    }
+}
+
+/// A movie or an episode, the titles characters are portrayed in.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum Production {
+    Movie(MovieMetadata),
+    Episode(Episode),
+}
+
+impl Production {
+    pub fn published_at(&self) -> DateTime<Utc> {
+        match self {
+            Production::Movie(meta) => meta.published_at,
+            Production::Episode(episode) => episode.published_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct Actor {
-    #[graphql(name = "last_name")]
+    pub id: Uuid,
+    pub version: i32,
     pub last_name: String,
-    #[graphql(name = "first_name")]
     pub first_name: String,
+    pub birth_date: Option<NaiveDate>,
+    /// IMDb id of the actor like `nm0000199`.
+    pub imdb_id: Option<String>,
+    pub biography: Option<String>,
+    /// Other names the actor is credited as.
+    pub aliases: Vec<String>,
+    /// Stored attributes unknown to this version, written back unchanged.
+    #[serde(skip)]
+    pub unknown: HashMap<String, AttributeValue>,
+}
+
+/// Movies belonging together, like the movies of a franchise. The movies are ordered by
+/// their position in the collection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Collection {
+    pub id: Uuid,
+    pub version: i32,
+    pub name: String,
+    /// Stored attributes unknown to this version, written back unchanged.
+    #[serde(skip)]
+    pub unknown: HashMap<String, AttributeValue>,
+}
+
+/// A TV series, its seasons and episodes are stored in the partition of the series.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Series {
+    pub id: Uuid,
+    pub version: i32,
+    pub title: String,
+    /// Premiere of the first episode.
+    pub published_at: DateTime<Utc>,
+    /// Stored attributes unknown to this version, written back unchanged.
+    #[serde(skip)]
+    pub unknown: HashMap<String, AttributeValue>,
+}
+
+/// A season of a series, seasons are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Season {
+    pub series_id: Uuid,
+    pub number: i32,
+    pub title: Option<String>,
+}
+
+/// An episode of a season, episodes are numbered from 1 within their season.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Episode {
+    pub series_id: Uuid,
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub title: String,
+    /// First broadcast of the episode.
+    pub published_at: DateTime<Utc>,
 }
@@ -1,34 +1,153 @@
-use crate::data::{Actor, Movie};
-use crate::option::OptionMutExt;
-use chrono::{DateTime, Datelike, Utc};
+use crate::config::UnknownAttributes;
+use crate::data::{
+    Actor, Character, Collection, Credit, CrewCredit, Department, Episode, Movie, MovieMetadata,
+    RatingSummary, ReleaseDate, Review, Role, Season, Series, MAX_RATING,
+};
+use crate::error::{Error, Result};
+use crate::keys::*;
+use crate::search::{normalize_title, search_prefix};
+use chrono::{DateTime, Datelike, NaiveDate, SecondsFormat, Utc};
+use rusoto_dynamodb::AttributeValue;
 use serde::{
-    de::{Deserializer, MapAccess, Visitor},
+    de::{self, Deserializer, IgnoredAny, MapAccess, Visitor},
     ser::{SerializeStruct, Serializer},
     Deserialize, Serialize,
 };
 use std::{collections::HashMap, fmt};
+use uuid::Uuid;
 
-error_chain! {
-    types {
-        Error, ErrorKind, ResultExt, Result;
-    }
+pub type Attributes = HashMap<String, AttributeValue>;
 
-    foreign_links {
-        SerdeDynamo(::serde_dynamodb::Error);
-        Var(::std::env::VarError);
-        ParseRegionError(::rusoto_core::region::ParseRegionError);
-    }
+/// Season and episode numbers are padded to this many digits in keys, so that they sort
+/// numerically. Larger numbers do not fit.
+pub const MAX_EPISODE_NUMBER: i32 = 9999;
+
+/// Attributes read into `DynamoTableItem`, all others are unknown to this version.
+const KNOWN_ATTRIBUTES: &[&str] = &[
+    "pk",
+    "sk",
+    "version",
+    "lookup",
+    "title",
+    "imdb_id",
+    "published_at",
+    "published_year",
+    "search_prefix",
+    "search_title",
+    "genres",
+    "runtime_minutes",
+    "original_language",
+    "countries",
+    "synopsis",
+    "age_rating",
+    "directors",
+    "writers",
+    "poster_url",
+    "release_dates",
+    "characters",
+    "character_ids",
+    "character_name",
+    "last_name",
+    "first_name",
+    "birth_date",
+    "biography",
+    "actor_imdb_id",
+    "aliases",
+    "billing_order",
+    "credited_as",
+    "uncredited",
+    "voice",
+    "cameo",
+    "department",
+    "crew_name",
+    "job",
+    "collection_name",
+    "position",
+    "series_title",
+    "season_title",
+    "episode_title",
+    "rating_count",
+    "rating_sum",
+    "rating_1",
+    "rating_2",
+    "rating_3",
+    "rating_4",
+    "rating_5",
+    "rating_6",
+    "rating_7",
+    "rating_8",
+    "rating_9",
+    "rating_10",
+    "rating",
+    "review_text",
+    "reviewed_at",
+];
+
+/// Attributes of movie meta rows counting the ratings by rating, they are numbers so that
+/// ratings can be counted with `ADD` updates.
+pub const RATING_HISTOGRAM_ATTRIBUTES: [&str; 10] = [
+    "rating_1",
+    "rating_2",
+    "rating_3",
+    "rating_4",
+    "rating_5",
+    "rating_6",
+    "rating_7",
+    "rating_8",
+    "rating_9",
+    "rating_10",
+];
+
+/// Optional attributes of movie meta rows, a write of the movie removes those it leaves out.
+const MOVIE_META_OPTIONAL_ATTRIBUTES: [&str; 8] = [
+    "imdb_id",
+    "search_prefix",
+    "search_title",
+    "runtime_minutes",
+    "original_language",
+    "synopsis",
+    "age_rating",
+    "poster_url",
+];
+
+/// Whether the attribute of a movie meta row is only changed by ratings. Writes of the
+/// movie keep these, so ratings are counted without being conditioned on its version.
+pub fn is_rating_attribute(name: &str) -> bool {
+    name == "rating_count" || name == "rating_sum" || RATING_HISTOGRAM_ATTRIBUTES.contains(&name)
 }
 
+// items only live between a request and the store, boxing the meta row is not worth it
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum MovieKindItem {
     Meta {
         title: String,
         published_at: DateTime<Utc>,
         imdb_id: Option<String>,
+        genres: Vec<String>,
+        runtime_minutes: Option<i32>,
+        original_language: Option<String>,
+        countries: Vec<String>,
+        synopsis: Option<String>,
+        age_rating: Option<String>,
+        directors: Vec<String>,
+        writers: Vec<String>,
+        poster_url: Option<String>,
+        release_dates: Vec<ReleaseDate>,
+        ratings: RatingSummary,
     },
     Actor {
-        characters: Vec<String>,
+        role: RoleItem,
+    },
+    Crew {
+        name: String,
+        department: Department,
+        job: Option<String>,
+    },
+    Review {
+        rating: i32,
+        text: Option<String>,
+        reviewed_at: DateTime<Utc>,
     },
 }
 #[derive(Debug)]
@@ -36,27 +155,199 @@ pub enum ActorKindItem {
     Meta {
         last_name: String,
         first_name: String,
+        birth_date: Option<NaiveDate>,
+        imdb_id: Option<String>,
+        biography: Option<String>,
+        aliases: Vec<String>,
+    },
+}
+
+/// Attributes of a role row, the casts of movies and episodes are stored alike. The names
+/// of the characters are stored along with their ids, so that the inverted index lists
+/// them without reading the characters.
+#[derive(Debug)]
+pub struct RoleItem {
+    pub characters: Vec<String>,
+    pub character_ids: Vec<String>,
+    pub billing_order: Option<i32>,
+    pub credited_as: Option<String>,
+    pub uncredited: bool,
+    pub voice: bool,
+    pub cameo: bool,
+}
+
+impl RoleItem {
+    fn new(credit: &Credit) -> Self {
+        RoleItem {
+            characters: credit.characters.iter().map(|c| c.name.clone()).collect(),
+            character_ids: credit.characters.iter().map(|c| c.id.to_string()).collect(),
+            billing_order: credit.billing_order,
+            credited_as: credit.credited_as.clone(),
+            uncredited: credit.uncredited,
+            voice: credit.voice,
+            cameo: credit.cameo,
+        }
+    }
+
+    fn into_credit(self) -> Result<Credit> {
+        let characters = if self.character_ids.is_empty() {
+            // roles written before characters were stored on their own name them only
+            self.characters
+                .into_iter()
+                .map(Character::unlinked)
+                .collect()
+        } else if self.characters.len() != self.character_ids.len() {
+            return Err(Error::CorruptItem(format!(
+                "{} characters with {} ids",
+                self.characters.len(),
+                self.character_ids.len()
+            )));
+        } else {
+            self.character_ids
+                .iter()
+                .zip(self.characters)
+                .map(|(id, name)| {
+                    Ok(Character {
+                        id: Self::parse_character_id(id)?,
+                        version: 1,
+                        name,
+                    })
+                })
+                .collect::<Result<Vec<Character>>>()?
+        };
+        Ok(Credit {
+            characters,
+            billing_order: self.billing_order,
+            credited_as: self.credited_as,
+            uncredited: self.uncredited,
+            voice: self.voice,
+            cameo: self.cameo,
+            unknown: Attributes::new(),
+        })
+    }
+
+    fn parse_character_id(id: &str) -> Result<Uuid> {
+        Uuid::parse_str(id).map_err(|_| Error::CorruptItem(format!("invalid character id {}", id)))
+    }
+
+    /// The ids of the characters, a character the role lists twice is returned once.
+    fn character_ids(&self) -> Result<Vec<Uuid>> {
+        let mut ids = self
+            .character_ids
+            .iter()
+            .map(|id| Self::parse_character_id(id))
+            .collect::<Result<Vec<Uuid>>>()?;
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    /// Writes the attributes of the role, the keys and version of the row are written
+    /// by the caller.
+    fn serialize_fields<S: SerializeStruct>(
+        &self,
+        state: &mut S,
+    ) -> std::result::Result<(), S::Error> {
+        state.serialize_field("characters", &self.characters)?;
+        state.serialize_field("character_ids", &self.character_ids)?;
+        serialize_optional(state, "billing_order", &self.billing_order)?;
+        serialize_optional(state, "credited_as", &self.credited_as)?;
+        state.serialize_field("uncredited", &self.uncredited)?;
+        state.serialize_field("voice", &self.voice)?;
+        state.serialize_field("cameo", &self.cameo)
+    }
+}
+
+/// Rows of a series partition: the meta row, the seasons and the episodes ordered by
+/// season and episode number.
+#[derive(Debug)]
+pub enum SeriesKindItem {
+    Meta {
+        title: String,
+        published_at: DateTime<Utc>,
+    },
+    Season {
+        title: Option<String>,
+    },
+    Episode {
+        title: String,
+        published_at: DateTime<Utc>,
     },
 }
 
+/// Rows of an episode partition, the cast of the episode. The partition key is made of
+/// the series, season and episode number, so the inverted index lists the episodes of
+/// an actor like it lists the movies.
+#[derive(Debug)]
+pub enum EpisodeKindItem {
+    Actor { role: RoleItem },
+}
+
+/// Rows of a character partition, the meta row and one row per movie or episode and actor
+/// portraying the character. Title and actor are part of the sort key.
+#[derive(Debug, Clone)]
+pub enum CharacterKindItem {
+    Meta { name: String },
+    Movie,
+    Episode,
+}
+
+/// The title of a portrayal row, a movie by id or an episode by the keys of its row in the
+/// series partition.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PortrayalTitle {
+    Movie(Uuid),
+    Episode((String, String)),
+}
+
+/// Rows of a collection partition, the meta row and one row per movie of the collection.
+/// The movie is part of the sort key, so the inverted index lists the collections of a movie.
+#[derive(Debug)]
+pub enum CollectionKindItem {
+    Meta { name: String },
+    Movie { position: i32 },
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum DynamoTableItemKind {
     Movie { kind: MovieKindItem },
     Actor { kind: ActorKindItem },
+    Character { kind: CharacterKindItem },
+    Collection { kind: CollectionKindItem },
+    Series { kind: SeriesKindItem },
+    Episode { kind: EpisodeKindItem },
 }
 
 #[derive(Debug)]
 pub struct DynamoTableItem {
     pub pk: String,
     pub sk: String,
+    /// Incremented with every write, items written before versioning was introduced
+    /// read as version 0.
+    pub version: i32,
     pub kind: DynamoTableItemKind,
+    /// Attributes this version does not know, e.g. ones written by a newer version.
+    /// They are only filled by `from_item` and written back by `to_item`.
+    pub unknown: Attributes,
 }
 
 #[derive(Debug)]
 pub enum DynamoTableRowKind {
     MovieMeta,
     MovieActor,
+    MovieCrew,
+    MovieReview,
     ActorMeta,
+    CharacterMeta,
+    CharacterMovie,
+    CharacterEpisode,
+    CollectionMeta,
+    CollectionMovie,
+    SeriesMeta,
+    SeriesSeason,
+    SeriesEpisode,
+    EpisodeActor,
 }
 
 impl DynamoTableRowKind {
@@ -64,7 +355,24 @@ impl DynamoTableRowKind {
         match self {
             Self::MovieMeta => (String::from("movie::"), String::from("meta")),
             Self::MovieActor => (String::from("movie::"), String::from("actor::")),
+            Self::MovieCrew => (String::from("movie::"), String::from("crew::")),
+            Self::MovieReview => (String::from("movie::"), String::from("review::")),
             Self::ActorMeta => (String::from("actor::"), String::from("meta")),
+            Self::CharacterMeta => (String::from("character::"), String::from("meta")),
+            Self::CharacterMovie => (
+                String::from("character::"),
+                String::from("portrayal::movie::"),
+            ),
+            Self::CharacterEpisode => (
+                String::from("character::"),
+                String::from("portrayal::episode::"),
+            ),
+            Self::CollectionMeta => (String::from("collection::"), String::from("meta")),
+            Self::CollectionMovie => (String::from("collection::"), String::from("movie::")),
+            Self::SeriesMeta => (String::from("series::"), String::from("meta")),
+            Self::SeriesSeason => (String::from("series::"), String::from("season::")),
+            Self::SeriesEpisode => (String::from("series::"), String::from("episode::")),
+            Self::EpisodeActor => (String::from("episode::"), String::from("actor::")),
         }
     }
 
@@ -72,103 +380,1245 @@ impl DynamoTableRowKind {
         let movie_pk = &Self::MovieMeta {}.get_prefixes().0;
         let movie_meta_sk = &Self::MovieMeta {}.get_prefixes().1;
         let movie_actor_sk = &Self::MovieActor {}.get_prefixes().1;
+        let movie_crew_sk = &Self::MovieCrew {}.get_prefixes().1;
+        let movie_review_sk = &Self::MovieReview {}.get_prefixes().1;
         let actor_pk = &Self::ActorMeta {}.get_prefixes().0;
         let actor_meta_sk = &Self::ActorMeta {}.get_prefixes().1;
+        let character_pk = &Self::CharacterMeta {}.get_prefixes().0;
+        let character_meta_sk = &Self::CharacterMeta {}.get_prefixes().1;
+        let character_movie_sk = &Self::CharacterMovie {}.get_prefixes().1;
+        let character_episode_sk = &Self::CharacterEpisode {}.get_prefixes().1;
+        let collection_pk = &Self::CollectionMeta {}.get_prefixes().0;
+        let collection_meta_sk = &Self::CollectionMeta {}.get_prefixes().1;
+        let collection_movie_sk = &Self::CollectionMovie {}.get_prefixes().1;
+        let series_pk = &Self::SeriesMeta {}.get_prefixes().0;
+        let series_meta_sk = &Self::SeriesMeta {}.get_prefixes().1;
+        let series_season_sk = &Self::SeriesSeason {}.get_prefixes().1;
+        let series_episode_sk = &Self::SeriesEpisode {}.get_prefixes().1;
+        let episode_pk = &Self::EpisodeActor {}.get_prefixes().0;
+        let episode_actor_sk = &Self::EpisodeActor {}.get_prefixes().1;
 
         if pk.starts_with(movie_pk) {
             if sk == movie_meta_sk {
                 Ok(DynamoTableRowKind::MovieMeta)
             } else if sk.starts_with(movie_actor_sk) {
                 Ok(DynamoTableRowKind::MovieActor)
+            } else if sk.starts_with(movie_crew_sk) {
+                Ok(DynamoTableRowKind::MovieCrew)
+            } else if sk.starts_with(movie_review_sk) {
+                Ok(DynamoTableRowKind::MovieReview)
+            } else {
+                Err(Error::CorruptItem(format!("unknown item {}/{}", pk, sk)))
+            }
+        } else if pk.starts_with(actor_pk) {
+            if sk.starts_with(actor_meta_sk) {
+                Ok(DynamoTableRowKind::ActorMeta)
+            } else {
+                Err(Error::CorruptItem(format!("unknown item {}/{}", pk, sk)))
+            }
+        } else if pk.starts_with(character_pk) {
+            if sk == character_meta_sk {
+                Ok(DynamoTableRowKind::CharacterMeta)
+            } else if sk.starts_with(character_movie_sk) {
+                Ok(DynamoTableRowKind::CharacterMovie)
+            } else if sk.starts_with(character_episode_sk) {
+                Ok(DynamoTableRowKind::CharacterEpisode)
+            } else {
+                Err(Error::CorruptItem(format!("unknown item {}/{}", pk, sk)))
+            }
+        } else if pk.starts_with(collection_pk) {
+            if sk == collection_meta_sk {
+                Ok(DynamoTableRowKind::CollectionMeta)
+            } else if sk.starts_with(collection_movie_sk) {
+                Ok(DynamoTableRowKind::CollectionMovie)
             } else {
-                Err("unknown".into())
+                Err(Error::CorruptItem(format!("unknown item {}/{}", pk, sk)))
+            }
+        } else if pk.starts_with(series_pk) {
+            if sk == series_meta_sk {
+                Ok(DynamoTableRowKind::SeriesMeta)
+            } else if sk.starts_with(series_season_sk) {
+                Ok(DynamoTableRowKind::SeriesSeason)
+            } else if sk.starts_with(series_episode_sk) {
+                Ok(DynamoTableRowKind::SeriesEpisode)
+            } else {
+                Err(Error::CorruptItem(format!("unknown item {}/{}", pk, sk)))
+            }
+        } else if pk.starts_with(episode_pk) {
+            if sk.starts_with(episode_actor_sk) {
+                Ok(DynamoTableRowKind::EpisodeActor)
+            } else {
+                Err(Error::CorruptItem(format!("unknown item {}/{}", pk, sk)))
+            }
+        } else {
+            Err(Error::CorruptItem(format!("unknown item {}/{}", pk, sk)))
+        }
+    }
+}
+
+impl DynamoTableItem {
+    pub fn movie_pk(id: &Uuid) -> String {
+        encode_key(
+            &DynamoTableRowKind::MovieMeta.get_prefixes().0,
+            &[&id.to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    pub fn actor_pk(id: &Uuid) -> String {
+        encode_key(
+            &DynamoTableRowKind::ActorMeta.get_prefixes().0,
+            &[&id.to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    pub fn character_pk(id: &Uuid) -> String {
+        encode_key(
+            &DynamoTableRowKind::CharacterMeta.get_prefixes().0,
+            &[&id.to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    pub fn character_movie_sk(movie_id: &Uuid, actor_id: &Uuid) -> String {
+        encode_key(
+            &DynamoTableRowKind::CharacterMovie.get_prefixes().1,
+            &[&movie_id.to_string(), &actor_id.to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    pub fn character_episode_sk(
+        series_id: &Uuid,
+        season_number: i32,
+        episode_number: i32,
+        actor_id: &Uuid,
+    ) -> String {
+        encode_key(
+            &DynamoTableRowKind::CharacterEpisode.get_prefixes().1,
+            &[
+                &series_id.to_string(),
+                &Self::number_component(season_number),
+                &Self::number_component(episode_number),
+                &actor_id.to_string(),
+            ],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    /// Prefix of the sort keys of the portrayals of a character, the episodes sort before
+    /// the movies.
+    pub fn character_portrayals_sk() -> String {
+        String::from("portrayal::")
+    }
+
+    /// Keys of the character rows of a role, a character the role lists twice has one row.
+    /// Episode roles are told apart from movie roles by their partition key.
+    pub fn character_keys(&self) -> Result<Vec<(String, String)>> {
+        let (role, sk) = match &self.kind {
+            DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Actor { role },
+            } => {
+                let prefixes = DynamoTableRowKind::MovieActor.get_prefixes();
+                let movie_id = Self::parse_id(&self.pk, &prefixes.0)?;
+                let actor_id = Self::parse_id(&self.sk, &prefixes.1)?;
+                (role, Self::character_movie_sk(&movie_id, &actor_id))
+            }
+            DynamoTableItemKind::Episode {
+                kind: EpisodeKindItem::Actor { role },
+            } => {
+                let (series_id, season_number, episode_number) = Self::parse_episode_pk(&self.pk)?;
+                let actor_id =
+                    Self::parse_id(&self.sk, &DynamoTableRowKind::EpisodeActor.get_prefixes().1)?;
+                let sk = Self::character_episode_sk(
+                    &series_id,
+                    season_number,
+                    episode_number,
+                    &actor_id,
+                );
+                (role, sk)
+            }
+            _ => return Ok(vec![]),
+        };
+        Ok(role
+            .character_ids()?
+            .iter()
+            .map(|id| (Self::character_pk(id), sk.clone()))
+            .collect())
+    }
+
+    /// Returns the keys of the given rows together with the keys of the rows written along
+    /// with them, i.e. the character rows of roles.
+    pub fn row_keys(items: Vec<Attributes>) -> Result<Vec<(String, String)>> {
+        let mut keys = vec![];
+        for item in items {
+            let item = Self::from_item(item, UnknownAttributes::Ignore)?;
+            keys.extend(item.character_keys()?);
+            keys.push((item.pk, item.sk));
+        }
+        Ok(keys)
+    }
+
+    /// Keys of the character rows of a stored role row, none if there is no role.
+    pub fn role_character_keys(role: Option<Attributes>) -> Result<Vec<(String, String)>> {
+        match role {
+            Some(item) => Self::from_item(item, UnknownAttributes::Ignore)?.character_keys(),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// The stored keys that are not written again, the rows to delete when the items
+    /// replace the stored rows.
+    pub fn stale_keys(stored: Vec<(String, String)>, items: &[Self]) -> Vec<(String, String)> {
+        stored
+            .into_iter()
+            .filter(|(pk, sk)| !items.iter().any(|item| item.pk == *pk && item.sk == *sk))
+            .collect()
+    }
+
+    pub fn collection_pk(id: &Uuid) -> String {
+        encode_key(
+            &DynamoTableRowKind::CollectionMeta.get_prefixes().0,
+            &[&id.to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    pub fn collection_movie_sk(movie_id: &Uuid) -> String {
+        encode_key(
+            &DynamoTableRowKind::CollectionMovie.get_prefixes().1,
+            &[&movie_id.to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    pub fn series_pk(id: &Uuid) -> String {
+        encode_key(
+            &DynamoTableRowKind::SeriesMeta.get_prefixes().0,
+            &[&id.to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    pub fn series_season_sk(number: i32) -> String {
+        encode_key(
+            &DynamoTableRowKind::SeriesSeason.get_prefixes().1,
+            &[&Self::number_component(number)],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    /// Episodes are keyed by season and episode number, so they are listed in order.
+    pub fn series_episode_sk(season_number: i32, episode_number: i32) -> String {
+        encode_key(
+            &DynamoTableRowKind::SeriesEpisode.get_prefixes().1,
+            &[
+                &Self::number_component(season_number),
+                &Self::number_component(episode_number),
+            ],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    /// Prefix of the sort keys of the episodes of a season.
+    pub fn series_season_episodes_sk(season_number: i32) -> String {
+        encode_key(
+            &DynamoTableRowKind::SeriesEpisode.get_prefixes().1,
+            &[&Self::number_component(season_number), ""],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    pub fn episode_pk(series_id: &Uuid, season_number: i32, episode_number: i32) -> String {
+        encode_key(
+            &DynamoTableRowKind::EpisodeActor.get_prefixes().0,
+            &[
+                &series_id.to_string(),
+                &Self::number_component(season_number),
+                &Self::number_component(episode_number),
+            ],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    fn number_component(number: i32) -> String {
+        format!("{:04}", number)
+    }
+
+    pub fn movie_actor_sk(actor_id: &Uuid) -> String {
+        encode_key(
+            &DynamoTableRowKind::MovieActor.get_prefixes().1,
+            &[&actor_id.to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    /// Reviews are keyed by user, so every user has one review per movie.
+    pub fn movie_review_sk(user_id: &str) -> String {
+        encode_key(
+            &DynamoTableRowKind::MovieReview.get_prefixes().1,
+            &[user_id],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    /// Crew members are keyed by department and name, so they are listed by department.
+    pub fn movie_crew_sk(department: Department, name: &str) -> String {
+        encode_key(
+            &DynamoTableRowKind::MovieCrew.get_prefixes().1,
+            &[department.as_str(), name],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    /// Formats a release date the way it is stored, so it can be compared in key conditions.
+    pub fn published_at_value(published_at: &DateTime<Utc>) -> String {
+        published_at.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    }
+
+    /// Value of the `lookup` attribute of a movie meta row, movies are found by title and year.
+    pub fn movie_lookup(title: &str, published_at: &DateTime<Utc>) -> String {
+        encode_key(
+            &DynamoTableRowKind::MovieMeta.get_prefixes().0,
+            &[title, &published_at.year().to_string()],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    /// Value of the `lookup` attribute of a character meta row, characters are found by name.
+    pub fn character_lookup(name: &str) -> String {
+        encode_key(
+            &DynamoTableRowKind::CharacterMeta.get_prefixes().0,
+            &[name],
+            COMPONENT_SEPARATOR,
+        )
+    }
+
+    /// Value of the `lookup` attribute of an actor meta row, actors are found by name.
+    pub fn actor_lookup(last_name: &str, first_name: &str) -> String {
+        encode_key(
+            &DynamoTableRowKind::ActorMeta.get_prefixes().0,
+            &[last_name, first_name],
+            NAME_SEPARATOR,
+        )
+    }
+
+    /// The `lookup` attribute of meta rows, indexed to find entities by their natural key.
+    pub fn lookup(&self) -> Option<String> {
+        match &self.kind {
+            DynamoTableItemKind::Movie {
+                kind:
+                    MovieKindItem::Meta {
+                        title,
+                        published_at,
+                        ..
+                    },
+            } => Some(Self::movie_lookup(title, published_at)),
+            DynamoTableItemKind::Actor {
+                kind:
+                    ActorKindItem::Meta {
+                        last_name,
+                        first_name,
+                        ..
+                    },
+            } => Some(Self::actor_lookup(last_name, first_name)),
+            DynamoTableItemKind::Character {
+                kind: CharacterKindItem::Meta { name },
+            } => Some(Self::character_lookup(name)),
+            _ => None,
+        }
+    }
+
+    /// Meta rows guard their partition: they are only written if the stored version is
+    /// the one preceding the version of the item. Other rows are written unconditionally,
+    /// together with the meta row of their partition. Reviews are written by their users
+    /// independently of the movie, so they are versioned on their own. Character rows are
+    /// written with the roles, only the meta row of a new character is guarded.
+    pub fn expected_version(&self) -> Option<i32> {
+        match self.kind {
+            DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Meta { .. },
+            }
+            | DynamoTableItemKind::Actor {
+                kind: ActorKindItem::Meta { .. },
+            }
+            | DynamoTableItemKind::Character {
+                kind: CharacterKindItem::Meta { .. },
+            }
+            | DynamoTableItemKind::Collection {
+                kind: CollectionKindItem::Meta { .. },
+            }
+            | DynamoTableItemKind::Series {
+                kind: SeriesKindItem::Meta { .. },
+            }
+            | DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Review { .. },
+            } => Some(self.version - 1),
+            _ => None,
+        }
+    }
+
+    /// The attributes a write of a movie meta row sets and the optional ones it removes. The
+    /// row is updated instead of put so that the rating counters are kept, `None` for other
+    /// rows.
+    pub fn catalog_attributes(&self) -> Result<Option<(Attributes, Vec<&'static str>)>> {
+        match self.kind {
+            DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Meta { .. },
+            } => {}
+            _ => return Ok(None),
+        }
+        let mut attributes = self.to_item()?;
+        attributes.retain(|name, _| !is_rating_attribute(name));
+        let removed = MOVIE_META_OPTIONAL_ATTRIBUTES
+            .iter()
+            .filter(|name| !attributes.contains_key(**name))
+            .copied()
+            .collect();
+        Ok(Some((attributes, removed)))
+    }
+
+    /// Reads a stored item. Attributes unknown to this version are dropped or kept on the
+    /// item depending on the configuration, they never fail the read.
+    pub fn from_item(item: Attributes, unknown: UnknownAttributes) -> Result<Self> {
+        let (known, unknown_attributes): (Attributes, Attributes) = item
+            .into_iter()
+            .partition(|(name, _)| KNOWN_ATTRIBUTES.contains(&name.as_str()));
+        let mut parsed = serde_dynamodb::from_hashmap::<DynamoTableItem, _>(known)?;
+        if unknown == UnknownAttributes::Keep {
+            parsed.unknown = unknown_attributes;
+        }
+        Ok(parsed)
+    }
+
+    /// Builds the attributes written for the item, including the kept unknown attributes.
+    pub fn to_item(&self) -> Result<Attributes> {
+        let mut item: Attributes = serde_dynamodb::to_hashmap(self)?;
+        for (name, value) in &self.unknown {
+            item.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        Ok(item)
+    }
+
+    /// Whether the row was written with the name keys of the first version like
+    /// `movie::The Irishman#2019`, those rows can't be parsed and reads skip them.
+    pub fn has_legacy_key(item: &Attributes) -> bool {
+        let pk = match item.get("pk").and_then(|pk| pk.s.as_deref()) {
+            Some(pk) => pk,
+            None => return false,
+        };
+        [
+            DynamoTableRowKind::MovieMeta.get_prefixes().0,
+            DynamoTableRowKind::ActorMeta.get_prefixes().0,
+        ]
+        .iter()
+        .any(|prefix| pk.starts_with(prefix.as_str()) && Self::parse_id(pk, prefix).is_err())
+    }
+
+    fn unexpected(expected: &str, pk: &str, sk: &str) -> Error {
+        Error::CorruptItem(format!("item {}/{} is not a {}", pk, sk, expected))
+    }
+
+    fn parse_id(key: &str, prefix: &str) -> Result<Uuid> {
+        match decode_key(prefix, key, COMPONENT_SEPARATOR)?.as_slice() {
+            [id] => Uuid::parse_str(id)
+                .map_err(|_| Error::CorruptItem(format!("invalid id in key {}", key))),
+            _ => Err(Error::CorruptItem(format!("invalid id in key {}", key))),
+        }
+    }
+
+    fn parse_number(key: &str, component: &str) -> Result<i32> {
+        component
+            .parse()
+            .map_err(|_| Error::CorruptItem(format!("invalid number in key {}", key)))
+    }
+
+    /// Returns the season and episode numbers encoded in a key.
+    fn parse_numbers(key: &str, prefix: &str) -> Result<Vec<i32>> {
+        decode_key(prefix, key, COMPONENT_SEPARATOR)?
+            .iter()
+            .map(|component| Self::parse_number(key, component))
+            .collect()
+    }
+
+    /// Returns the series, season and episode number encoded in the partition key of
+    /// an episode.
+    fn parse_episode_pk(pk: &str) -> Result<(Uuid, i32, i32)> {
+        let prefix = DynamoTableRowKind::EpisodeActor.get_prefixes().0;
+        match decode_key(&prefix, pk, COMPONENT_SEPARATOR)?.as_slice() {
+            [series_id, season_number, episode_number] => Ok((
+                Uuid::parse_str(series_id)
+                    .map_err(|_| Error::CorruptItem(format!("invalid id in key {}", pk)))?,
+                Self::parse_number(pk, season_number)?,
+                Self::parse_number(pk, episode_number)?,
+            )),
+            _ => Err(Error::CorruptItem(format!("invalid episode key {}", pk))),
+        }
+    }
+
+    /// Builds the items written for a movie. Like all constructors the items carry the
+    /// version following the one of the given entities, so they replace the stored ones.
+    /// Only actors and characters that are not stored yet are written, existing ones are
+    /// left as they are. Every character played gets a row in its own partition.
+    pub fn new_movie(movie: &Movie) -> Vec<Self> {
+        let mut movie_actor_items = movie
+            .roles
+            .iter()
+            .map(|a| DynamoTableItem::new_movie_actor(&movie.meta, &a.actor.id, &a.credit))
+            .collect::<Vec<DynamoTableItem>>();
+        let mut movie_crew_items = movie
+            .crew
+            .iter()
+            .map(|c| DynamoTableItem::new_movie_crew(&movie.meta, c))
+            .collect::<Vec<DynamoTableItem>>();
+        let mut character_items = movie
+            .roles
+            .iter()
+            .flat_map(|a| {
+                DynamoTableItem::new_character_movies(&movie.meta, &a.actor.id, &a.credit)
+            })
+            .collect::<Vec<DynamoTableItem>>();
+        let mut actor_items = movie
+            .roles
+            .iter()
+            .filter(|a| a.actor.version == 0)
+            .map(|a| DynamoTableItem::new_actor(&a.actor))
+            .collect::<Vec<DynamoTableItem>>();
+        let mut new_character_items =
+            DynamoTableItem::new_characters(movie.roles.iter().map(|a| &a.credit));
+
+        let mut items = vec![DynamoTableItem::new_movie_metadata(&movie.meta)];
+        items.append(&mut movie_actor_items);
+        items.append(&mut movie_crew_items);
+        items.append(&mut character_items);
+        items.append(&mut actor_items);
+        items.append(&mut new_character_items);
+        items
+    }
+
+    pub fn new_movie_metadata(meta: &MovieMetadata) -> Self {
+        DynamoTableItem {
+            pk: Self::movie_pk(&meta.id),
+            sk: DynamoTableRowKind::MovieMeta.get_prefixes().1,
+            version: meta.version + 1,
+            kind: DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Meta {
+                    title: meta.title.clone(),
+                    published_at: meta.published_at,
+                    imdb_id: meta.imdb_id.clone(),
+                    genres: meta.genres.clone(),
+                    runtime_minutes: meta.runtime_minutes,
+                    original_language: meta.original_language.clone(),
+                    countries: meta.countries.clone(),
+                    synopsis: meta.synopsis.clone(),
+                    age_rating: meta.age_rating.clone(),
+                    directors: meta.directors.clone(),
+                    writers: meta.writers.clone(),
+                    poster_url: meta.poster_url.clone(),
+                    release_dates: meta.release_dates.clone(),
+                    ratings: meta.ratings.clone(),
+                },
+            },
+            unknown: meta.unknown.clone(),
+        }
+    }
+
+    pub fn new_movie_review(review: &Review) -> Self {
+        DynamoTableItem {
+            pk: Self::movie_pk(&review.movie_id),
+            sk: Self::movie_review_sk(&review.user_id),
+            version: review.version + 1,
+            kind: DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Review {
+                    rating: review.rating,
+                    text: review.text.clone(),
+                    reviewed_at: review.reviewed_at,
+                },
+            },
+            unknown: Attributes::new(),
+        }
+    }
+
+    /// Builds a role row, it carries the version of the movie it is written with.
+    pub fn new_movie_actor(meta: &MovieMetadata, actor_id: &Uuid, credit: &Credit) -> Self {
+        DynamoTableItem {
+            pk: Self::movie_pk(&meta.id),
+            sk: Self::movie_actor_sk(actor_id),
+            version: meta.version + 1,
+            kind: DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Actor {
+                    role: RoleItem::new(credit),
+                },
+            },
+            unknown: credit.unknown.clone(),
+        }
+    }
+
+    /// Builds a crew row, like role rows it carries the version of the movie.
+    pub fn new_movie_crew(meta: &MovieMetadata, credit: &CrewCredit) -> Self {
+        DynamoTableItem {
+            pk: Self::movie_pk(&meta.id),
+            sk: Self::movie_crew_sk(credit.department, &credit.name),
+            version: meta.version + 1,
+            kind: DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Crew {
+                    name: credit.name.clone(),
+                    department: credit.department,
+                    job: credit.job.clone(),
+                },
+            },
+            unknown: credit.unknown.clone(),
+        }
+    }
+
+    /// Builds the character rows of a role, like role rows they carry the version of the movie.
+    /// The rows have no data of their own, the stores carry the unknown attributes of the
+    /// stored rows over when they replace them.
+    pub fn new_character_movies(
+        meta: &MovieMetadata,
+        actor_id: &Uuid,
+        credit: &Credit,
+    ) -> Vec<Self> {
+        let sk = Self::character_movie_sk(&meta.id, actor_id);
+        Self::new_portrayals(credit, sk, meta.version + 1, CharacterKindItem::Movie)
+    }
+
+    /// Builds the character rows of an episode role, they carry the version of the series.
+    pub fn new_character_episodes(
+        series: &Series,
+        episode: &Episode,
+        actor_id: &Uuid,
+        credit: &Credit,
+    ) -> Vec<Self> {
+        let sk = Self::character_episode_sk(
+            &series.id,
+            episode.season_number,
+            episode.episode_number,
+            actor_id,
+        );
+        Self::new_portrayals(credit, sk, series.version, CharacterKindItem::Episode)
+    }
+
+    fn new_portrayals(
+        credit: &Credit,
+        sk: String,
+        version: i32,
+        kind: CharacterKindItem,
+    ) -> Vec<Self> {
+        let mut ids = credit
+            .characters
+            .iter()
+            .map(|c| c.id)
+            .collect::<Vec<Uuid>>();
+        ids.sort();
+        ids.dedup();
+        ids.iter()
+            .map(|id| DynamoTableItem {
+                pk: Self::character_pk(id),
+                sk: sk.clone(),
+                version,
+                kind: DynamoTableItemKind::Character { kind: kind.clone() },
+                unknown: Attributes::new(),
+            })
+            .collect()
+    }
+
+    pub fn new_character(character: &Character) -> Self {
+        DynamoTableItem {
+            pk: Self::character_pk(&character.id),
+            sk: DynamoTableRowKind::CharacterMeta.get_prefixes().1,
+            version: character.version + 1,
+            kind: DynamoTableItemKind::Character {
+                kind: CharacterKindItem::Meta {
+                    name: character.name.clone(),
+                },
+            },
+            unknown: Attributes::new(),
+        }
+    }
+
+    /// Builds the meta rows of the characters of the credits that are not stored yet, a
+    /// character several credits list is written once.
+    pub fn new_characters<'a>(credits: impl IntoIterator<Item = &'a Credit>) -> Vec<Self> {
+        let mut characters = credits
+            .into_iter()
+            .flat_map(|credit| &credit.characters)
+            .filter(|c| c.version == 0)
+            .collect::<Vec<&Character>>();
+        characters.sort_by_key(|c| c.id);
+        characters.dedup_by_key(|c| c.id);
+        characters.into_iter().map(Self::new_character).collect()
+    }
+
+    pub fn new_actor(actor: &Actor) -> Self {
+        DynamoTableItem {
+            pk: Self::actor_pk(&actor.id),
+            sk: DynamoTableRowKind::ActorMeta.get_prefixes().1,
+            version: actor.version + 1,
+            kind: DynamoTableItemKind::Actor {
+                kind: ActorKindItem::Meta {
+                    last_name: actor.last_name.clone(),
+                    first_name: actor.first_name.clone(),
+                    birth_date: actor.birth_date,
+                    imdb_id: actor.imdb_id.clone(),
+                    biography: actor.biography.clone(),
+                    aliases: actor.aliases.clone(),
+                },
+            },
+            unknown: actor.unknown.clone(),
+        }
+    }
+
+    pub fn new_collection(collection: &Collection) -> Self {
+        DynamoTableItem {
+            pk: Self::collection_pk(&collection.id),
+            sk: DynamoTableRowKind::CollectionMeta.get_prefixes().1,
+            version: collection.version + 1,
+            kind: DynamoTableItemKind::Collection {
+                kind: CollectionKindItem::Meta {
+                    name: collection.name.clone(),
+                },
+            },
+            unknown: collection.unknown.clone(),
+        }
+    }
+
+    /// Builds the row of a movie in a collection, it carries the version of the collection.
+    pub fn new_collection_movie(collection: &Collection, movie_id: &Uuid, position: i32) -> Self {
+        DynamoTableItem {
+            pk: Self::collection_pk(&collection.id),
+            sk: Self::collection_movie_sk(movie_id),
+            version: collection.version + 1,
+            kind: DynamoTableItemKind::Collection {
+                kind: CollectionKindItem::Movie { position },
+            },
+            unknown: Attributes::new(),
+        }
+    }
+
+    /// Builds the rows of the movies of a collection whose position changes when a movie is
+    /// added at or moved to a position. The movies from that position on move back and the
+    /// positions are renumbered from 1, so no two movies share a position. The stored movies
+    /// are given ordered by position.
+    pub fn new_collection_movies(
+        collection: &Collection,
+        stored: &[(Uuid, i32)],
+        movie_id: &Uuid,
+        position: i32,
+    ) -> Vec<Self> {
+        let mut movies = stored
+            .iter()
+            .filter(|(id, _)| id != movie_id)
+            .copied()
+            .collect::<Vec<(Uuid, i32)>>();
+        let previous = stored
+            .iter()
+            .find(|(id, _)| id == movie_id)
+            .map_or(0, |(_, position)| *position);
+        let index = ((position.max(1) - 1) as usize).min(movies.len());
+        movies.insert(index, (*movie_id, previous));
+        movies
+            .into_iter()
+            .zip(1..)
+            .filter(|((_, previous), position)| previous != position)
+            .map(|((id, _), position)| Self::new_collection_movie(collection, &id, position))
+            .collect()
+    }
+
+    pub fn new_series(series: &Series) -> Self {
+        DynamoTableItem {
+            pk: Self::series_pk(&series.id),
+            sk: DynamoTableRowKind::SeriesMeta.get_prefixes().1,
+            version: series.version + 1,
+            kind: DynamoTableItemKind::Series {
+                kind: SeriesKindItem::Meta {
+                    title: series.title.clone(),
+                    published_at: series.published_at,
+                },
+            },
+            unknown: series.unknown.clone(),
+        }
+    }
+
+    /// Builds the row of a season, it carries the version of the series.
+    pub fn new_season(series: &Series, season: &Season) -> Self {
+        DynamoTableItem {
+            pk: Self::series_pk(&series.id),
+            sk: Self::series_season_sk(season.number),
+            version: series.version + 1,
+            kind: DynamoTableItemKind::Series {
+                kind: SeriesKindItem::Season {
+                    title: season.title.clone(),
+                },
+            },
+            unknown: Attributes::new(),
+        }
+    }
+
+    /// Builds the items written for an episode: its row in the series partition, its roles
+    /// with their character rows and the actors and characters that are not stored yet.
+    /// The rows carry the version of the series, writing an episode does not change it.
+    pub fn new_episode(series: &Series, episode: &Episode, roles: &[Role]) -> Vec<Self> {
+        let mut items = vec![DynamoTableItem::new_series_episode(series, episode)];
+        items.extend(
+            roles.iter().map(|r| {
+                DynamoTableItem::new_episode_actor(series, episode, &r.actor.id, &r.credit)
+            }),
+        );
+        items.extend(roles.iter().flat_map(|r| {
+            DynamoTableItem::new_character_episodes(series, episode, &r.actor.id, &r.credit)
+        }));
+        items.extend(
+            roles
+                .iter()
+                .filter(|r| r.actor.version == 0)
+                .map(|r| DynamoTableItem::new_actor(&r.actor)),
+        );
+        items.extend(DynamoTableItem::new_characters(
+            roles.iter().map(|r| &r.credit),
+        ));
+        items
+    }
+
+    pub fn new_series_episode(series: &Series, episode: &Episode) -> Self {
+        DynamoTableItem {
+            pk: Self::series_pk(&series.id),
+            sk: Self::series_episode_sk(episode.season_number, episode.episode_number),
+            version: series.version,
+            kind: DynamoTableItemKind::Series {
+                kind: SeriesKindItem::Episode {
+                    title: episode.title.clone(),
+                    published_at: episode.published_at,
+                },
+            },
+            unknown: Attributes::new(),
+        }
+    }
+
+    /// Builds a role row of an episode, like the roles of movies its characters get rows
+    /// in their partitions.
+    pub fn new_episode_actor(
+        series: &Series,
+        episode: &Episode,
+        actor_id: &Uuid,
+        credit: &Credit,
+    ) -> Self {
+        DynamoTableItem {
+            pk: Self::episode_pk(&series.id, episode.season_number, episode.episode_number),
+            sk: Self::movie_actor_sk(actor_id),
+            version: series.version,
+            kind: DynamoTableItemKind::Episode {
+                kind: EpisodeKindItem::Actor {
+                    role: RoleItem::new(credit),
+                },
+            },
+            unknown: credit.unknown.clone(),
+        }
+    }
+
+    pub fn into_movie_metadata(self) -> Result<MovieMetadata> {
+        let id = Self::parse_id(&self.pk, &DynamoTableRowKind::MovieMeta.get_prefixes().0)?;
+        match self.kind {
+            DynamoTableItemKind::Movie {
+                kind:
+                    MovieKindItem::Meta {
+                        title,
+                        imdb_id,
+                        published_at,
+                        genres,
+                        runtime_minutes,
+                        original_language,
+                        countries,
+                        synopsis,
+                        age_rating,
+                        directors,
+                        writers,
+                        poster_url,
+                        release_dates,
+                        ratings,
+                    },
+            } => Ok(MovieMetadata {
+                id,
+                version: self.version,
+                title,
+                imdb_id,
+                published_at,
+                genres,
+                runtime_minutes,
+                original_language,
+                countries,
+                synopsis,
+                age_rating,
+                directors,
+                writers,
+                poster_url,
+                release_dates,
+                ratings,
+                unknown: self.unknown,
+            }),
+            _ => Err(Self::unexpected("movie metadata", &self.pk, &self.sk)),
+        }
+    }
+
+    pub fn into_review(self) -> Result<Review> {
+        let movie_id = Self::parse_id(&self.pk, &DynamoTableRowKind::MovieReview.get_prefixes().0)?;
+        let prefix = DynamoTableRowKind::MovieReview.get_prefixes().1;
+        match (
+            self.kind,
+            decode_key(&prefix, &self.sk, COMPONENT_SEPARATOR)?.as_slice(),
+        ) {
+            (
+                DynamoTableItemKind::Movie {
+                    kind:
+                        MovieKindItem::Review {
+                            rating,
+                            text,
+                            reviewed_at,
+                        },
+                },
+                [user_id],
+            ) => {
+                // the rating picks a counter of the histogram, it must not be out of range
+                if !(1..=MAX_RATING).contains(&rating) {
+                    return Err(Error::CorruptItem(format!(
+                        "invalid rating {} in item {}/{}",
+                        rating, self.pk, self.sk
+                    )));
+                }
+                Ok(Review {
+                    movie_id,
+                    user_id: user_id.clone(),
+                    version: self.version,
+                    rating,
+                    text,
+                    reviewed_at,
+                })
+            }
+            _ => Err(Self::unexpected("review", &self.pk, &self.sk)),
+        }
+    }
+
+    pub fn into_actor(self) -> Result<Actor> {
+        let id = Self::parse_id(&self.pk, &DynamoTableRowKind::ActorMeta.get_prefixes().0)?;
+        match self.kind {
+            DynamoTableItemKind::Actor {
+                kind:
+                    ActorKindItem::Meta {
+                        last_name,
+                        first_name,
+                        birth_date,
+                        imdb_id,
+                        biography,
+                        aliases,
+                    },
+            } => Ok(Actor {
+                id,
+                version: self.version,
+                last_name,
+                first_name,
+                birth_date,
+                imdb_id,
+                biography,
+                aliases,
+                unknown: self.unknown,
+            }),
+            _ => Err(Self::unexpected("actor", &self.pk, &self.sk)),
+        }
+    }
+
+    /// Returns the actor id encoded in the sort key of a movie actor row
+    /// together with the credit of the role.
+    pub fn into_movie_actor(self) -> Result<(Uuid, Credit)> {
+        let actor_id = Self::parse_id(&self.sk, &DynamoTableRowKind::MovieActor.get_prefixes().1)?;
+        match self.kind {
+            DynamoTableItemKind::Movie {
+                kind: MovieKindItem::Actor { role },
+            } => Ok((
+                actor_id,
+                Credit {
+                    unknown: self.unknown,
+                    ..role.into_credit()?
+                },
+            )),
+            _ => Err(Self::unexpected("movie role", &self.pk, &self.sk)),
+        }
+    }
+
+    /// Returns the movie id of a movie actor row together with the characters played.
+    /// Rows read through the inverted index only carry the characters of the credit.
+    pub fn into_actor_movie(self) -> Result<(Uuid, Vec<Character>)> {
+        let movie_id = Self::parse_id(&self.pk, &DynamoTableRowKind::MovieActor.get_prefixes().0)?;
+        let (_, credit) = self.into_movie_actor()?;
+        Ok((movie_id, credit.characters))
+    }
+
+    pub fn into_character(self) -> Result<Character> {
+        let id = Self::parse_id(
+            &self.pk,
+            &DynamoTableRowKind::CharacterMeta.get_prefixes().0,
+        )?;
+        match self.kind {
+            DynamoTableItemKind::Character {
+                kind: CharacterKindItem::Meta { name },
+            } => Ok(Character {
+                id,
+                version: self.version,
+                name,
+            }),
+            _ => Err(Self::unexpected("character", &self.pk, &self.sk)),
+        }
+    }
+
+    /// Returns the title and the actor id encoded in the sort key of a portrayal row.
+    pub fn into_character_portrayal(self) -> Result<(PortrayalTitle, Uuid)> {
+        let invalid = || Error::CorruptItem(format!("invalid key {}", self.sk));
+        let parse_id = |id: &str| Uuid::parse_str(id).map_err(|_| invalid());
+        match &self.kind {
+            DynamoTableItemKind::Character {
+                kind: CharacterKindItem::Movie,
+            } => {
+                let prefix = DynamoTableRowKind::CharacterMovie.get_prefixes().1;
+                match decode_key(&prefix, &self.sk, COMPONENT_SEPARATOR)?.as_slice() {
+                    [movie_id, actor_id] => Ok((
+                        PortrayalTitle::Movie(parse_id(movie_id)?),
+                        parse_id(actor_id)?,
+                    )),
+                    _ => Err(invalid()),
+                }
             }
-        } else if pk.starts_with(actor_pk) {
-            if sk.starts_with(actor_meta_sk) {
-                Ok(DynamoTableRowKind::ActorMeta)
-            } else {
-                Err("unknown".into())
+            DynamoTableItemKind::Character {
+                kind: CharacterKindItem::Episode,
+            } => {
+                let prefix = DynamoTableRowKind::CharacterEpisode.get_prefixes().1;
+                match decode_key(&prefix, &self.sk, COMPONENT_SEPARATOR)?.as_slice() {
+                    [series_id, season_number, episode_number, actor_id] => {
+                        let season_number = Self::parse_number(&self.sk, season_number)?;
+                        let episode_number = Self::parse_number(&self.sk, episode_number)?;
+                        Ok((
+                            PortrayalTitle::Episode((
+                                Self::series_pk(&parse_id(series_id)?),
+                                Self::series_episode_sk(season_number, episode_number),
+                            )),
+                            parse_id(actor_id)?,
+                        ))
+                    }
+                    _ => Err(invalid()),
+                }
             }
-        } else {
-            Err("unknown".into())
+            _ => Err(Self::unexpected("character portrayal", &self.pk, &self.sk)),
         }
     }
-}
 
-impl DynamoTableItem {
-    pub fn new_movie(movie: &Movie) -> Vec<Self> {
-        let movie_item = DynamoTableItem {
-            pk: format!(
-                "{}{}#{}",
-                DynamoTableRowKind::MovieMeta.get_prefixes().0,
-                movie.meta.title,
-                movie.meta.published_at.year()
-            )
-            .to_owned(),
-            sk: DynamoTableRowKind::MovieMeta.get_prefixes().1,
-            kind: DynamoTableItemKind::Movie {
-                kind: MovieKindItem::Meta {
-                    title: movie.meta.title.clone(),
-                    published_at: movie.meta.published_at,
-                    imdb_id: movie.meta.imdb_id.clone(),
-                },
-            },
+    pub fn into_collection(self) -> Result<Collection> {
+        let id = Self::parse_id(
+            &self.pk,
+            &DynamoTableRowKind::CollectionMeta.get_prefixes().0,
+        )?;
+        match self.kind {
+            DynamoTableItemKind::Collection {
+                kind: CollectionKindItem::Meta { name },
+            } => Ok(Collection {
+                id,
+                version: self.version,
+                name,
+                unknown: self.unknown,
+            }),
+            _ => Err(Self::unexpected("collection", &self.pk, &self.sk)),
+        }
+    }
+
+    /// Returns the movie id encoded in the sort key of a collection movie row together with
+    /// the position of the movie in the collection.
+    pub fn into_collection_movie(self) -> Result<(Uuid, i32)> {
+        let movie_id = Self::parse_id(
+            &self.sk,
+            &DynamoTableRowKind::CollectionMovie.get_prefixes().1,
+        )?;
+        match self.kind {
+            DynamoTableItemKind::Collection {
+                kind: CollectionKindItem::Movie { position },
+            } => Ok((movie_id, position)),
+            _ => Err(Self::unexpected("collection movie", &self.pk, &self.sk)),
+        }
+    }
+
+    /// Returns the collection id of a collection movie row read through the inverted index.
+    /// Only the keys are read, the index may project the row to its keys.
+    pub fn movie_collection_id(item: &Attributes) -> Result<Uuid> {
+        let key = |name: &str| {
+            item.get(name)
+                .and_then(|value| value.s.as_deref())
+                .ok_or_else(|| Error::CorruptItem(format!("missing field `{}`", name)))
         };
-        let mut movie_actor_items = movie
-            .roles
+        let (pk_prefix, sk_prefix) = DynamoTableRowKind::CollectionMovie.get_prefixes();
+        Self::parse_id(key("sk")?, &sk_prefix)?;
+        Self::parse_id(key("pk")?, &pk_prefix)
+    }
+
+    /// Keys of the collection movie rows of a movie read through the inverted index, built
+    /// from the keys of the rows.
+    pub fn membership_keys(movie_id: &Uuid, items: &[Attributes]) -> Result<Vec<(String, String)>> {
+        items
             .iter()
-            .map(|a| DynamoTableItem {
-                pk: format!(
-                    "{}{}#{}",
-                    DynamoTableRowKind::MovieActor.get_prefixes().0,
-                    movie.meta.title,
-                    movie.meta.published_at.year()
-                )
-                .to_owned(),
-                sk: format!(
-                    "{}{} {}",
-                    DynamoTableRowKind::MovieActor.get_prefixes().1,
-                    a.actor.last_name,
-                    a.actor.first_name
-                ),
-                kind: DynamoTableItemKind::Movie {
-                    kind: MovieKindItem::Actor {
-                        characters: a.characters.iter().map(|c| c.name.clone()).collect(),
+            .map(|item| {
+                let collection_id = Self::movie_collection_id(item)?;
+                Ok((
+                    Self::collection_pk(&collection_id),
+                    Self::collection_movie_sk(movie_id),
+                ))
+            })
+            .collect()
+    }
+
+    pub fn into_series(self) -> Result<Series> {
+        let id = Self::parse_id(&self.pk, &DynamoTableRowKind::SeriesMeta.get_prefixes().0)?;
+        match self.kind {
+            DynamoTableItemKind::Series {
+                kind:
+                    SeriesKindItem::Meta {
+                        title,
+                        published_at,
                     },
+            } => Ok(Series {
+                id,
+                version: self.version,
+                title,
+                published_at,
+                unknown: self.unknown,
+            }),
+            _ => Err(Self::unexpected("series", &self.pk, &self.sk)),
+        }
+    }
+
+    pub fn into_season(self) -> Result<Season> {
+        let series_id =
+            Self::parse_id(&self.pk, &DynamoTableRowKind::SeriesSeason.get_prefixes().0)?;
+        let numbers =
+            Self::parse_numbers(&self.sk, &DynamoTableRowKind::SeriesSeason.get_prefixes().1)?;
+        match (self.kind, numbers.as_slice()) {
+            (
+                DynamoTableItemKind::Series {
+                    kind: SeriesKindItem::Season { title },
                 },
-            })
-            .collect::<Vec<DynamoTableItem>>();
-        let mut actor_items = movie
-            .roles
-            .iter()
-            .map(|a| DynamoTableItem::new_actor(&a.actor))
-            .collect::<Vec<DynamoTableItem>>();
+                [number],
+            ) => Ok(Season {
+                series_id,
+                number: *number,
+                title,
+            }),
+            _ => Err(Self::unexpected("season", &self.pk, &self.sk)),
+        }
+    }
 
-        let mut items = vec![movie_item];
-        items.append(&mut movie_actor_items);
-        items.append(&mut actor_items);
-        items
+    pub fn into_episode(self) -> Result<Episode> {
+        let series_id = Self::parse_id(
+            &self.pk,
+            &DynamoTableRowKind::SeriesEpisode.get_prefixes().0,
+        )?;
+        let numbers = Self::parse_numbers(
+            &self.sk,
+            &DynamoTableRowKind::SeriesEpisode.get_prefixes().1,
+        )?;
+        match (self.kind, numbers.as_slice()) {
+            (
+                DynamoTableItemKind::Series {
+                    kind:
+                        SeriesKindItem::Episode {
+                            title,
+                            published_at,
+                        },
+                },
+                [season_number, episode_number],
+            ) => Ok(Episode {
+                series_id,
+                season_number: *season_number,
+                episode_number: *episode_number,
+                title,
+                published_at,
+            }),
+            _ => Err(Self::unexpected("episode", &self.pk, &self.sk)),
+        }
     }
 
-    pub fn new_actor(actor: &Actor) -> Self {
-        DynamoTableItem {
-            pk: format!(
-                "{}{} {}",
-                DynamoTableRowKind::ActorMeta.get_prefixes().0,
-                actor.last_name,
-                actor.first_name
-            ),
-            sk: DynamoTableRowKind::ActorMeta.get_prefixes().1,
-            kind: DynamoTableItemKind::Actor {
-                kind: ActorKindItem::Meta {
-                    last_name: actor.last_name.clone(),
-                    first_name: actor.first_name.clone(),
+    /// Returns the actor id encoded in the sort key of an episode actor row
+    /// together with the credit of the role.
+    pub fn into_episode_actor(self) -> Result<(Uuid, Credit)> {
+        let actor_id =
+            Self::parse_id(&self.sk, &DynamoTableRowKind::EpisodeActor.get_prefixes().1)?;
+        match self.kind {
+            DynamoTableItemKind::Episode {
+                kind: EpisodeKindItem::Actor { role },
+            } => Ok((
+                actor_id,
+                Credit {
+                    unknown: self.unknown,
+                    ..role.into_credit()?
                 },
-            },
+            )),
+            _ => Err(Self::unexpected("episode role", &self.pk, &self.sk)),
+        }
+    }
+
+    /// Returns the keys of the episode row of an episode actor row read through the inverted
+    /// index, together with the characters played.
+    pub fn into_actor_episode(self) -> Result<((String, String), Vec<Character>)> {
+        let (series_id, season_number, episode_number) = Self::parse_episode_pk(&self.pk)?;
+        let (_, credit) = self.into_episode_actor()?;
+        Ok((
+            (
+                Self::series_pk(&series_id),
+                Self::series_episode_sk(season_number, episode_number),
+            ),
+            credit.characters,
+        ))
+    }
+
+    pub fn into_crew_credit(self) -> Result<CrewCredit> {
+        match self.kind {
+            DynamoTableItemKind::Movie {
+                kind:
+                    MovieKindItem::Crew {
+                        name,
+                        department,
+                        job,
+                    },
+            } => Ok(CrewCredit {
+                name,
+                department,
+                job,
+                unknown: self.unknown,
+            }),
+            _ => Err(Self::unexpected("crew credit", &self.pk, &self.sk)),
         }
     }
 }
 
+/// Writes a counter, counters that are zero are left out of the item.
+fn serialize_counter<S: SerializeStruct>(
+    state: &mut S,
+    name: &'static str,
+    value: i32,
+) -> std::result::Result<(), S::Error> {
+    if value == 0 {
+        state.skip_field(name)
+    } else {
+        state.serialize_field(name, &value)
+    }
+}
+
+/// Writes an optional attribute, attributes without a value are left out of the item.
+fn serialize_optional<S: SerializeStruct, T: Serialize>(
+    state: &mut S,
+    name: &'static str,
+    value: &Option<T>,
+) -> std::result::Result<(), S::Error> {
+    match value {
+        Some(value) => state.serialize_field(name, value),
+        None => state.skip_field(name),
+    }
+}
+
 impl Serialize for DynamoTableItem {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -180,21 +1630,95 @@ impl Serialize for DynamoTableItem {
                     title,
                     imdb_id,
                     published_at,
+                    genres,
+                    runtime_minutes,
+                    original_language,
+                    countries,
+                    synopsis,
+                    age_rating,
+                    directors,
+                    writers,
+                    poster_url,
+                    release_dates,
+                    ratings,
                 } => {
-                    let mut state = serializer.serialize_struct("", 6)?;
+                    let mut state = serializer.serialize_struct("", 32)?;
                     state.serialize_field("pk", &self.pk)?;
                     state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.serialize_field("lookup", &self.lookup())?;
                     state.serialize_field("title", &title)?;
-                    state.serialize_field("imdb_id", &imdb_id)?;
+                    // index keys must not be null, the attribute is left out instead
+                    serialize_optional(&mut state, "imdb_id", imdb_id)?;
                     state.serialize_field("published_at", &published_at)?;
                     state.serialize_field("published_year", &published_at.year())?;
+                    // index keys must not be empty either, titles without letters or
+                    // digits are not searchable
+                    let search_title = normalize_title(title);
+                    match search_prefix(&search_title) {
+                        Some(prefix) => {
+                            state.serialize_field("search_prefix", &prefix)?;
+                            state.serialize_field("search_title", &search_title)?;
+                        }
+                        None => {
+                            state.skip_field("search_prefix")?;
+                            state.skip_field("search_title")?;
+                        }
+                    }
+                    state.serialize_field("genres", genres)?;
+                    serialize_optional(&mut state, "runtime_minutes", runtime_minutes)?;
+                    serialize_optional(&mut state, "original_language", original_language)?;
+                    state.serialize_field("countries", countries)?;
+                    serialize_optional(&mut state, "synopsis", synopsis)?;
+                    serialize_optional(&mut state, "age_rating", age_rating)?;
+                    state.serialize_field("directors", directors)?;
+                    state.serialize_field("writers", writers)?;
+                    serialize_optional(&mut state, "poster_url", poster_url)?;
+                    state.serialize_field("release_dates", release_dates)?;
+                    // movies without ratings have no counters, like movies written before
+                    // ratings were introduced
+                    serialize_counter(&mut state, "rating_count", ratings.count)?;
+                    serialize_counter(&mut state, "rating_sum", ratings.sum)?;
+                    for (name, count) in RATING_HISTOGRAM_ATTRIBUTES.iter().zip(&ratings.histogram)
+                    {
+                        serialize_counter(&mut state, name, *count)?;
+                    }
                     state.end()
                 }
-                MovieKindItem::Actor { characters } => {
-                    let mut state = serializer.serialize_struct("", 3)?;
+                MovieKindItem::Actor { role } => {
+                    let mut state = serializer.serialize_struct("", 9)?;
                     state.serialize_field("pk", &self.pk)?;
                     state.serialize_field("sk", &self.sk)?;
-                    state.serialize_field("characters", &characters)?;
+                    state.serialize_field("version", &self.version)?;
+                    role.serialize_fields(&mut state)?;
+                    state.end()
+                }
+                MovieKindItem::Crew {
+                    name,
+                    department,
+                    job,
+                } => {
+                    let mut state = serializer.serialize_struct("", 6)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.serialize_field("crew_name", name)?;
+                    state.serialize_field("department", department)?;
+                    serialize_optional(&mut state, "job", job)?;
+                    state.end()
+                }
+                MovieKindItem::Review {
+                    rating,
+                    text,
+                    reviewed_at,
+                } => {
+                    let mut state = serializer.serialize_struct("", 6)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.serialize_field("rating", rating)?;
+                    serialize_optional(&mut state, "review_text", text)?;
+                    state.serialize_field("reviewed_at", reviewed_at)?;
                     state.end()
                 }
             },
@@ -202,12 +1726,104 @@ impl Serialize for DynamoTableItem {
                 ActorKindItem::Meta {
                     last_name,
                     first_name,
+                    birth_date,
+                    imdb_id,
+                    biography,
+                    aliases,
                 } => {
-                    let mut state = serializer.serialize_struct("", 5)?;
+                    let mut state = serializer.serialize_struct("", 10)?;
                     state.serialize_field("pk", &self.pk)?;
                     state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.serialize_field("lookup", &self.lookup())?;
                     state.serialize_field("last_name", &last_name)?;
                     state.serialize_field("first_name", &first_name)?;
+                    serialize_optional(&mut state, "birth_date", birth_date)?;
+                    // not `imdb_id`, the IMDb id index only holds movies
+                    serialize_optional(&mut state, "actor_imdb_id", imdb_id)?;
+                    serialize_optional(&mut state, "biography", biography)?;
+                    state.serialize_field("aliases", &aliases)?;
+                    state.end()
+                }
+            },
+            DynamoTableItemKind::Character { kind } => match kind {
+                CharacterKindItem::Meta { name } => {
+                    let mut state = serializer.serialize_struct("", 5)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.serialize_field("lookup", &self.lookup())?;
+                    state.serialize_field("character_name", name)?;
+                    state.end()
+                }
+                CharacterKindItem::Movie | CharacterKindItem::Episode => {
+                    let mut state = serializer.serialize_struct("", 3)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.end()
+                }
+            },
+            DynamoTableItemKind::Collection { kind } => match kind {
+                CollectionKindItem::Meta { name } => {
+                    let mut state = serializer.serialize_struct("", 4)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.serialize_field("collection_name", name)?;
+                    state.end()
+                }
+                CollectionKindItem::Movie { position } => {
+                    let mut state = serializer.serialize_struct("", 4)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.serialize_field("position", position)?;
+                    state.end()
+                }
+            },
+            DynamoTableItemKind::Series { kind } => match kind {
+                SeriesKindItem::Meta {
+                    title,
+                    published_at,
+                } => {
+                    let mut state = serializer.serialize_struct("", 5)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    // not `title`, only movies are part of the title index
+                    state.serialize_field("series_title", title)?;
+                    state.serialize_field("published_at", published_at)?;
+                    state.end()
+                }
+                SeriesKindItem::Season { title } => {
+                    let mut state = serializer.serialize_struct("", 4)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    serialize_optional(&mut state, "season_title", title)?;
+                    state.end()
+                }
+                SeriesKindItem::Episode {
+                    title,
+                    published_at,
+                } => {
+                    let mut state = serializer.serialize_struct("", 5)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    state.serialize_field("episode_title", title)?;
+                    state.serialize_field("published_at", published_at)?;
+                    state.end()
+                }
+            },
+            DynamoTableItemKind::Episode { kind } => match kind {
+                EpisodeKindItem::Actor { role } => {
+                    let mut state = serializer.serialize_struct("", 9)?;
+                    state.serialize_field("pk", &self.pk)?;
+                    state.serialize_field("sk", &self.sk)?;
+                    state.serialize_field("version", &self.version)?;
+                    role.serialize_fields(&mut state)?;
                     state.end()
                 }
             },
@@ -225,95 +1841,239 @@ impl<'de> Deserialize<'de> for DynamoTableItem {
             type Value = DynamoTableItem;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("")
+                formatter.write_str("a movie, actor, character, collection or series item")
             }
 
             fn visit_map<V>(self, mut map: V) -> std::result::Result<DynamoTableItem, V::Error>
             where
                 V: MapAccess<'de>,
             {
-                let mut data = HashMap::<String, serde_json::Value>::new();
+                let mut pk = None;
+                let mut sk = None;
+                let mut version = None;
+                let mut title = None;
+                let mut imdb_id = None;
+                let mut published_at = None;
+                let mut characters = None;
+                let mut character_ids = None;
+                let mut character_name = None;
+                let mut last_name = None;
+                let mut first_name = None;
+                let mut birth_date = None;
+                let mut biography = None;
+                let mut actor_imdb_id = None;
+                let mut aliases = None;
+                let mut genres = None;
+                let mut runtime_minutes = None;
+                let mut original_language = None;
+                let mut countries = None;
+                let mut synopsis = None;
+                let mut age_rating = None;
+                let mut directors = None;
+                let mut writers = None;
+                let mut poster_url = None;
+                let mut release_dates = None;
+                let mut billing_order = None;
+                let mut credited_as = None;
+                let mut uncredited = None;
+                let mut voice = None;
+                let mut cameo = None;
+                let mut crew_name = None;
+                let mut department = None;
+                let mut job = None;
+                let mut collection_name = None;
+                let mut position = None;
+                let mut series_title = None;
+                let mut season_title = None;
+                let mut episode_title = None;
+                let mut ratings = RatingSummary::default();
+                let mut rating = None;
+                let mut review_text = None;
+                let mut reviewed_at = None;
                 while let Some(key) = map.next_key::<String>()? {
                     match key.as_ref() {
-                        "pk" | "sk" | "title" | "imdb_id" | "last_name" | "first_name" => {
-                            data.insert(
-                                key.to_owned(),
-                                serde_json::to_value(map.next_value::<String>()?).unwrap(),
-                            );
+                        "pk" => pk = Some(map.next_value::<String>()?),
+                        "sk" => sk = Some(map.next_value::<String>()?),
+                        "version" => version = Some(map.next_value::<i32>()?),
+                        "title" => title = Some(map.next_value::<String>()?),
+                        "imdb_id" => imdb_id = map.next_value::<Option<String>>()?,
+                        "published_at" => published_at = Some(map.next_value::<DateTime<Utc>>()?),
+                        "characters" => characters = Some(map.next_value::<Vec<String>>()?),
+                        "character_ids" => character_ids = Some(map.next_value::<Vec<String>>()?),
+                        "character_name" => character_name = Some(map.next_value::<String>()?),
+                        "last_name" => last_name = Some(map.next_value::<String>()?),
+                        "first_name" => first_name = Some(map.next_value::<String>()?),
+                        "birth_date" => birth_date = map.next_value::<Option<NaiveDate>>()?,
+                        "biography" => biography = map.next_value::<Option<String>>()?,
+                        "actor_imdb_id" => actor_imdb_id = map.next_value::<Option<String>>()?,
+                        "aliases" => aliases = Some(map.next_value::<Vec<String>>()?),
+                        "genres" => genres = Some(map.next_value::<Vec<String>>()?),
+                        "runtime_minutes" => runtime_minutes = map.next_value::<Option<i32>>()?,
+                        "original_language" => {
+                            original_language = map.next_value::<Option<String>>()?
                         }
-                        "characters" => {
-                            data.insert(
-                                key.to_owned(),
-                                serde_json::to_value(map.next_value::<Vec<String>>()?).unwrap(),
-                            );
+                        "countries" => countries = Some(map.next_value::<Vec<String>>()?),
+                        "synopsis" => synopsis = map.next_value::<Option<String>>()?,
+                        "age_rating" => age_rating = map.next_value::<Option<String>>()?,
+                        "directors" => directors = Some(map.next_value::<Vec<String>>()?),
+                        "writers" => writers = Some(map.next_value::<Vec<String>>()?),
+                        "poster_url" => poster_url = map.next_value::<Option<String>>()?,
+                        "release_dates" => {
+                            release_dates = Some(map.next_value::<Vec<ReleaseDate>>()?)
                         }
-                        "published_at" => {
-                            data.insert(
-                                key.to_owned(),
-                                serde_json::to_value(map.next_value::<DateTime<Utc>>()?).unwrap(),
-                            );
-                        }
-                        "published_year" => {
-                            map.next_value::<i32>()?;
-                        }
-                        _ => return Err(serde::de::Error::unknown_field(key.as_ref(), &[])),
+                        "billing_order" => billing_order = map.next_value::<Option<i32>>()?,
+                        "credited_as" => credited_as = map.next_value::<Option<String>>()?,
+                        "uncredited" => uncredited = Some(map.next_value::<bool>()?),
+                        "voice" => voice = Some(map.next_value::<bool>()?),
+                        "cameo" => cameo = Some(map.next_value::<bool>()?),
+                        "crew_name" => crew_name = Some(map.next_value::<String>()?),
+                        "department" => department = Some(map.next_value::<Department>()?),
+                        "job" => job = map.next_value::<Option<String>>()?,
+                        "collection_name" => collection_name = Some(map.next_value::<String>()?),
+                        "position" => position = Some(map.next_value::<i32>()?),
+                        "series_title" => series_title = Some(map.next_value::<String>()?),
+                        "season_title" => season_title = map.next_value::<Option<String>>()?,
+                        "episode_title" => episode_title = Some(map.next_value::<String>()?),
+                        "rating_count" => ratings.count = map.next_value::<i32>()?,
+                        "rating_sum" => ratings.sum = map.next_value::<i32>()?,
+                        "rating" => rating = Some(map.next_value::<i32>()?),
+                        "review_text" => review_text = map.next_value::<Option<String>>()?,
+                        "reviewed_at" => reviewed_at = Some(map.next_value::<DateTime<Utc>>()?),
+                        name => match RATING_HISTOGRAM_ATTRIBUTES.iter().position(|a| *a == name) {
+                            Some(i) => ratings.histogram[i] = map.next_value::<i32>()?,
+                            // derived from the other attributes, and attributes of other versions
+                            None => {
+                                map.next_value::<IgnoredAny>()?;
+                            }
+                        },
                     }
                 }
-                let pk = data.get("pk").unwrap().as_str().unwrap().to_owned();
-                let sk = data.get("sk").unwrap().as_str().unwrap().to_owned();
-                let kind = DynamoTableRowKind::get_row_kind_by_keys(&pk, &sk).unwrap();
-                match kind {
-                    DynamoTableRowKind::MovieMeta => Ok(DynamoTableItem {
-                        pk,
-                        sk,
-                        kind: DynamoTableItemKind::Movie {
-                            kind: MovieKindItem::Meta {
-                                title: data.get("title").unwrap().as_str().unwrap().to_owned(),
-                                imdb_id: data
-                                    .get("imdb_id")
-                                    .unwrap()
-                                    .as_str()
-                                    .mutate(|x| Some(x.to_owned())),
-                                published_at: serde_json::from_value(
-                                    data.get("published_at").unwrap().clone(),
-                                )
-                                .unwrap(),
-                            },
+                let pk = pk.ok_or_else(|| de::Error::missing_field("pk"))?;
+                let sk = sk.ok_or_else(|| de::Error::missing_field("sk"))?;
+                // roles written before credits were introduced are plain roles
+                let role = move || -> std::result::Result<RoleItem, V::Error> {
+                    Ok(RoleItem {
+                        characters: characters
+                            .ok_or_else(|| de::Error::missing_field("characters"))?,
+                        character_ids: character_ids.unwrap_or_default(),
+                        billing_order,
+                        credited_as,
+                        uncredited: uncredited.unwrap_or_default(),
+                        voice: voice.unwrap_or_default(),
+                        cameo: cameo.unwrap_or_default(),
+                    })
+                };
+                let kind = match DynamoTableRowKind::get_row_kind_by_keys(&pk, &sk)
+                    .map_err(de::Error::custom)?
+                {
+                    DynamoTableRowKind::MovieMeta => DynamoTableItemKind::Movie {
+                        kind: MovieKindItem::Meta {
+                            title: title.ok_or_else(|| de::Error::missing_field("title"))?,
+                            imdb_id,
+                            published_at: published_at
+                                .ok_or_else(|| de::Error::missing_field("published_at"))?,
+                            // movies written before these were introduced have none
+                            genres: genres.unwrap_or_default(),
+                            runtime_minutes,
+                            original_language,
+                            countries: countries.unwrap_or_default(),
+                            synopsis,
+                            age_rating,
+                            directors: directors.unwrap_or_default(),
+                            writers: writers.unwrap_or_default(),
+                            poster_url,
+                            release_dates: release_dates.unwrap_or_default(),
+                            ratings,
+                        },
+                    },
+                    DynamoTableRowKind::MovieActor => DynamoTableItemKind::Movie {
+                        kind: MovieKindItem::Actor { role: role()? },
+                    },
+                    DynamoTableRowKind::MovieCrew => DynamoTableItemKind::Movie {
+                        kind: MovieKindItem::Crew {
+                            name: crew_name.ok_or_else(|| de::Error::missing_field("crew_name"))?,
+                            department: department
+                                .ok_or_else(|| de::Error::missing_field("department"))?,
+                            job,
+                        },
+                    },
+                    DynamoTableRowKind::MovieReview => DynamoTableItemKind::Movie {
+                        kind: MovieKindItem::Review {
+                            rating: rating.ok_or_else(|| de::Error::missing_field("rating"))?,
+                            text: review_text,
+                            reviewed_at: reviewed_at
+                                .ok_or_else(|| de::Error::missing_field("reviewed_at"))?,
                         },
-                    }),
-                    DynamoTableRowKind::MovieActor => Ok(DynamoTableItem {
-                        pk,
-                        sk,
-                        kind: DynamoTableItemKind::Movie {
-                            kind: MovieKindItem::Actor {
-                                characters: serde_json::from_value(
-                                    data.get("characters").unwrap().clone(),
-                                )
-                                .unwrap(),
-                            },
+                    },
+                    DynamoTableRowKind::ActorMeta => DynamoTableItemKind::Actor {
+                        kind: ActorKindItem::Meta {
+                            last_name: last_name
+                                .ok_or_else(|| de::Error::missing_field("last_name"))?,
+                            first_name: first_name
+                                .ok_or_else(|| de::Error::missing_field("first_name"))?,
+                            birth_date,
+                            imdb_id: actor_imdb_id,
+                            biography,
+                            // actors written before aliases were introduced have none
+                            aliases: aliases.unwrap_or_default(),
                         },
-                    }),
-                    DynamoTableRowKind::ActorMeta => Ok(DynamoTableItem {
-                        pk,
-                        sk,
-                        kind: DynamoTableItemKind::Actor {
-                            kind: ActorKindItem::Meta {
-                                last_name: data
-                                    .get("last_name")
-                                    .unwrap()
-                                    .as_str()
-                                    .unwrap()
-                                    .to_owned(),
-                                first_name: data
-                                    .get("first_name")
-                                    .unwrap()
-                                    .as_str()
-                                    .unwrap()
-                                    .to_owned(),
-                            },
+                    },
+                    DynamoTableRowKind::CharacterMeta => DynamoTableItemKind::Character {
+                        kind: CharacterKindItem::Meta {
+                            name: character_name
+                                .ok_or_else(|| de::Error::missing_field("character_name"))?,
                         },
-                    }),
-                }
+                    },
+                    DynamoTableRowKind::CharacterMovie => DynamoTableItemKind::Character {
+                        kind: CharacterKindItem::Movie,
+                    },
+                    DynamoTableRowKind::CharacterEpisode => DynamoTableItemKind::Character {
+                        kind: CharacterKindItem::Episode,
+                    },
+                    DynamoTableRowKind::CollectionMeta => DynamoTableItemKind::Collection {
+                        kind: CollectionKindItem::Meta {
+                            name: collection_name
+                                .ok_or_else(|| de::Error::missing_field("collection_name"))?,
+                        },
+                    },
+                    DynamoTableRowKind::CollectionMovie => DynamoTableItemKind::Collection {
+                        kind: CollectionKindItem::Movie {
+                            position: position
+                                .ok_or_else(|| de::Error::missing_field("position"))?,
+                        },
+                    },
+                    DynamoTableRowKind::SeriesMeta => DynamoTableItemKind::Series {
+                        kind: SeriesKindItem::Meta {
+                            title: series_title
+                                .ok_or_else(|| de::Error::missing_field("series_title"))?,
+                            published_at: published_at
+                                .ok_or_else(|| de::Error::missing_field("published_at"))?,
+                        },
+                    },
+                    DynamoTableRowKind::SeriesSeason => DynamoTableItemKind::Series {
+                        kind: SeriesKindItem::Season {
+                            title: season_title,
+                        },
+                    },
+                    DynamoTableRowKind::SeriesEpisode => DynamoTableItemKind::Series {
+                        kind: SeriesKindItem::Episode {
+                            title: episode_title
+                                .ok_or_else(|| de::Error::missing_field("episode_title"))?,
+                            published_at: published_at
+                                .ok_or_else(|| de::Error::missing_field("published_at"))?,
+                        },
+                    },
+                    DynamoTableRowKind::EpisodeActor => DynamoTableItemKind::Episode {
+                        kind: EpisodeKindItem::Actor { role: role()? },
+                    },
+                };
+                Ok(DynamoTableItem {
+                    pk,
+                    sk,
+                    version: version.unwrap_or_default(),
+                    kind,
+                    unknown: Attributes::new(),
+                })
             }
         }
         deserializer.deserialize_struct("", &[], ItemVisitor {})
@@ -322,36 +2082,103 @@ impl<'de> Deserialize<'de> for DynamoTableItem {
 
 #[cfg(test)]
 mod tests {
-    use crate::dynamo::DynamoTableItem;
-    use chrono::{DateTime, Utc};
+    use crate::config::UnknownAttributes;
+    use crate::data::ReleaseDate;
+    use crate::dynamo::{Attributes, DynamoTableItem, PortrayalTitle};
+    use chrono::{DateTime, NaiveDate, Utc};
+    use rusoto_dynamodb::AttributeValue;
     use std::str::FromStr;
+    use uuid::Uuid;
+
+    fn s(value: &str) -> AttributeValue {
+        AttributeValue {
+            s: Some(value.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn movie_meta_item() -> Attributes {
+        map! {
+            "pk".to_owned() => s("movie::00000000-0000-0000-0000-000000000001"),
+            "sk".to_owned() => s("meta"),
+            "title".to_owned() => s("Heat"),
+            "published_at".to_owned() => s("1995-12-15T00:00:00Z"),
+            "popularity".to_owned() => AttributeValue {
+                n: Some("5".to_owned()),
+                ..Default::default()
+            }
+        }
+    }
 
     #[test]
     fn test_dynamotableitem_serialization() {
         let movie = crate::data::Movie {
             meta: crate::data::MovieMetadata {
+                id: Uuid::from_u128(1),
+                version: 0,
                 title: "The Irishman".to_owned(),
                 imdb_id: Some("tt1302006".to_owned()),
                 published_at: DateTime::<Utc>::from_str("2019-09-27T00:00:00Z").unwrap(),
+                genres: vec!["Crime".to_owned(), "Drama".to_owned()],
+                runtime_minutes: Some(209),
+                directors: vec!["Martin Scorsese".to_owned()],
+                original_language: None,
+                countries: vec![],
+                synopsis: None,
+                age_rating: None,
+                writers: vec![],
+                poster_url: None,
+                release_dates: vec![ReleaseDate {
+                    country: "DE".to_owned(),
+                    released_on: NaiveDate::from_ymd(2019, 11, 14),
+                }],
+                ratings: Default::default(),
+                unknown: Default::default(),
             },
+            crew: vec![crate::data::CrewCredit {
+                name: "Martin Scorsese".to_owned(),
+                department: crate::data::Department::Directing,
+                job: None,
+                unknown: Attributes::new(),
+            }],
             roles: vec![
                 crate::data::Role {
                     actor: crate::data::Actor {
+                        id: Uuid::from_u128(2),
+                        version: 0,
                         last_name: "Deniro".to_owned(),
                         first_name: "Robert".to_owned(),
+                        ..Default::default()
+                    },
+                    credit: crate::data::Credit {
+                        characters: vec![crate::data::Character {
+                            id: Uuid::from_u128(101),
+                            version: 0,
+                            name: "Frank Sheeran".to_owned(),
+                        }],
+                        billing_order: Some(1),
+                        ..Default::default()
                     },
-                    characters: vec![crate::data::Character {
-                        name: "Frank Sheeran".to_owned(),
-                    }],
                 },
                 crate::data::Role {
                     actor: crate::data::Actor {
+                        id: Uuid::from_u128(3),
+                        version: 0,
                         last_name: "Pacino".to_owned(),
                         first_name: "Al".to_owned(),
+                        birth_date: Some(NaiveDate::from_ymd(1940, 4, 25)),
+                        imdb_id: Some("nm0000199".to_owned()),
+                        aliases: vec!["Alfredo James Pacino".to_owned()],
+                        ..Default::default()
+                    },
+                    credit: crate::data::Credit {
+                        characters: vec![crate::data::Character {
+                            id: Uuid::from_u128(102),
+                            version: 0,
+                            name: "Jimmy Hoffa".to_owned(),
+                        }],
+                        ..Default::default()
                     },
-                    characters: vec![crate::data::Character {
-                        name: "Jimmy Hoffa".to_owned(),
-                    }],
                 },
             ],
         };
@@ -361,33 +2188,532 @@ mod tests {
             .map(|x| serde_json::to_string(&x).unwrap())
             .collect::<Vec<String>>();
         let expected_table_items = vec![
-            r#"{"pk":"movie::The Irishman#2019","sk":"meta","title":"The Irishman","imdb_id":"tt1302006","published_at":"2019-09-27T00:00:00Z","published_year":2019}"#,
-            r#"{"pk":"movie::The Irishman#2019","sk":"actor::Deniro Robert","characters":["Frank Sheeran"]}"#,
-            r#"{"pk":"movie::The Irishman#2019","sk":"actor::Pacino Al","characters":["Jimmy Hoffa"]}"#,
-            r#"{"pk":"actor::Deniro Robert","sk":"meta","last_name":"Deniro","first_name":"Robert"}"#,
-            r#"{"pk":"actor::Pacino Al","sk":"meta","last_name":"Pacino","first_name":"Al"}"#,
+            r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"meta","version":1,"lookup":"movie::The Irishman#2019","title":"The Irishman","imdb_id":"tt1302006","published_at":"2019-09-27T00:00:00Z","published_year":2019,"search_prefix":"i","search_title":"irishman","genres":["Crime","Drama"],"runtime_minutes":209,"countries":[],"directors":["Martin Scorsese"],"writers":[],"release_dates":[{"country":"DE","released_on":"2019-11-14"}]}"#,
+            r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"actor::00000000-0000-0000-0000-000000000002","version":1,"characters":["Frank Sheeran"],"character_ids":["00000000-0000-0000-0000-000000000065"],"billing_order":1,"uncredited":false,"voice":false,"cameo":false}"#,
+            r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"actor::00000000-0000-0000-0000-000000000003","version":1,"characters":["Jimmy Hoffa"],"character_ids":["00000000-0000-0000-0000-000000000066"],"uncredited":false,"voice":false,"cameo":false}"#,
+            r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"crew::directing#Martin Scorsese","version":1,"crew_name":"Martin Scorsese","department":"directing"}"#,
+            r#"{"pk":"character::00000000-0000-0000-0000-000000000065","sk":"portrayal::movie::00000000-0000-0000-0000-000000000001#00000000-0000-0000-0000-000000000002","version":1}"#,
+            r#"{"pk":"character::00000000-0000-0000-0000-000000000066","sk":"portrayal::movie::00000000-0000-0000-0000-000000000001#00000000-0000-0000-0000-000000000003","version":1}"#,
+            r#"{"pk":"actor::00000000-0000-0000-0000-000000000002","sk":"meta","version":1,"lookup":"actor::Deniro Robert","last_name":"Deniro","first_name":"Robert","aliases":[]}"#,
+            r#"{"pk":"actor::00000000-0000-0000-0000-000000000003","sk":"meta","version":1,"lookup":"actor::Pacino Al","last_name":"Pacino","first_name":"Al","birth_date":"1940-04-25","actor_imdb_id":"nm0000199","aliases":["Alfredo James Pacino"]}"#,
+            r#"{"pk":"character::00000000-0000-0000-0000-000000000065","sk":"meta","version":1,"lookup":"character::Frank Sheeran","character_name":"Frank Sheeran"}"#,
+            r#"{"pk":"character::00000000-0000-0000-0000-000000000066","sk":"meta","version":1,"lookup":"character::Jimmy Hoffa","character_name":"Jimmy Hoffa"}"#,
         ];
         assert_eq!(expected_table_items, table_items);
+
+        let item = DynamoTableItem::new_movie_metadata(&movie.meta)
+            .to_item()
+            .unwrap();
+        let meta = DynamoTableItem::from_item(item, UnknownAttributes::Keep)
+            .unwrap()
+            .into_movie_metadata()
+            .unwrap();
+        assert_eq!(Some(209), meta.runtime_minutes);
+        assert_eq!(movie.meta.release_dates, meta.release_dates);
     }
 
     #[test]
     fn test_deserialize_movie_meta() {
-        let data = r#"{"pk":"movie::The Irishman#2019","sk":"meta","title":"The Irishman","imdb_id":"tt1302006","published_at":"2019-09-27T00:00:00Z","published_year":2019}"#;
+        let data = r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"meta","lookup":"movie::The Irishman#2019","title":"The Irishman","imdb_id":"tt1302006","published_at":"2019-09-27T00:00:00Z","published_year":2019}"#;
         let item: DynamoTableItem = serde_json::from_str(data).unwrap();
-        println!("{:?}", item);
+        let meta = item.into_movie_metadata().unwrap();
+        assert_eq!(Uuid::from_u128(1), meta.id);
+        assert_eq!("The Irishman", meta.title);
+        // written before the optional fields were introduced
+        assert!(meta.genres.is_empty());
+        assert_eq!(None, meta.runtime_minutes);
+        assert!(meta.release_dates.is_empty());
     }
 
     #[test]
     fn test_deserialize_movie_actor() {
-        let data = r#"{"pk":"movie::The Irishman#2019","sk":"actor::Deniro Robert","characters":["Frank Sheeran"]}"#;
+        let data = r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"actor::00000000-0000-0000-0000-000000000002","characters":["Frank Sheeran"],"character_ids":["00000000-0000-0000-0000-000000000003"]}"#;
+        let item: DynamoTableItem = serde_json::from_str(data).unwrap();
+        let (actor_id, credit) = serde_json::from_str::<DynamoTableItem>(data)
+            .unwrap()
+            .into_movie_actor()
+            .unwrap();
+        assert_eq!(Uuid::from_u128(2), actor_id);
+        // written before credits had flags
+        assert_eq!(None, credit.billing_order);
+        assert!(!credit.uncredited);
+        let (movie_id, characters) = item.into_actor_movie().unwrap();
+        assert_eq!(Uuid::from_u128(1), movie_id);
+        assert_eq!("Frank Sheeran", characters[0].name);
+    }
+
+    #[test]
+    fn test_deserialize_role_without_character_ids() {
+        // written before characters were stored on their own
+        let data = r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"actor::00000000-0000-0000-0000-000000000002","characters":["Frank Sheeran"]}"#;
+        let item: DynamoTableItem = serde_json::from_str(data).unwrap();
+        assert!(item.character_keys().unwrap().is_empty());
+        let (_, credit) = item.into_movie_actor().unwrap();
+        assert_eq!("Frank Sheeran", credit.characters[0].name);
+        assert!(!credit.characters[0].is_linked());
+    }
+
+    #[test]
+    fn test_deserialize_character_portrayal() {
+        let data = r#"{"pk":"character::00000000-0000-0000-0000-000000000003","sk":"portrayal::movie::00000000-0000-0000-0000-000000000001#00000000-0000-0000-0000-000000000002","version":1}"#;
+        let item: DynamoTableItem = serde_json::from_str(data).unwrap();
+        let (title, actor_id) = item.into_character_portrayal().unwrap();
+        assert_eq!(PortrayalTitle::Movie(Uuid::from_u128(1)), title);
+        assert_eq!(Uuid::from_u128(2), actor_id);
+
+        let data = r#"{"pk":"character::00000000-0000-0000-0000-000000000003","sk":"portrayal::episode::00000000-0000-0000-0000-000000000001#0001#0012#00000000-0000-0000-0000-000000000002","version":1}"#;
         let item: DynamoTableItem = serde_json::from_str(data).unwrap();
-        println!("{:?}", item);
+        let (title, _) = item.into_character_portrayal().unwrap();
+        assert_eq!(
+            PortrayalTitle::Episode((
+                DynamoTableItem::series_pk(&Uuid::from_u128(1)),
+                DynamoTableItem::series_episode_sk(1, 12)
+            )),
+            title
+        );
+
+        let role = r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"actor::00000000-0000-0000-0000-000000000002","characters":["Michael Corleone","Michael Corleone"],"character_ids":["00000000-0000-0000-0000-000000000003","00000000-0000-0000-0000-000000000003"]}"#;
+        let keys = serde_json::from_str::<DynamoTableItem>(role)
+            .unwrap()
+            .character_keys()
+            .unwrap();
+        assert_eq!(
+            vec![(
+                "character::00000000-0000-0000-0000-000000000003".to_owned(),
+                "portrayal::movie::00000000-0000-0000-0000-000000000001#00000000-0000-0000-0000-000000000002"
+                    .to_owned()
+            )],
+            keys
+        );
+    }
+
+    #[test]
+    fn test_collection_serialization() {
+        let collection = crate::data::Collection {
+            id: Uuid::from_u128(5),
+            version: 1,
+            name: "The Godfather Trilogy".to_owned(),
+            ..Default::default()
+        };
+        let items = [
+            DynamoTableItem::new_collection(&collection),
+            DynamoTableItem::new_collection_movie(&collection, &Uuid::from_u128(1), 2),
+        ]
+        .iter()
+        .map(|x| serde_json::to_string(&x).unwrap())
+        .collect::<Vec<String>>();
+        assert_eq!(
+            vec![
+                r#"{"pk":"collection::00000000-0000-0000-0000-000000000005","sk":"meta","version":2,"collection_name":"The Godfather Trilogy"}"#,
+                r#"{"pk":"collection::00000000-0000-0000-0000-000000000005","sk":"movie::00000000-0000-0000-0000-000000000001","version":2,"position":2}"#,
+            ],
+            items
+        );
+
+        let mut item = DynamoTableItem::new_collection_movie(&collection, &Uuid::from_u128(1), 2)
+            .to_item()
+            .unwrap();
+        // the inverted index projects the row to its keys
+        item.retain(|name, _| name == "pk" || name == "sk");
+        assert_eq!(
+            Uuid::from_u128(5),
+            DynamoTableItem::movie_collection_id(&item).unwrap()
+        );
+        let item: DynamoTableItem = serde_json::from_str(&items[1]).unwrap();
+        assert_eq!(
+            (Uuid::from_u128(1), 2),
+            item.into_collection_movie().unwrap()
+        );
+        // positions stored tied are renumbered when a movie is added
+        let stored = [
+            (Uuid::from_u128(1), 1),
+            (Uuid::from_u128(2), 1),
+            (Uuid::from_u128(3), 2),
+        ];
+        let moved =
+            DynamoTableItem::new_collection_movies(&collection, &stored, &Uuid::from_u128(4), 2)
+                .into_iter()
+                .map(|item| item.into_collection_movie().unwrap())
+                .collect::<Vec<(Uuid, i32)>>();
+        assert_eq!(
+            vec![
+                (Uuid::from_u128(4), 2),
+                (Uuid::from_u128(2), 3),
+                (Uuid::from_u128(3), 4)
+            ],
+            moved
+        );
+
+        let item: DynamoTableItem = serde_json::from_str(&items[0]).unwrap();
+        let stored = item.into_collection().unwrap();
+        assert_eq!(2, stored.version);
+        assert_eq!(collection.name, stored.name);
+    }
+
+    #[test]
+    fn test_series_serialization() {
+        let series = crate::data::Series {
+            id: Uuid::from_u128(7),
+            version: 1,
+            title: "The Sopranos".to_owned(),
+            published_at: DateTime::<Utc>::from_str("1999-01-10T00:00:00Z").unwrap(),
+            unknown: Attributes::new(),
+        };
+        let season = crate::data::Season {
+            series_id: series.id,
+            number: 1,
+            title: None,
+        };
+        let episode = crate::data::Episode {
+            series_id: series.id,
+            season_number: 1,
+            episode_number: 12,
+            title: "Isabella".to_owned(),
+            published_at: DateTime::<Utc>::from_str("1999-03-28T00:00:00Z").unwrap(),
+        };
+        let credit = crate::data::Credit {
+            characters: vec![crate::data::Character {
+                id: Uuid::from_u128(103),
+                version: 1,
+                name: "Tony Soprano".to_owned(),
+            }],
+            billing_order: Some(1),
+            ..Default::default()
+        };
+        let items = [
+            DynamoTableItem::new_series(&series),
+            DynamoTableItem::new_season(&series, &season),
+            DynamoTableItem::new_series_episode(&series, &episode),
+            DynamoTableItem::new_episode_actor(&series, &episode, &Uuid::from_u128(2), &credit),
+        ]
+        .iter()
+        .map(|x| serde_json::to_string(&x).unwrap())
+        .collect::<Vec<String>>();
+        assert_eq!(
+            vec![
+                r#"{"pk":"series::00000000-0000-0000-0000-000000000007","sk":"meta","version":2,"series_title":"The Sopranos","published_at":"1999-01-10T00:00:00Z"}"#,
+                r#"{"pk":"series::00000000-0000-0000-0000-000000000007","sk":"season::0001","version":2}"#,
+                r#"{"pk":"series::00000000-0000-0000-0000-000000000007","sk":"episode::0001#0012","version":1,"episode_title":"Isabella","published_at":"1999-03-28T00:00:00Z"}"#,
+                r#"{"pk":"episode::00000000-0000-0000-0000-000000000007#0001#0012","sk":"actor::00000000-0000-0000-0000-000000000002","version":1,"characters":["Tony Soprano"],"character_ids":["00000000-0000-0000-0000-000000000067"],"billing_order":1,"uncredited":false,"voice":false,"cameo":false}"#,
+            ],
+            items
+        );
+
+        let item: DynamoTableItem = serde_json::from_str(&items[0]).unwrap();
+        assert_eq!(series.title, item.into_series().unwrap().title);
+        let item: DynamoTableItem = serde_json::from_str(&items[1]).unwrap();
+        assert_eq!(season, item.into_season().unwrap());
+        let item: DynamoTableItem = serde_json::from_str(&items[2]).unwrap();
+        let stored = item.into_episode().unwrap();
+        assert_eq!((1, 12), (stored.season_number, stored.episode_number));
+        let item: DynamoTableItem = serde_json::from_str(&items[3]).unwrap();
+        assert_eq!(
+            (Uuid::from_u128(2), credit.clone()),
+            item.into_episode_actor().unwrap()
+        );
+        let item: DynamoTableItem = serde_json::from_str(&items[3]).unwrap();
+        let (key, characters) = item.into_actor_episode().unwrap();
+        assert_eq!(
+            (
+                "series::00000000-0000-0000-0000-000000000007".to_owned(),
+                "episode::0001#0012".to_owned()
+            ),
+            key
+        );
+        assert_eq!(credit.characters, characters);
+    }
+
+    #[test]
+    fn test_review_serialization() {
+        let mut meta = crate::data::MovieMetadata::new(
+            Uuid::from_u128(1),
+            "Heat".to_owned(),
+            DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap(),
+        );
+        meta.ratings.rate(6, None);
+        meta.ratings.rate(9, None);
+        meta.ratings.rate(8, Some(6));
+        let review = crate::data::Review {
+            movie_id: meta.id,
+            user_id: "movie#goer".to_owned(),
+            version: 0,
+            rating: 8,
+            text: Some("Great heist".to_owned()),
+            reviewed_at: DateTime::<Utc>::from_str("2020-01-01T00:00:00Z").unwrap(),
+        };
+        let items = [
+            DynamoTableItem::new_movie_metadata(&meta),
+            DynamoTableItem::new_movie_review(&review),
+        ]
+        .iter()
+        .map(|x| serde_json::to_string(&x).unwrap())
+        .collect::<Vec<String>>();
+        assert_eq!(
+            vec![
+                r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"meta","version":1,"lookup":"movie::Heat#1995","title":"Heat","published_at":"1995-12-15T00:00:00Z","published_year":1995,"search_prefix":"h","search_title":"heat","genres":[],"countries":[],"directors":[],"writers":[],"release_dates":[],"rating_count":2,"rating_sum":17,"rating_8":1,"rating_9":1}"#,
+                r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"review::movie%23goer","version":1,"rating":8,"review_text":"Great heist","reviewed_at":"2020-01-01T00:00:00Z"}"#,
+            ],
+            items
+        );
+
+        let item: DynamoTableItem = serde_json::from_str(&items[0]).unwrap();
+        let stored = item.into_movie_metadata().unwrap();
+        assert_eq!(meta.ratings, stored.ratings);
+        assert_eq!(Some(8.5), stored.ratings.mean());
+        let item: DynamoTableItem = serde_json::from_str(&items[1]).unwrap();
+        assert_eq!(
+            crate::data::Review {
+                version: 1,
+                ..review
+            },
+            item.into_review().unwrap()
+        );
+        let item: DynamoTableItem = serde_json::from_str(&items[1]).unwrap();
+        assert_eq!(Some(0), item.expected_version());
+        // a rating out of range would pick no counter of the histogram
+        for rating in &["0", "11"] {
+            let data = items[1].replace(r#""rating":8"#, &format!(r#""rating":{}"#, rating));
+            let item: DynamoTableItem = serde_json::from_str(&data).unwrap();
+            match item.into_review().unwrap_err() {
+                crate::error::Error::CorruptItem(_) => {}
+                e => panic!("unexpected error {}", e),
+            }
+        }
     }
 
     #[test]
     fn test_deserialize_actor_meta() {
-        let data = r#"{"pk":"actor::Deniro Robert","sk":"meta","last_name":"Deniro","first_name":"Robert"}"#;
+        let data = r#"{"pk":"actor::00000000-0000-0000-0000-000000000002","sk":"meta","lookup":"actor::Deniro Robert","last_name":"Deniro","first_name":"Robert"}"#;
         let item: DynamoTableItem = serde_json::from_str(data).unwrap();
-        println!("{:?}", item);
+        let actor = item.into_actor().unwrap();
+        assert_eq!(Uuid::from_u128(2), actor.id);
+        assert_eq!(None, actor.birth_date);
+        assert!(actor.aliases.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_corrupt_items() {
+        let corrupt = vec![
+            (r#"{"sk":"meta","title":"Heat"}"#, "missing field `pk`"),
+            (
+                r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"meta","published_at":"1995-12-15T00:00:00Z"}"#,
+                "missing field `title`",
+            ),
+            (
+                r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"meta","title":"Heat","published_at":"1995"}"#,
+                "premature end of input",
+            ),
+            (
+                r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"actor::00000000-0000-0000-0000-000000000002","version":"one","characters":[]}"#,
+                "invalid type",
+            ),
+            (
+                r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"review::1","reviewed_at":"2020-01-01T00:00:00Z"}"#,
+                "missing field `rating`",
+            ),
+            (
+                r#"{"pk":"movie::00000000-0000-0000-0000-000000000001","sk":"trailer::1"}"#,
+                "unknown item",
+            ),
+        ];
+        for (data, message) in corrupt {
+            let error = serde_json::from_str::<DynamoTableItem>(data)
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains(message), "{} for {}", error, data);
+        }
+
+        let mut item = movie_meta_item();
+        item.insert(
+            "title".to_owned(),
+            AttributeValue {
+                n: Some("1995".to_owned()),
+                ..Default::default()
+            },
+        );
+        match DynamoTableItem::from_item(item, UnknownAttributes::Keep) {
+            Err(crate::error::Error::CorruptItem(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_unknown_attributes() {
+        let item = DynamoTableItem::from_item(movie_meta_item(), UnknownAttributes::Keep).unwrap();
+        assert_eq!(Some("5"), item.unknown["popularity"].n.as_deref());
+        let meta = item.into_movie_metadata().unwrap();
+        assert_eq!(None, meta.imdb_id);
+        let written = DynamoTableItem::new_movie_metadata(&meta)
+            .to_item()
+            .unwrap();
+        assert_eq!(Some("5"), written["popularity"].n.as_deref());
+        assert!(!written.contains_key("imdb_id"));
+
+        let item =
+            DynamoTableItem::from_item(movie_meta_item(), UnknownAttributes::Ignore).unwrap();
+        assert!(item.unknown.is_empty());
+        assert!(!item.to_item().unwrap().contains_key("popularity"));
+    }
+
+    #[test]
+    fn test_unknown_attributes_of_credits() {
+        let mut item = map! {
+            "pk".to_owned() => s("movie::00000000-0000-0000-0000-000000000001"),
+            "sk".to_owned() => s("actor::00000000-0000-0000-0000-000000000002"),
+            "characters".to_owned() => AttributeValue {
+                l: Some(vec![s("Lt. Vincent Hanna")]),
+                ..Default::default()
+            },
+            "character_ids".to_owned() => AttributeValue {
+                l: Some(vec![s("00000000-0000-0000-0000-000000000003")]),
+                ..Default::default()
+            },
+            "popularity".to_owned() => s("high")
+        };
+        let meta = DynamoTableItem::from_item(movie_meta_item(), UnknownAttributes::Keep)
+            .unwrap()
+            .into_movie_metadata()
+            .unwrap();
+        let (actor_id, credit) = DynamoTableItem::from_item(item.clone(), UnknownAttributes::Keep)
+            .unwrap()
+            .into_movie_actor()
+            .unwrap();
+        let written = DynamoTableItem::new_movie_actor(&meta, &actor_id, &credit)
+            .to_item()
+            .unwrap();
+        assert_eq!(Some("high"), written["popularity"].s.as_deref());
+
+        item.insert("sk".to_owned(), s("crew::directing#Michael Mann"));
+        item.insert("crew_name".to_owned(), s("Michael Mann"));
+        item.insert("department".to_owned(), s("directing"));
+        let crew = DynamoTableItem::from_item(item, UnknownAttributes::Keep)
+            .unwrap()
+            .into_crew_credit()
+            .unwrap();
+        let written = DynamoTableItem::new_movie_crew(&meta, &crew)
+            .to_item()
+            .unwrap();
+        assert_eq!(Some("high"), written["popularity"].s.as_deref());
+    }
+
+    /// Every attribute written for an item is read back, so `KNOWN_ATTRIBUTES` has to list
+    /// exactly the attributes of the items with all optional fields set.
+    #[test]
+    fn test_known_attributes_are_written() {
+        let published_at = DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap();
+        let mut meta = crate::data::MovieMetadata {
+            id: Uuid::from_u128(1),
+            version: 0,
+            title: "Heat".to_owned(),
+            imdb_id: Some("tt0113277".to_owned()),
+            published_at,
+            genres: vec!["Crime".to_owned()],
+            runtime_minutes: Some(170),
+            original_language: Some("en".to_owned()),
+            countries: vec!["US".to_owned()],
+            synopsis: Some("A heist goes wrong.".to_owned()),
+            age_rating: Some("R".to_owned()),
+            directors: vec!["Michael Mann".to_owned()],
+            writers: vec!["Michael Mann".to_owned()],
+            poster_url: Some("https://example.com/heat.jpg".to_owned()),
+            release_dates: vec![ReleaseDate {
+                country: "DE".to_owned(),
+                released_on: NaiveDate::from_ymd(1996, 2, 29),
+            }],
+            ratings: Default::default(),
+            unknown: Attributes::new(),
+        };
+        for rating in 1..=crate::data::MAX_RATING {
+            meta.ratings.rate(rating, None);
+        }
+        let actor = crate::data::Actor {
+            id: Uuid::from_u128(2),
+            version: 0,
+            last_name: "Pacino".to_owned(),
+            first_name: "Al".to_owned(),
+            birth_date: Some(NaiveDate::from_ymd(1940, 4, 25)),
+            imdb_id: Some("nm0000199".to_owned()),
+            biography: Some("Actor.".to_owned()),
+            aliases: vec![],
+            unknown: Attributes::new(),
+        };
+        let credit = crate::data::Credit {
+            characters: vec![crate::data::Character {
+                id: Uuid::from_u128(104),
+                version: 0,
+                name: "Lt. Vincent Hanna".to_owned(),
+            }],
+            billing_order: Some(1),
+            credited_as: Some("Alfredo Pacino".to_owned()),
+            ..Default::default()
+        };
+        let crew = crate::data::CrewCredit {
+            name: "Michael Mann".to_owned(),
+            department: crate::data::Department::Directing,
+            job: Some("Director".to_owned()),
+            unknown: Attributes::new(),
+        };
+        let review = crate::data::Review {
+            movie_id: meta.id,
+            user_id: "moviegoer".to_owned(),
+            version: 0,
+            rating: 8,
+            text: Some("Great.".to_owned()),
+            reviewed_at: published_at,
+        };
+        let collection = crate::data::Collection {
+            id: Uuid::from_u128(3),
+            version: 0,
+            name: "Michael Mann".to_owned(),
+            unknown: Attributes::new(),
+        };
+        let series = crate::data::Series {
+            id: Uuid::from_u128(4),
+            version: 0,
+            title: "Miami Vice".to_owned(),
+            published_at,
+            unknown: Attributes::new(),
+        };
+        let season = crate::data::Season {
+            series_id: series.id,
+            number: 1,
+            title: Some("Season One".to_owned()),
+        };
+        let episode = crate::data::Episode {
+            series_id: series.id,
+            season_number: 1,
+            episode_number: 1,
+            title: "Brother's Keeper".to_owned(),
+            published_at,
+        };
+        let mut items = vec![
+            DynamoTableItem::new_movie_metadata(&meta),
+            DynamoTableItem::new_movie_actor(&meta, &actor.id, &credit),
+            DynamoTableItem::new_movie_crew(&meta, &crew),
+            DynamoTableItem::new_movie_review(&review),
+            DynamoTableItem::new_actor(&actor),
+            DynamoTableItem::new_collection(&collection),
+            DynamoTableItem::new_collection_movie(&collection, &meta.id, 1),
+            DynamoTableItem::new_series(&series),
+            DynamoTableItem::new_season(&series, &season),
+            DynamoTableItem::new_series_episode(&series, &episode),
+            DynamoTableItem::new_episode_actor(&series, &episode, &actor.id, &credit),
+        ];
+        items.extend(DynamoTableItem::new_character_movies(
+            &meta, &actor.id, &credit,
+        ));
+        items.extend(DynamoTableItem::new_characters(Some(&credit)));
+        let mut written = items
+            .iter()
+            .flat_map(|item| item.to_item().unwrap().into_keys())
+            .collect::<Vec<String>>();
+        written.sort();
+        written.dedup();
+        let mut known = super::KNOWN_ATTRIBUTES
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<String>>();
+        known.sort();
+        assert_eq!(known, written);
     }
 }
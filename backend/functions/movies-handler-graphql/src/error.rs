@@ -0,0 +1,141 @@
+use crate::validation::Violation;
+use juniper::{graphql_value, FieldError, IntoFieldError, Object, ScalarValue, Value};
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors of the API. Every kind is reported to clients with its own `extensions.code`,
+/// so they can tell e.g. a missing movie apart from an unavailable database.
+#[derive(Debug)]
+pub enum Error {
+    /// The requested record does not exist.
+    NotFound(String),
+    /// The request is invalid.
+    Validation(String),
+    /// Fields of the request are invalid, every violation is reported.
+    InvalidInput(Vec<Violation>),
+    /// The request conflicts with another request or the stored data.
+    Conflict(String),
+    /// The record was modified since it was read.
+    ConcurrentModification,
+    /// DynamoDB could not be reached or failed to process the request.
+    StorageUnavailable(String),
+    /// DynamoDB rejected the request because of the request rate.
+    Throttled(String),
+    /// A stored item could not be read.
+    CorruptItem(String),
+    /// The lambda function is not configured properly.
+    Configuration(String),
+}
+
+impl Error {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NotFound(_) => "NOT_FOUND",
+            Error::Validation(_) | Error::InvalidInput(_) => "VALIDATION",
+            Error::Conflict(_) => "CONFLICT",
+            Error::ConcurrentModification => "CONCURRENT_MODIFICATION",
+            Error::StorageUnavailable(_) => "STORAGE_UNAVAILABLE",
+            Error::Throttled(_) => "THROTTLED",
+            Error::CorruptItem(_) => "CORRUPT_ITEM",
+            Error::Configuration(_) => "CONFIGURATION",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotFound(what) => write!(f, "{} not found", what),
+            Error::Validation(message) => write!(f, "{}", message),
+            Error::InvalidInput(violations) => {
+                write!(f, "invalid input")?;
+                for (i, violation) in violations.iter().enumerate() {
+                    let separator = if i == 0 { ": " } else { ", " };
+                    write!(f, "{}{} {}", separator, violation.field, violation.message)?;
+                }
+                Ok(())
+            }
+            Error::Conflict(message) => write!(f, "conflict: {}", message),
+            Error::ConcurrentModification => write!(f, "the item was modified concurrently"),
+            Error::StorageUnavailable(message) => write!(f, "storage unavailable: {}", message),
+            Error::Throttled(message) => write!(f, "throttled: {}", message),
+            Error::CorruptItem(message) => write!(f, "corrupt item: {}", message),
+            Error::Configuration(message) => write!(f, "invalid configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl<S: ScalarValue> IntoFieldError<S> for Error {
+    fn into_field_error(self) -> FieldError<S> {
+        let code = self.code();
+        let mut extensions = graphql_value!({ "code": code });
+        if let (Error::InvalidInput(violations), Some(object)) =
+            (&self, extensions.as_mut_object_value())
+        {
+            let violations = violations
+                .iter()
+                .map(|violation| {
+                    let mut fields = Object::with_capacity(2);
+                    fields.add_field("field", Value::scalar(violation.field.clone()));
+                    fields.add_field("message", Value::scalar(violation.message.clone()));
+                    Value::object(fields)
+                })
+                .collect();
+            object.add_field("violations", Value::list(violations));
+        }
+        FieldError::new(self, extensions)
+    }
+}
+
+impl From<serde_dynamodb::Error> for Error {
+    fn from(e: serde_dynamodb::Error) -> Self {
+        Error::CorruptItem(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::validation::Violation;
+    use juniper::{graphql_value, DefaultScalarValue, IntoFieldError};
+
+    #[test]
+    fn test_field_error_code() {
+        let error: juniper::FieldError<DefaultScalarValue> =
+            Error::NotFound("movie Heat (1995)".to_owned()).into_field_error();
+        assert_eq!("movie Heat (1995) not found", error.message());
+        assert_eq!(&graphql_value!({ "code": "NOT_FOUND" }), error.extensions());
+    }
+
+    #[test]
+    fn test_field_error_violations() {
+        let error: juniper::FieldError<DefaultScalarValue> = Error::InvalidInput(vec![
+            Violation {
+                field: "title".to_owned(),
+                message: "must not be empty".to_owned(),
+            },
+            Violation {
+                field: "imdb_id".to_owned(),
+                message: "must be an IMDb id like tt0111161".to_owned(),
+            },
+        ])
+        .into_field_error();
+        assert_eq!(
+            "invalid input: title must not be empty, imdb_id must be an IMDb id like tt0111161",
+            error.message()
+        );
+        assert_eq!(
+            &graphql_value!({
+                "code": "VALIDATION",
+                "violations": [
+                    { "field": "title", "message": "must not be empty" },
+                    { "field": "imdb_id", "message": "must be an IMDb id like tt0111161" },
+                ],
+            }),
+            error.extensions()
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::error::{Error, Result};
+
+/// Separates the components of a key, e.g. title and year in `movie::Heat#1995`.
+pub const COMPONENT_SEPARATOR: char = '#';
+/// Separates the parts of a name, e.g. last and first name in `actor::Deniro Robert`.
+pub const NAME_SEPARATOR: char = ' ';
+
+/// Characters with a meaning in every key, they are percent-encoded inside of components
+/// together with the separator of the key. Escaping is done per character, so the escaped
+/// prefix of a component is a prefix of the escaped component and `begins_with`
+/// conditions keep working.
+const RESERVED: &[char] = &['%', ':'];
+
+fn is_reserved(c: char, separator: char) -> bool {
+    c == separator || RESERVED.contains(&c)
+}
+
+pub fn escape(component: &str, separator: char) -> String {
+    let mut escaped = String::with_capacity(component.len());
+    for c in component.chars() {
+        if is_reserved(c, separator) {
+            escaped.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+pub fn unescape(escaped: &str, separator: char) -> Result<String> {
+    let mut component = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            component.push(c);
+            continue;
+        }
+        let code = chars
+            .next()
+            .and_then(|h| chars.next().map(|l| format!("{}{}", h, l)));
+        match code
+            .and_then(|code| u8::from_str_radix(&code, 16).ok())
+            .map(char::from)
+        {
+            Some(c) if is_reserved(c, separator) => component.push(c),
+            _ => {
+                return Err(Error::CorruptItem(format!(
+                    "invalid escape sequence in key {}",
+                    escaped
+                )))
+            }
+        }
+    }
+    Ok(component)
+}
+
+/// Builds a key out of a prefix like `movie::` and the escaped components.
+pub fn encode_key(prefix: &str, components: &[&str], separator: char) -> String {
+    let mut key = prefix.to_owned();
+    for (i, component) in components.iter().enumerate() {
+        if i > 0 {
+            key.push(separator);
+        }
+        key.push_str(&escape(component, separator));
+    }
+    key
+}
+
+/// Splits a key built by `encode_key` back into its unescaped components.
+pub fn decode_key(prefix: &str, key: &str, separator: char) -> Result<Vec<String>> {
+    key.strip_prefix(prefix)
+        .ok_or_else(|| Error::CorruptItem(format!("key {} does not start with {}", key, prefix)))?
+        .split(separator)
+        .map(|component| unescape(component, separator))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::{
+        decode_key, encode_key, escape, unescape, COMPONENT_SEPARATOR, NAME_SEPARATOR,
+    };
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_escape_delimiters() {
+        assert_eq!(
+            "movie::Heat%231995#1995",
+            encode_key("movie::", &["Heat#1995", "1995"], COMPONENT_SEPARATOR)
+        );
+        assert_eq!(
+            "actor::De%20Niro Robert",
+            encode_key("actor::", &["De Niro", "Robert"], NAME_SEPARATOR)
+        );
+        assert_eq!(
+            vec!["De Niro", "Robert"],
+            decode_key("actor::", "actor::De%20Niro Robert", NAME_SEPARATOR).unwrap()
+        );
+        assert_eq!(
+            "movie::The Irishman#2019",
+            encode_key("movie::", &["The Irishman", "2019"], COMPONENT_SEPARATOR)
+        );
+        assert!(unescape("100%", COMPONENT_SEPARATOR).is_err());
+        assert!(unescape("%41", COMPONENT_SEPARATOR).is_err());
+        assert!(decode_key("movie::", "actor::Pacino Al", NAME_SEPARATOR).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_escape_roundtrip(
+            component in any::<String>(),
+            separator in prop::sample::select(vec![COMPONENT_SEPARATOR, NAME_SEPARATOR]),
+        ) {
+            let escaped = escape(&component, separator);
+            prop_assert!(!escaped.contains(separator));
+            prop_assert_eq!(&component, &unescape(&escaped, separator).unwrap());
+        }
+
+        #[test]
+        fn test_escape_keeps_prefixes(component in any::<String>(), suffix in any::<String>()) {
+            let escaped = escape(&format!("{}{}", component, suffix), COMPONENT_SEPARATOR);
+            prop_assert!(escaped.starts_with(&escape(&component, COMPONENT_SEPARATOR)));
+        }
+
+        #[test]
+        fn test_movie_key_roundtrip(title in any::<String>(), year in any::<i32>()) {
+            let year = year.to_string();
+            let key = encode_key("movie::", &[&title, &year], COMPONENT_SEPARATOR);
+            prop_assert_eq!(
+                vec![title, year],
+                decode_key("movie::", &key, COMPONENT_SEPARATOR).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_actor_key_roundtrip(last_name in any::<String>(), first_name in any::<String>()) {
+            let key = encode_key("actor::", &[&last_name, &first_name], NAME_SEPARATOR);
+            prop_assert_eq!(
+                vec![last_name, first_name],
+                decode_key("actor::", &key, NAME_SEPARATOR).unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::data::Actor;
+use crate::error::{Error, Result};
+use crate::storage::MovieStore;
+use futures::lock::Mutex;
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+
+/// Loads actors for the duration of a single GraphQL request.
+/// Keys that are not cached yet are fetched in one batch, the results are cached so
+/// every actor is read from the store at most once per request.
+pub struct ActorLoader {
+    store: Arc<dyn MovieStore>,
+    cache: Mutex<HashMap<Uuid, Actor>>,
+}
+
+impl ActorLoader {
+    pub fn new(store: Arc<dyn MovieStore>) -> Self {
+        ActorLoader {
+            store,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Loads an actor, `None` if there is no actor with the id.
+    pub async fn load(&self, id: &Uuid) -> Result<Option<Actor>> {
+        Ok(self.load_found(&[*id]).await?.remove(id))
+    }
+
+    /// Loads all given actors, failing if one of them does not exist.
+    pub async fn load_many(&self, ids: &[Uuid]) -> Result<Vec<Actor>> {
+        let actors = self.load_found(ids).await?;
+        ids.iter()
+            .map(|id| {
+                actors
+                    .get(id)
+                    .cloned()
+                    .ok_or_else(|| Error::NotFound(format!("actor {}", id)))
+            })
+            .collect()
+    }
+
+    /// Returns the given actors that exist.
+    async fn load_found(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Actor>> {
+        // the lock is only held while touching the cache, resolvers waiting for other
+        // actors are not blocked by a pending batch
+        let mut missing = {
+            let cache = self.cache.lock().await;
+            ids.iter()
+                .filter(|id| !cache.contains_key(*id))
+                .cloned()
+                .collect::<Vec<Uuid>>()
+        };
+        missing.sort();
+        missing.dedup();
+        let loaded = if missing.is_empty() {
+            HashMap::new()
+        } else {
+            self.store.read_actors_metadata(&missing).await?
+        };
+        let mut cache = self.cache.lock().await;
+        cache.extend(loaded);
+        Ok(ids
+            .iter()
+            .filter_map(|id| cache.get(id).map(|actor| (*id, actor.clone())))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::loader::ActorLoader;
+    use crate::storage::DynamoMovieStore;
+    use rusoto_core::Region;
+    use rusoto_dynamodb::DynamoDbClient;
+    use rusoto_mock::{MockCredentialsProvider, MockRequestDispatcher};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn test_load_many_reads_each_actor_once() {
+        let config = Config {
+            region: Region::EuCentral1,
+            table_name: "aws-movies--movies".to_owned(),
+            unknown_attributes: crate::config::UnknownAttributes::Keep,
+        };
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body(
+                r#"{"Responses":{"aws-movies--movies":[
+                    {"pk":{"S":"actor::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"},"last_name":{"S":"Deniro"},"first_name":{"S":"Robert"}},
+                    {"pk":{"S":"actor::00000000-0000-0000-0000-000000000002"},"sk":{"S":"meta"},"last_name":{"S":"Pacino"},"first_name":{"S":"Al"}}
+                ]},"UnprocessedKeys":{}}"#,
+            )
+            .with_request_checker(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        let client =
+            DynamoDbClient::new_with(dispatcher, MockCredentialsProvider, config.region.clone());
+        let loader = ActorLoader::new(Arc::new(DynamoMovieStore::with_client(client, &config)));
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let ids = vec![Uuid::from_u128(1), Uuid::from_u128(2), Uuid::from_u128(1)];
+        let actors = runtime.block_on(loader.load_many(&ids)).unwrap();
+        assert_eq!(
+            vec!["Deniro", "Pacino", "Deniro"],
+            actors
+                .iter()
+                .map(|a| a.last_name.as_str())
+                .collect::<Vec<&str>>()
+        );
+        runtime
+            .block_on(loader.load_many(&[Uuid::from_u128(2)]))
+            .unwrap();
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
+}
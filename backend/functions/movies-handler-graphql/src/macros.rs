@@ -10,3 +10,41 @@ macro_rules! map(
         }
      };
 );
+
+/// Declares a Relay-style connection type and its edge type for the given node type.
+#[macro_export]
+macro_rules! connection(
+    { $connection:ident, $edge:ident, $node:ty, $context:ty } => {
+        #[derive(juniper::GraphQLObject)]
+        #[graphql(context = $context)]
+        pub struct $edge {
+            #[graphql(name = "cursor")]
+            pub cursor: String,
+            #[graphql(name = "node")]
+            pub node: $node,
+        }
+
+        #[derive(juniper::GraphQLObject)]
+        #[graphql(context = $context)]
+        pub struct $connection {
+            #[graphql(name = "edges")]
+            pub edges: Vec<$edge>,
+            #[graphql(name = "page_info")]
+            pub page_info: $crate::pagination::PageInfo,
+        }
+
+        impl $connection {
+            pub fn new(page: $crate::pagination::Page<$node>, after: &Option<String>) -> Self {
+                let page_info = $crate::pagination::PageInfo::new(&page, after);
+                $connection {
+                    edges: page
+                        .items
+                        .into_iter()
+                        .map(|(cursor, node)| $edge { cursor, node })
+                        .collect(),
+                    page_info,
+                }
+            }
+        }
+    };
+);
@@ -2,48 +2,1748 @@
 extern crate lambda_runtime as lambda;
 #[macro_use]
 extern crate log;
-#[macro_use]
-extern crate error_chain;
 
 #[macro_use]
 mod macros;
+mod config;
 mod data;
 mod dynamo;
-mod option;
+mod error;
+mod keys;
+mod loader;
+#[cfg(test)]
+mod memory;
+mod pagination;
 mod schema;
+mod search;
 mod storage;
-use crate::schema::create_schema;
+mod validation;
+use crate::config::Config;
+use crate::schema::{create_schema, Environment};
+use crate::storage::DynamoMovieStore;
 
 use juniper::http::GraphQLRequest;
 use lambda::error::HandlerError;
 
-error_chain! {
-    types {
-        Error, ErrorKind, ResultExt, Result;
-    }
-
-    foreign_links {
-        LambdaError(::lambda::error::HandlerError);
-        LoggerError(::log::SetLoggerError);
-    }
-}
-
-pub fn execute() -> std::result::Result<(), HandlerError> {
-    lambda!(handler);
+pub fn execute() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::from_env()?;
+    let store = DynamoMovieStore::new(&config);
+    let environment = Environment::new(config, Box::new(store))?;
+    lambda!(|request: GraphQLRequest, c: lambda::Context| handler(request, c, &environment));
     Ok(())
 }
 
 pub fn handler(
     request: GraphQLRequest,
     _: lambda::Context,
-) -> std::result::Result<String, HandlerError> {
+    environment: &Environment,
+) -> Result<String, HandlerError> {
     info!("{:?}", request);
     let schema = create_schema();
-    Ok(serde_json::to_string(&request.execute(&schema, &())).unwrap())
+    let context = environment.for_request();
+    let response = environment.block_on(request.execute(&schema, &context));
+    Ok(serde_json::to_string(&response).unwrap())
 }
 
-fn main() -> Result<()> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     simple_logger::init_with_level(log::Level::Info)?;
-    lambda!(handler);
+    execute()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::memory::InMemoryMovieStore;
+    use crate::schema::Environment;
+    use juniper::http::GraphQLRequest;
+
+    fn run(context: &Environment, query: &str) -> serde_json::Value {
+        let request: GraphQLRequest =
+            serde_json::from_value(serde_json::json!({ "query": query })).unwrap();
+        let response = crate::handler(request, lambda::Context::default(), context).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    fn context() -> Environment {
+        let config = Config {
+            region: rusoto_core::Region::EuCentral1,
+            table_name: "aws-movies--movies".to_owned(),
+            unknown_attributes: crate::config::UnknownAttributes::Keep,
+        };
+        Environment::new(config, Box::new(InMemoryMovieStore::new())).unwrap()
+    }
+
+    #[test]
+    fn test_handler_put_and_get_movie() {
+        let context = context();
+        let put = run(
+            &context,
+            r#"mutation {
+                put_movie(request: {
+                    title: "The Irishman",
+                    imdb_id: "tt1302006",
+                    published_at: "2019-09-27T00:00:00Z",
+                    roles: [
+                        { actor_last_name: "Deniro", actor_first_name: "Robert", character_names: ["Frank Sheeran"] },
+                        { actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Jimmy Hoffa"] }
+                    ]
+                })
+            }"#,
+        );
+        assert_eq!(serde_json::json!({ "data": { "put_movie": true } }), put);
+
+        let get = run(
+            &context,
+            r#"query {
+                get_movie(title: "The Irishman", published_at: "2019-09-27T00:00:00Z") {
+                    meta { title, imdb_id }
+                    roles { actor { last_name, first_name }, characters { name } }
+                }
+            }"#,
+        );
+        assert_eq!(
+            serde_json::json!({ "data": { "get_movie": {
+                "meta": { "title": "The Irishman", "imdb_id": "tt1302006" },
+                "roles": [
+                    { "actor": { "last_name": "Deniro", "first_name": "Robert" }, "characters": [{ "name": "Frank Sheeran" }] },
+                    { "actor": { "last_name": "Pacino", "first_name": "Al" }, "characters": [{ "name": "Jimmy Hoffa" }] }
+                ]
+            } } }),
+            get
+        );
+    }
+
+    #[test]
+    fn test_handler_movie_without_imdb_id() {
+        let context = context();
+        run(
+            &context,
+            r#"mutation {
+                put_movie(request: { title: "Heat", published_at: "1995-12-15T00:00:00Z", roles: [] })
+            }"#,
+        );
+        let get = run(
+            &context,
+            r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") { meta { title, imdb_id } } }"#,
+        );
+        assert_eq!(
+            serde_json::json!({ "data": { "get_movie": { "meta": { "title": "Heat", "imdb_id": null } } } }),
+            get
+        );
+    }
+
+    #[test]
+    fn test_handler_movie_details() {
+        let context = context();
+        let put = run(
+            &context,
+            r#"mutation {
+                put_movie(request: {
+                    title: "Heat",
+                    published_at: "1995-12-15T00:00:00Z",
+                    genres: ["Crime", "Thriller"],
+                    runtime_minutes: 170,
+                    original_language: "en",
+                    countries: ["US"],
+                    age_rating: "R",
+                    directors: ["Michael Mann"],
+                    writers: ["Michael Mann"],
+                    poster_url: "https://example.com/heat.jpg",
+                    release_dates: [{ country: "DE", released_on: "1996-02-29" }],
+                    roles: []
+                })
+            }"#,
+        );
+        assert!(put["errors"].is_null(), "{}", put);
+        let get = run(
+            &context,
+            r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") { meta {
+                genres, runtime_minutes, original_language, countries, synopsis, age_rating,
+                directors, writers, poster_url, release_dates { country, released_on }
+            } } }"#,
+        );
+        assert_eq!(
+            serde_json::json!({
+                "genres": ["Crime", "Thriller"],
+                "runtime_minutes": 170,
+                "original_language": "en",
+                "countries": ["US"],
+                "synopsis": null,
+                "age_rating": "R",
+                "directors": ["Michael Mann"],
+                "writers": ["Michael Mann"],
+                "poster_url": "https://example.com/heat.jpg",
+                "release_dates": [{ "country": "DE", "released_on": "1996-02-29" }]
+            }),
+            get["data"]["get_movie"]["meta"]
+        );
+
+        // writing the movie again without the optional fields keeps them
+        let put = run(
+            &context,
+            r#"mutation {
+                put_movie(request: { title: "Heat", published_at: "1995-12-15T00:00:00Z", roles: [] })
+            }"#,
+        );
+        assert!(put["errors"].is_null(), "{}", put);
+        let details = r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") {
+            id, meta { genres, runtime_minutes, countries, synopsis, poster_url }
+        } }"#;
+        let heat = run(&context, details)["data"]["get_movie"].clone();
+        assert_eq!(
+            serde_json::json!({
+                "genres": ["Crime", "Thriller"],
+                "runtime_minutes": 170,
+                "countries": ["US"],
+                "synopsis": null,
+                "poster_url": "https://example.com/heat.jpg"
+            }),
+            heat["meta"]
+        );
+        let update = |request: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ update_movie_metadata(id: "{}", request: {{ {} }}) }}"#,
+                    heat["id"].as_str().unwrap(),
+                    request
+                ),
+            )
+        };
+        let updated = update(
+            r#"genres: ["Crime"], countries: [], synopsis: "A heist.", clear: [RUNTIME_MINUTES, POSTER_URL]"#,
+        );
+        assert!(updated["errors"].is_null(), "{}", updated);
+        assert_eq!(
+            serde_json::json!({
+                "genres": ["Crime"],
+                "runtime_minutes": null,
+                "countries": [],
+                "synopsis": "A heist.",
+                "poster_url": null
+            }),
+            run(&context, details)["data"]["get_movie"]["meta"]
+        );
+        let both = update(r#"synopsis: "A heist.", clear: [SYNOPSIS]"#);
+        assert_eq!(
+            "clear",
+            both["errors"][0]["extensions"]["violations"][0]["field"]
+        );
+
+        let invalid = run(
+            &context,
+            r#"mutation {
+                put_movie(request: {
+                    title: "Heat",
+                    published_at: "1995-12-15T00:00:00Z",
+                    runtime_minutes: 0,
+                    original_language: "English",
+                    countries: ["usa"],
+                    poster_url: "heat.jpg",
+                    roles: []
+                })
+            }"#,
+        );
+        assert_eq!(
+            4,
+            invalid["errors"][0]["extensions"]["violations"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_handler_credits_and_crew() {
+        let context = context();
+        let put = run(
+            &context,
+            r#"mutation {
+                put_movie(request: {
+                    title: "Heat",
+                    published_at: "1995-12-15T00:00:00Z",
+                    roles: [
+                        { actor_last_name: "Trejo", actor_first_name: "Danny", character_names: ["Trejo"], billing_order: 12 },
+                        { actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Vincent Hanna"], billing_order: 1 },
+                        { actor_last_name: "Bauchau", actor_first_name: "Patrick", character_names: ["Kelso"], uncredited: true },
+                        { actor_last_name: "Deniro", actor_first_name: "Robert", character_names: ["Neil McCauley"], billing_order: 2, credited_as: "Robert De Niro" }
+                    ],
+                    crew: [
+                        { name: "Elliot Goldenthal", department: MUSIC },
+                        { name: "Michael Mann", department: WRITING, job: "Screenplay" },
+                        { name: "Michael Mann", department: DIRECTING }
+                    ]
+                })
+            }"#,
+        );
+        assert!(put["errors"].is_null(), "{}", put);
+
+        let query = r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") {
+            roles { actor { last_name }, billing_order, credited_as, uncredited }
+            crew { department, members { name, job } }
+        } }"#;
+        let get = run(&context, query);
+        assert_eq!(
+            serde_json::json!({
+                "roles": [
+                    { "actor": { "last_name": "Pacino" }, "billing_order": 1, "credited_as": null, "uncredited": false },
+                    { "actor": { "last_name": "Deniro" }, "billing_order": 2, "credited_as": "Robert De Niro", "uncredited": false },
+                    { "actor": { "last_name": "Trejo" }, "billing_order": 12, "credited_as": null, "uncredited": false },
+                    { "actor": { "last_name": "Bauchau" }, "billing_order": null, "credited_as": null, "uncredited": true }
+                ],
+                "crew": [
+                    { "department": "DIRECTING", "members": [{ "name": "Michael Mann", "job": null }] },
+                    { "department": "WRITING", "members": [{ "name": "Michael Mann", "job": "Screenplay" }] },
+                    { "department": "MUSIC", "members": [{ "name": "Elliot Goldenthal", "job": null }] }
+                ]
+            }),
+            get["data"]["get_movie"]
+        );
+
+        // replacing the movie without crew removes the crew credits
+        let put = run(
+            &context,
+            r#"mutation {
+                put_movie(request: { title: "Heat", published_at: "1995-12-15T00:00:00Z", roles: [] })
+            }"#,
+        );
+        assert!(put["errors"].is_null(), "{}", put);
+        let get = run(&context, query);
+        assert_eq!(serde_json::json!([]), get["data"]["get_movie"]["crew"]);
+
+        let invalid = run(
+            &context,
+            r#"mutation {
+                put_movie(request: {
+                    title: "Heat",
+                    published_at: "1995-12-15T00:00:00Z",
+                    roles: [{ actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Vincent Hanna"], billing_order: 0 }],
+                    crew: [
+                        { name: "Michael Mann", department: DIRECTING },
+                        { name: "Michael Mann", department: DIRECTING }
+                    ]
+                })
+            }"#,
+        );
+        assert_eq!(
+            2,
+            invalid["errors"][0]["extensions"]["violations"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_handler_missing_records_are_null() {
+        let context = context();
+        let id = uuid::Uuid::nil();
+        let missing = run(
+            &context,
+            &format!(
+                r#"query {{
+                    movie(id: "{id}") {{ id }}
+                    movie_exists(id: "{id}")
+                    actor(id: "{id}") {{ id }}
+                    get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") {{ id }}
+                    get_actor(last_name: "Pacino", first_name: "Al") {{ id }}
+                }}"#,
+                id = id
+            ),
+        );
+        assert_eq!(
+            serde_json::json!({ "data": {
+                "movie": null,
+                "movie_exists": false,
+                "actor": null,
+                "get_movie": null,
+                "get_actor": null
+            } }),
+            missing
+        );
+
+        run(
+            &context,
+            r#"mutation {
+                put_movie(request: { title: "Heat", published_at: "1995-12-15T00:00:00Z", roles: [] })
+            }"#,
+        );
+        let get = run(
+            &context,
+            r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") { id } }"#,
+        );
+        let exists = run(
+            &context,
+            &format!(
+                r#"query {{ movie_exists(id: {}) }}"#,
+                get["data"]["get_movie"]["id"]
+            ),
+        );
+        assert_eq!(true, exists["data"]["movie_exists"]);
+    }
+
+    #[test]
+    fn test_handler_remakes() {
+        let context = context();
+        for (imdb_id, published_at) in &[
+            ("tt0054135", "1960-08-10T00:00:00Z"),
+            ("tt0240772", "2001-12-07T00:00:00Z"),
+        ] {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{
+                        put_movie(request: {{ title: "Ocean's Eleven", imdb_id: "{}", published_at: "{}", roles: [] }})
+                    }}"#,
+                    imdb_id, published_at
+                ),
+            );
+        }
+        let releases = run(
+            &context,
+            r#"query {
+                movies_by_title(title: "Ocean's Eleven", first: 1) {
+                    edges { node { meta { imdb_id } } }
+                    page_info { has_next_page }
+                }
+                get_movie(title: "Ocean's Eleven", published_at: "2001-12-07T00:00:00Z") { meta { imdb_id } }
+                movie_by_imdb_id(imdb_id: "tt0054135") { meta { published_at } }
+                unreleased: get_movie(title: "Ocean's Eleven", published_at: "2001-01-01T00:00:00Z") { id }
+            }"#,
+        );
+        assert_eq!(
+            serde_json::json!({ "data": {
+                "movies_by_title": {
+                    "edges": [{ "node": { "meta": { "imdb_id": "tt0054135" } } }],
+                    "page_info": { "has_next_page": true }
+                },
+                "get_movie": { "meta": { "imdb_id": "tt0240772" } },
+                "movie_by_imdb_id": { "meta": { "published_at": "1960-08-10T00:00:00+00:00" } },
+                "unreleased": null
+            } }),
+            releases
+        );
+        let cursor = run(
+            &context,
+            r#"query { movies_by_title(title: "Ocean's Eleven", first: 1) { page_info { end_cursor } } }"#,
+        )["data"]["movies_by_title"]["page_info"]["end_cursor"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        let remakes = run(
+            &context,
+            &format!(
+                r#"query {{
+                    movies_by_title(title: "Ocean's Eleven", after: "{}") {{
+                        edges {{ node {{ meta {{ imdb_id }} }} }}
+                        page_info {{ has_next_page }}
+                    }}
+                }}"#,
+                cursor
+            ),
+        );
+        assert_eq!(
+            serde_json::json!({ "data": { "movies_by_title": {
+                "edges": [{ "node": { "meta": { "imdb_id": "tt0240772" } } }],
+                "page_info": { "has_next_page": false }
+            } } }),
+            remakes
+        );
+    }
+
+    #[test]
+    fn test_handler_release_calendar() {
+        let context = context();
+        for (title, published_at) in &[
+            ("Heat", "1995-12-15T00:00:00Z"),
+            ("Toy Story", "1995-11-22T00:00:00Z"),
+            ("Casino", "1995-11-22T00:00:00Z"),
+            ("Fargo", "1996-03-08T00:00:00Z"),
+            ("Se7en", "1995-09-22T00:00:00Z"),
+        ] {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ put_movie(request: {{ title: "{}", published_at: "{}", roles: [] }}) }}"#,
+                    title, published_at
+                ),
+            );
+        }
+        let query = |after: &str| {
+            format!(
+                r#"query {{
+                    movies_by_year(year: 1995, first: 2{}) {{
+                        edges {{ node {{ meta {{ title }} }} }}
+                        page_info {{ has_next_page, end_cursor }}
+                    }}
+                }}"#,
+                after
+            )
+        };
+        let mut titles = vec![];
+        let mut after = String::new();
+        loop {
+            let page = run(&context, &query(&after));
+            let connection = &page["data"]["movies_by_year"];
+            for edge in connection["edges"].as_array().unwrap() {
+                titles.push(edge["node"]["meta"]["title"].as_str().unwrap().to_owned());
+            }
+            if connection["page_info"]["has_next_page"] != true {
+                break;
+            }
+            after = format!(r#", after: {}"#, connection["page_info"]["end_cursor"]);
+        }
+        // movies released the same day are ordered arbitrarily
+        titles[1..3].sort();
+        assert_eq!(vec!["Se7en", "Casino", "Toy Story", "Heat"], titles);
+
+        let between = |after: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"query {{
+                        movies_between(from: "1995-12-01T00:00:00Z", to: "1996-12-31T00:00:00Z", first: 1{}) {{
+                            edges {{ node {{ meta {{ title }} }} }}
+                            page_info {{ has_next_page, end_cursor }}
+                        }}
+                    }}"#,
+                    after
+                ),
+            )
+        };
+        // the cursor of a page continues in the year of its last movie
+        let first = between("");
+        let connection = &first["data"]["movies_between"];
+        assert_eq!("Heat", connection["edges"][0]["node"]["meta"]["title"]);
+        assert_eq!(true, connection["page_info"]["has_next_page"]);
+        let second = between(&format!(
+            r#", after: {}"#,
+            connection["page_info"]["end_cursor"]
+        ));
+        let connection = &second["data"]["movies_between"];
+        assert_eq!("Fargo", connection["edges"][0]["node"]["meta"]["title"]);
+        assert_eq!(false, connection["page_info"]["has_next_page"]);
+        let reversed = run(
+            &context,
+            r#"query { movies_between(from: "1996-01-01T00:00:00Z", to: "1995-01-01T00:00:00Z") { page_info { has_next_page } } }"#,
+        );
+        assert_eq!("VALIDATION", reversed["errors"][0]["extensions"]["code"]);
+    }
+
+    #[test]
+    fn test_handler_search_movies() {
+        let context = context();
+        for (title, published_at) in &[
+            ("The Irishman", "2019-09-27T00:00:00Z"),
+            ("Irish Jam", "2006-01-27T00:00:00Z"),
+            ("Amélie", "2001-04-25T00:00:00Z"),
+            ("Heat", "1995-12-15T00:00:00Z"),
+        ] {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ put_movie(request: {{ title: "{}", published_at: "{}", roles: [] }}) }}"#,
+                    title, published_at
+                ),
+            );
+        }
+        let search = |query: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"query {{ search_movies(query: "{}") {{ edges {{ node {{ meta {{ title }} }} }} }} }}"#,
+                    query
+                ),
+            )
+        };
+        let titles = |result: serde_json::Value| {
+            result["data"]["search_movies"]["edges"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|edge| edge["node"]["meta"]["title"].as_str().unwrap().to_owned())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(vec!["Irish Jam", "The Irishman"], titles(search("irish")));
+        assert_eq!(vec!["The Irishman"], titles(search("The IRISHM")));
+        assert_eq!(vec!["Amélie"], titles(search("AMEL")));
+        assert_eq!(vec!["Amélie"], titles(search("amé")));
+        assert!(titles(search("casino")).is_empty());
+        assert_eq!(
+            "VALIDATION",
+            search("?!")["errors"][0]["extensions"]["code"]
+        );
+    }
+
+    #[test]
+    fn test_handler_roles_connection() {
+        let context = context();
+        run(
+            &context,
+            r#"mutation {
+                put_movie(request: {
+                    title: "Heat",
+                    imdb_id: "tt0113277",
+                    published_at: "1995-12-15T00:00:00Z",
+                    roles: [
+                        { actor_last_name: "Deniro", actor_first_name: "Robert", character_names: ["Neil McCauley"] },
+                        { actor_last_name: "Kilmer", actor_first_name: "Val", character_names: ["Chris Shiherlis"] },
+                        { actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Lt. Vincent Hanna"] }
+                    ]
+                })
+            }"#,
+        );
+
+        let query = |after: &str| {
+            format!(
+                r#"query {{
+                    get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") {{
+                        roles_connection(first: 2{}) {{
+                            edges {{ cursor, node {{ actor {{ last_name }} }} }}
+                            page_info {{ has_next_page, end_cursor }}
+                        }}
+                    }}
+                }}"#,
+                after
+            )
+        };
+        let first = run(&context, &query(""));
+        let connection = &first["data"]["get_movie"]["roles_connection"];
+        let names = |c: &serde_json::Value| {
+            c["edges"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|e| e["node"]["actor"]["last_name"].as_str().unwrap().to_owned())
+                .collect::<Vec<String>>()
+        };
+        // roles are paged in the order of the ids of their actors
+        let all = run(
+            &context,
+            r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") { roles { actor { id, last_name } } } }"#,
+        );
+        let mut expected = all["data"]["get_movie"]["roles"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["actor"]["id"].as_str().unwrap().to_owned(),
+                    r["actor"]["last_name"].as_str().unwrap().to_owned(),
+                )
+            })
+            .collect::<Vec<(String, String)>>();
+        expected.sort();
+        let expected = expected
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect::<Vec<String>>();
+        assert_eq!(expected[..2].to_vec(), names(connection));
+        assert_eq!(true, connection["page_info"]["has_next_page"]);
+        assert_eq!(
+            connection["edges"][1]["cursor"],
+            connection["page_info"]["end_cursor"]
+        );
+
+        let second = run(
+            &context,
+            &query(&format!(
+                r#", after: "{}""#,
+                connection["page_info"]["end_cursor"].as_str().unwrap()
+            )),
+        );
+        let connection = &second["data"]["get_movie"]["roles_connection"];
+        assert_eq!(expected[2..].to_vec(), names(connection));
+        assert_eq!(false, connection["page_info"]["has_next_page"]);
+    }
+
+    #[test]
+    fn test_handler_get_actor_movies() {
+        let context = context();
+        for movie in &[
+            r#"{ title: "The Irishman", imdb_id: "tt1302006", published_at: "2019-09-27T00:00:00Z", roles: [
+                { actor_last_name: "Deniro", actor_first_name: "Robert", character_names: ["Frank Sheeran"] }
+            ] }"#,
+            r#"{ title: "Heat", imdb_id: "tt0113277", published_at: "1995-12-15T00:00:00Z", roles: [
+                { actor_last_name: "Deniro", actor_first_name: "Robert", character_names: ["Neil McCauley"] },
+                { actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Lt. Vincent Hanna"] }
+            ] }"#,
+        ] {
+            run(
+                &context,
+                &format!("mutation {{ put_movie(request: {}) }}", movie),
+            );
+        }
+
+        let mut get = run(
+            &context,
+            r#"query {
+                get_actor(last_name: "Deniro", first_name: "Robert") {
+                    last_name
+                    movies { movie { meta { title } }, characters { name } }
+                }
+            }"#,
+        );
+        get["data"]["get_actor"]["movies"]
+            .as_array_mut()
+            .unwrap()
+            .sort_by_key(|m| m["movie"]["meta"]["title"].as_str().unwrap().to_owned());
+        assert_eq!(
+            serde_json::json!({ "data": { "get_actor": {
+                "last_name": "Deniro",
+                "movies": [
+                    { "movie": { "meta": { "title": "Heat" } }, "characters": [{ "name": "Neil McCauley" }] },
+                    { "movie": { "meta": { "title": "The Irishman" } }, "characters": [{ "name": "Frank Sheeran" }] }
+                ]
+            } } }),
+            get
+        );
+    }
+
+    #[test]
+    fn test_handler_characters() {
+        let context = context();
+        for movie in &[
+            r#"{ title: "The Godfather Part II", published_at: "1974-12-20T00:00:00Z", roles: [
+                { actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Michael Corleone"] },
+                { actor_last_name: "Deniro", actor_first_name: "Robert", character_names: ["Vito Corleone"] }
+            ] }"#,
+            r#"{ title: "The Godfather", published_at: "1972-03-24T00:00:00Z", roles: [
+                { actor_last_name: "Brando", actor_first_name: "Marlon", character_names: ["Vito Corleone"] },
+                { actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Michael Corleone"] }
+            ] }"#,
+        ] {
+            run(
+                &context,
+                &format!("mutation {{ put_movie(request: {}) }}", movie),
+            );
+        }
+
+        let character = |name: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"query {{ get_character(name: "{}") {{
+                        name, portrayals {{ movie {{ id, meta {{ title }} }}, actor {{ last_name }} }}
+                    }} }}"#,
+                    name
+                ),
+            )["data"]["get_character"]
+                .clone()
+        };
+        let vito = character("Vito Corleone");
+        assert_eq!(
+            serde_json::json!({
+                "name": "Vito Corleone",
+                "portrayals": [
+                    { "movie": { "id": vito["portrayals"][0]["movie"]["id"], "meta": { "title": "The Godfather" } }, "actor": { "last_name": "Brando" } },
+                    { "movie": { "id": vito["portrayals"][1]["movie"]["id"], "meta": { "title": "The Godfather Part II" } }, "actor": { "last_name": "Deniro" } }
+                ]
+            }),
+            vito
+        );
+        assert!(character("Kay Adams").is_null());
+
+        // characters of a role are followed to their other movies
+        let roles = run(
+            &context,
+            r#"query { get_movie(title: "The Godfather Part II", published_at: "1974-12-20T00:00:00Z") {
+                roles { actor { last_name }, characters { portrayals { movie { meta { title } } } } }
+            } }"#,
+        );
+        let pacino = roles["data"]["get_movie"]["roles"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["actor"]["last_name"] == "Pacino")
+            .unwrap()
+            .clone();
+        assert_eq!(
+            2,
+            pacino["characters"][0]["portrayals"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+
+        // renaming the character of a role moves the role to the new character
+        let part_two = vito["portrayals"][1]["movie"]["id"].as_str().unwrap();
+        let deniro_id = run(
+            &context,
+            r#"query { get_actor(last_name: "Deniro", first_name: "Robert") { id } }"#,
+        )["data"]["get_actor"]["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        run(
+            &context,
+            &format!(
+                r#"mutation {{ update_role_characters(movie_id: "{}", actor_id: "{}", character_names: ["Vito Andolini"]) }}"#,
+                part_two, deniro_id
+            ),
+        );
+        assert_eq!(
+            1,
+            character("Vito Corleone")["portrayals"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            "Deniro",
+            character("Vito Andolini")["portrayals"][0]["actor"]["last_name"]
+        );
+
+        run(
+            &context,
+            &format!(r#"mutation {{ delete_movie(id: "{}") }}"#, part_two),
+        );
+        // the character is kept without portrayals
+        assert_eq!(
+            0,
+            character("Vito Andolini")["portrayals"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            "The Godfather",
+            character("Michael Corleone")["portrayals"][0]["movie"]["meta"]["title"]
+        );
+
+        // characters sharing a name are referred to by id
+        let created = run(
+            &context,
+            r#"mutation { create_character(name: "Michael Corleone") { id } }"#,
+        );
+        let character_id = created["data"]["create_character"]["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        let ambiguous = run(
+            &context,
+            r#"query { get_character(name: "Michael Corleone") { name } }"#,
+        );
+        assert_eq!("CONFLICT", ambiguous["errors"][0]["extensions"]["code"]);
+        let series_id = run(
+            &context,
+            r#"mutation { create_series(request: { title: "Corleone", published_at: "1990-01-01T00:00:00Z" }) { id } }"#,
+        )["data"]["create_series"]["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        run(
+            &context,
+            &format!(
+                r#"mutation {{ put_season(series_id: "{}", request: {{ number: 1 }}) }}"#,
+                series_id
+            ),
+        );
+        let episode = |roles: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ put_episode(series_id: "{}", request: {{ season_number: 1, episode_number: 1, title: "Pilot", published_at: "1990-01-01T00:00:00Z", roles: [{}] }}) }}"#,
+                    series_id, roles
+                ),
+            )
+        };
+        let named = episode(
+            r#"{ actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Michael Corleone"] }"#,
+        );
+        assert_eq!("CONFLICT", named["errors"][0]["extensions"]["code"]);
+        let response = episode(&format!(
+            r#"{{ actor_last_name: "Pacino", actor_first_name: "Al", character_names: [], character_ids: ["{}"] }}"#,
+            character_id
+        ));
+        assert!(response["errors"].is_null(), "{}", response);
+        let portrayals = run(
+            &context,
+            &format!(
+                r#"query {{ character(id: "{}") {{
+                    name, portrayals {{ title {{ title }}, movie {{ id }}, actor {{ last_name }} }}
+                }} }}"#,
+                character_id
+            ),
+        );
+        assert_eq!(
+            serde_json::json!({
+                "name": "Michael Corleone",
+                "portrayals": [
+                    { "title": { "title": "Pilot" }, "movie": null, "actor": { "last_name": "Pacino" } }
+                ]
+            }),
+            portrayals["data"]["character"]
+        );
+    }
+
+    #[test]
+    fn test_handler_collections() {
+        let context = context();
+        let mut movie_ids = vec![];
+        for (title, published_at) in &[
+            ("The Godfather Part III", "1990-12-25T00:00:00Z"),
+            ("The Godfather", "1972-03-24T00:00:00Z"),
+            ("The Godfather Part II", "1974-12-20T00:00:00Z"),
+        ] {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ put_movie(request: {{ title: "{}", published_at: "{}", roles: [] }}) }}"#,
+                    title, published_at
+                ),
+            );
+            let movie = run(
+                &context,
+                &format!(
+                    r#"query {{ get_movie(title: "{}", published_at: "{}") {{ id }} }}"#,
+                    title, published_at
+                ),
+            );
+            movie_ids.push(
+                movie["data"]["get_movie"]["id"]
+                    .as_str()
+                    .unwrap()
+                    .to_owned(),
+            );
+        }
+
+        let created = run(
+            &context,
+            r#"mutation { create_collection(request: { name: "The Godfather Trilogy" }) { id, version, name } }"#,
+        );
+        let collection_id = created["data"]["create_collection"]["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(1, created["data"]["create_collection"]["version"]);
+
+        let add = |movie_id: &str, position: &str| {
+            let response = run(
+                &context,
+                &format!(
+                    r#"mutation {{ add_to_collection(collection_id: "{}", movie_id: "{}"{}) }}"#,
+                    collection_id, movie_id, position
+                ),
+            );
+            assert!(response["errors"].is_null(), "{}", response);
+        };
+        // appended in the order they are added, then the third one is moved to the end
+        add(&movie_ids[1], "");
+        add(&movie_ids[0], "");
+        add(&movie_ids[2], "");
+        add(&movie_ids[0], ", position: 3");
+        add(&movie_ids[2], ", position: 2");
+
+        let collection = run(
+            &context,
+            &format!(
+                r#"query {{ get_collection(id: "{}") {{ name, version, movies {{ position, movie {{ meta {{ title }} }} }} }} }}"#,
+                collection_id
+            ),
+        );
+        assert_eq!(
+            serde_json::json!({ "data": { "get_collection": {
+                "name": "The Godfather Trilogy",
+                "version": 6,
+                "movies": [
+                    { "position": 1, "movie": { "meta": { "title": "The Godfather" } } },
+                    { "position": 2, "movie": { "meta": { "title": "The Godfather Part II" } } },
+                    { "position": 3, "movie": { "meta": { "title": "The Godfather Part III" } } }
+                ]
+            } } }),
+            collection
+        );
+
+        let movie = run(
+            &context,
+            &format!(
+                r#"query {{ movie(id: "{}") {{ collections {{ name }} }} }}"#,
+                movie_ids[0]
+            ),
+        );
+        assert_eq!(
+            serde_json::json!({ "data": { "movie": { "collections": [{ "name": "The Godfather Trilogy" }] } } }),
+            movie
+        );
+
+        // adding at a taken position moves the movies from there on back
+        add(&movie_ids[0], ", position: 1");
+        let collection = run(
+            &context,
+            &format!(
+                r#"query {{ get_collection(id: "{}") {{ version, movies {{ position, movie {{ meta {{ title }} }} }} }} }}"#,
+                collection_id
+            ),
+        );
+        assert_eq!(
+            serde_json::json!({ "data": { "get_collection": {
+                "version": 7,
+                "movies": [
+                    { "position": 1, "movie": { "meta": { "title": "The Godfather Part III" } } },
+                    { "position": 2, "movie": { "meta": { "title": "The Godfather" } } },
+                    { "position": 3, "movie": { "meta": { "title": "The Godfather Part II" } } }
+                ]
+            } } }),
+            collection
+        );
+
+        let invalid = run(
+            &context,
+            &format!(
+                r#"mutation {{ add_to_collection(collection_id: "{}", movie_id: "{}", position: 0) }}"#,
+                collection_id, movie_ids[0]
+            ),
+        );
+        assert_eq!("VALIDATION", invalid["errors"][0]["extensions"]["code"]);
+
+        run(
+            &context,
+            &format!(r#"mutation {{ delete_movie(id: "{}") }}"#, movie_ids[0]),
+        );
+        let collection = run(
+            &context,
+            &format!(
+                r#"query {{ get_collection(id: "{}") {{ version, movies {{ position }} }} }}"#,
+                collection_id
+            ),
+        );
+        assert_eq!(8, collection["data"]["get_collection"]["version"]);
+        assert_eq!(
+            2,
+            collection["data"]["get_collection"]["movies"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_handler_series() {
+        let context = context();
+        run(
+            &context,
+            r#"mutation { put_movie(request: { title: "Heat", published_at: "1995-12-15T00:00:00Z", roles: [{ actor_last_name: "Gandolfini", actor_first_name: "James", character_names: ["Albert Torena"] }] }) }"#,
+        );
+        let created = run(
+            &context,
+            r#"mutation { create_series(request: { title: "The Sopranos", published_at: "1999-01-10T00:00:00Z" }) { id, version } }"#,
+        );
+        let series_id = created["data"]["create_series"]["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(1, created["data"]["create_series"]["version"]);
+
+        // the season has to exist before its episodes
+        let episode = |season: i32, number: i32, title: &str, published_at: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ put_episode(series_id: "{}", request: {{ season_number: {}, episode_number: {}, title: "{}", published_at: "{}", roles: [{{ actor_last_name: "Gandolfini", actor_first_name: "James", character_names: ["Tony Soprano"], billing_order: 1 }}] }}) }}"#,
+                    series_id, season, number, title, published_at
+                ),
+            )
+        };
+        let missing = episode(1, 1, "The Sopranos", "1999-01-10T00:00:00Z");
+        assert_eq!("NOT_FOUND", missing["errors"][0]["extensions"]["code"]);
+        let response = run(
+            &context,
+            &format!(
+                r#"mutation {{ put_season(series_id: "{}", request: {{ number: 1 }}, expected_version: 1) }}"#,
+                series_id
+            ),
+        );
+        assert!(response["errors"].is_null(), "{}", response);
+        for (number, title, published_at) in &[
+            (2, "46 Long", "1999-01-17T00:00:00Z"),
+            (1, "The Sopranos", "1999-01-10T00:00:00Z"),
+        ] {
+            let response = episode(1, *number, title, published_at);
+            assert!(response["errors"].is_null(), "{}", response);
+        }
+        let invalid = episode(0, 1, "The Sopranos", "1999-01-10T00:00:00Z");
+        assert_eq!("VALIDATION", invalid["errors"][0]["extensions"]["code"]);
+
+        let series = run(
+            &context,
+            &format!(
+                r#"query {{ series(id: "{}") {{ title, version, seasons {{ number, episodes {{ episode_number, title, series {{ title }}, roles {{ actor {{ last_name }}, characters {{ name }} }} }} }} }} }}"#,
+                series_id
+            ),
+        );
+        let roles = serde_json::json!([{ "actor": { "last_name": "Gandolfini" }, "characters": [{ "name": "Tony Soprano" }] }]);
+        // only the season changed the series, the episodes do not
+        assert_eq!(
+            serde_json::json!({ "data": { "series": {
+                "title": "The Sopranos",
+                "version": 2,
+                "seasons": [{
+                    "number": 1,
+                    "episodes": [
+                        { "episode_number": 1, "title": "The Sopranos", "series": { "title": "The Sopranos" }, "roles": roles },
+                        { "episode_number": 2, "title": "46 Long", "series": { "title": "The Sopranos" }, "roles": roles }
+                    ]
+                }]
+            } } }),
+            series
+        );
+
+        // movies and episodes share the actor and are listed together
+        let actor = run(
+            &context,
+            r#"query { get_actor(last_name: "Gandolfini", first_name: "James") { filmography { title { __typename, title, published_at }, characters { name } } } }"#,
+        );
+        assert_eq!(
+            serde_json::json!({ "data": { "get_actor": { "filmography": [
+                { "title": { "__typename": "Movie", "title": "Heat", "published_at": "1995-12-15T00:00:00+00:00" }, "characters": [{ "name": "Albert Torena" }] },
+                { "title": { "__typename": "Episode", "title": "The Sopranos", "published_at": "1999-01-10T00:00:00+00:00" }, "characters": [{ "name": "Tony Soprano" }] },
+                { "title": { "__typename": "Episode", "title": "46 Long", "published_at": "1999-01-17T00:00:00+00:00" }, "characters": [{ "name": "Tony Soprano" }] }
+            ] } } }),
+            actor
+        );
+
+        let episodes = |season: i32, after: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"query {{ series(id: "{}") {{ episodes_connection(season: {}, first: 1{}) {{
+                        edges {{ node {{ title }} }}, page_info {{ has_next_page, end_cursor }}
+                    }} }} }}"#,
+                    series_id, season, after
+                ),
+            )
+        };
+        let first = episodes(1, "");
+        let connection = &first["data"]["series"]["episodes_connection"];
+        assert_eq!("The Sopranos", connection["edges"][0]["node"]["title"]);
+        assert_eq!(true, connection["page_info"]["has_next_page"]);
+        let after = format!(", after: {}", connection["page_info"]["end_cursor"]);
+        let second = episodes(1, &after);
+        assert_eq!(
+            serde_json::json!({
+                "edges": [{ "node": { "title": "46 Long" } }],
+                "page_info": { "has_next_page": false, "end_cursor": second["data"]["series"]["episodes_connection"]["page_info"]["end_cursor"] }
+            }),
+            second["data"]["series"]["episodes_connection"]
+        );
+        // a cursor only continues the query it was returned by
+        let foreign = episodes(2, &after);
+        assert_eq!("VALIDATION", foreign["errors"][0]["extensions"]["code"]);
+
+        let episode = run(
+            &context,
+            &format!(
+                r#"query {{ episode(series_id: "{}", season_number: 1, episode_number: 3) {{ title }} }}"#,
+                series_id
+            ),
+        );
+        assert_eq!(serde_json::json!({ "data": { "episode": null } }), episode);
+    }
+
+    #[test]
+    fn test_handler_reviews() {
+        let context = context();
+        let put = r#"mutation { put_movie(request: { title: "Heat", published_at: "1995-12-15T00:00:00Z", roles: [] }) }"#;
+        run(&context, put);
+        let get = run(
+            &context,
+            r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") { id } }"#,
+        );
+        let movie_id = get["data"]["get_movie"]["id"].as_str().unwrap().to_owned();
+
+        let rate = |user_id: &str, rating: i32, expected_version: i32| {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ rate_movie(movie_id: "{}", user_id: "{}", rating: {}, expected_version: {}) {{ version, rating, text }} }}"#,
+                    movie_id, user_id, rating, expected_version
+                ),
+            )
+        };
+        let review = run(
+            &context,
+            &format!(
+                r#"mutation {{ review_movie(movie_id: "{}", user_id: "neil", request: {{ rating: 6, text: "Too long" }}, expected_version: 0) {{ version, rating, text }} }}"#,
+                movie_id
+            ),
+        );
+        assert_eq!(
+            serde_json::json!({ "data": { "review_movie": { "version": 1, "rating": 6, "text": "Too long" } } }),
+            review
+        );
+        assert!(rate("vincent", 9, 0)["errors"].is_null());
+        // a new rating replaces the earlier one and keeps the text of the review
+        assert_eq!(
+            serde_json::json!({ "data": { "rate_movie": { "version": 2, "rating": 8, "text": "Too long" } } }),
+            rate("neil", 8, 1)
+        );
+        let stale = rate("neil", 7, 1);
+        assert_eq!(
+            "CONCURRENT_MODIFICATION",
+            stale["errors"][0]["extensions"]["code"]
+        );
+        let invalid = rate("neil", 11, 2);
+        assert_eq!("VALIDATION", invalid["errors"][0]["extensions"]["code"]);
+
+        // ratings do not change the version of the movie, submitting the movie again keeps
+        // its reviews and ratings
+        run(&context, put);
+        let movie = run(
+            &context,
+            &format!(
+                r#"query {{ movie(id: "{}") {{ version, rating_summary {{ count, mean, histogram {{ rating, count }} }}, reviews {{ user_id, rating, text }} }} }}"#,
+                movie_id
+            ),
+        );
+        let histogram = (1..=10)
+            .map(|rating| {
+                let count = if rating == 8 || rating == 9 { 1 } else { 0 };
+                serde_json::json!({ "rating": rating, "count": count })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            serde_json::json!({ "data": { "movie": {
+                "version": 2,
+                "rating_summary": { "count": 2, "mean": 8.5, "histogram": histogram },
+                "reviews": [
+                    { "user_id": "neil", "rating": 8, "text": "Too long" },
+                    { "user_id": "vincent", "rating": 9, "text": null }
+                ]
+            } } }),
+            movie
+        );
+
+        // the connection pages the reviews in the order of the users
+        let page = |after: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"query {{ movie(id: "{}") {{ reviews_connection(first: 1{}) {{ edges {{ node {{ user_id }} }}, page_info {{ has_next_page, end_cursor }} }} }} }}"#,
+                    movie_id, after
+                ),
+            )
+        };
+        let first = page("");
+        let connection = &first["data"]["movie"]["reviews_connection"];
+        assert_eq!("neil", connection["edges"][0]["node"]["user_id"]);
+        assert_eq!(true, connection["page_info"]["has_next_page"]);
+        let cursor = connection["page_info"]["end_cursor"].as_str().unwrap();
+        let second = page(&format!(r#", after: "{}""#, cursor));
+        let connection = &second["data"]["movie"]["reviews_connection"];
+        assert_eq!("vincent", connection["edges"][0]["node"]["user_id"]);
+        assert_eq!(false, connection["page_info"]["has_next_page"]);
+    }
+
+    #[test]
+    fn test_handler_lookup_by_id() {
+        let context = context();
+        let put = r#"mutation {
+            put_movie(request: {
+                title: "Heat",
+                imdb_id: "tt0113277",
+                published_at: "1995-12-15T00:00:00Z",
+                roles: [
+                    { actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Lt. Vincent Hanna"] }
+                ]
+            })
+        }"#;
+        let ids = |context: &Environment| {
+            let get = run(
+                context,
+                r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") { id, roles { actor { id } } } }"#,
+            );
+            let movie = &get["data"]["get_movie"];
+            (
+                movie["id"].as_str().unwrap().to_owned(),
+                movie["roles"][0]["actor"]["id"]
+                    .as_str()
+                    .unwrap()
+                    .to_owned(),
+            )
+        };
+        run(&context, put);
+        let (movie_id, actor_id) = ids(&context);
+        // submitting the same movie again keeps the ids of the movie and its actors
+        run(&context, put);
+        assert_eq!((movie_id.clone(), actor_id.clone()), ids(&context));
+
+        let movie = run(
+            &context,
+            &format!(
+                r#"query {{ movie(id: "{}") {{ meta {{ title }} }} }}"#,
+                movie_id
+            ),
+        );
+        assert_eq!("Heat", movie["data"]["movie"]["meta"]["title"]);
+        let actor = run(
+            &context,
+            &format!(r#"query {{ actor(id: "{}") {{ last_name }} }}"#, actor_id),
+        );
+        assert_eq!("Pacino", actor["data"]["actor"]["last_name"]);
+        let invalid = run(&context, r#"query { movie(id: "Heat") { id } }"#);
+        assert_eq!("invalid id", invalid["errors"][0]["message"]);
+        assert_eq!("VALIDATION", invalid["errors"][0]["extensions"]["code"]);
+        let missing = run(
+            &context,
+            &format!(
+                r#"mutation {{ delete_movie(id: "{}") }}"#,
+                uuid::Uuid::nil()
+            ),
+        );
+        assert_eq!("NOT_FOUND", missing["errors"][0]["extensions"]["code"]);
+
+        // a second movie with the same title and release date can only be read by id
+        let twin = run(
+            &context,
+            &format!(
+                r#"mutation {{ put_movie(request: {{ id: "{}", title: "Heat", published_at: "1995-12-15T00:00:00Z", roles: [] }}) }}"#,
+                uuid::Uuid::from_u128(1)
+            ),
+        );
+        assert!(twin["errors"].is_null(), "{}", twin);
+        let ambiguous = run(
+            &context,
+            r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") { id } }"#,
+        );
+        assert_eq!("CONFLICT", ambiguous["errors"][0]["extensions"]["code"]);
+        let replaced = run(&context, put);
+        assert_eq!("CONFLICT", replaced["errors"][0]["extensions"]["code"]);
+    }
+
+    #[test]
+    fn test_handler_put_movie_validation() {
+        let context = context();
+        let invalid = run(
+            &context,
+            r#"mutation {
+                put_movie(request: {
+                    title: "  ",
+                    imdb_id: "0113277",
+                    published_at: "1795-12-15T00:00:00Z",
+                    roles: [
+                        { actor_last_name: "Pacino", actor_first_name: "Al", character_names: [] },
+                        { actor_last_name: "Pacino", actor_first_name: "Al", character_names: [""] }
+                    ]
+                })
+            }"#,
+        );
+        assert_eq!("VALIDATION", invalid["errors"][0]["extensions"]["code"]);
+        let fields = invalid["errors"][0]["extensions"]["violations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|violation| violation["field"].as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec![
+                "title",
+                "imdb_id",
+                "published_at",
+                "roles[0].character_names",
+                "roles[1]",
+                "roles[1].character_names[0]"
+            ],
+            fields
+        );
+        let missing = run(
+            &context,
+            r#"query { movies_by_year(year: 1795) { edges { cursor } } }"#,
+        );
+        assert_eq!(
+            serde_json::json!([]),
+            missing["data"]["movies_by_year"]["edges"]
+        );
+    }
+
+    #[test]
+    fn test_handler_names_with_delimiters() {
+        let context = context();
+        run(
+            &context,
+            r#"mutation {
+                put_movie(request: {
+                    title: "Mission: Impossible #1",
+                    imdb_id: "tt0117060",
+                    published_at: "1996-05-22T00:00:00Z",
+                    roles: [
+                        { actor_last_name: "Van Buren", actor_first_name: "Anna", character_names: ["Someone"] }
+                    ]
+                })
+            }"#,
+        );
+        let movie = run(
+            &context,
+            r#"query { get_movie(title: "Mission: Impossible #1", published_at: "1996-05-22T00:00:00Z") { meta { title } } }"#,
+        );
+        assert_eq!(
+            "Mission: Impossible #1",
+            movie["data"]["get_movie"]["meta"]["title"]
+        );
+        let actor = run(
+            &context,
+            r#"query { get_actor(last_name: "Van Buren", first_name: "Anna") { first_name } }"#,
+        );
+        assert_eq!("Anna", actor["data"]["get_actor"]["first_name"]);
+        let ambiguous = run(
+            &context,
+            r#"query { get_actor(last_name: "Van", first_name: "Buren Anna") { first_name } }"#,
+        );
+        assert_eq!(serde_json::Value::Null, ambiguous["data"]["get_actor"]);
+    }
+
+    #[test]
+    fn test_handler_actor_mutations() {
+        let context = context();
+        let create = r#"mutation {
+            create_actor(request: {
+                last_name: "Pacino",
+                first_name: "Al",
+                birth_date: "1940-04-25",
+                imdb_id: "nm0000199",
+                aliases: ["Alfredo James Pacino"]
+            }) { id, version }
+        }"#;
+        let created = run(&context, create);
+        let actor_id = created["data"]["create_actor"]["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(1, created["data"]["create_actor"]["version"]);
+
+        let updated = run(
+            &context,
+            &format!(
+                r#"mutation {{ update_actor(id: "{}", request: {{ biography: "Actor from New York." }}, expected_version: 1) }}"#,
+                actor_id
+            ),
+        );
+        assert!(updated["errors"].is_null(), "{}", updated);
+        let put = |role: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ put_movie(request: {{
+                        title: "Heat", published_at: "1995-12-15T00:00:00Z", roles: [{}]
+                    }}) }}"#,
+                    role
+                ),
+            )
+        };
+        put(&format!(
+            r#"{{ actor_id: "{}", character_names: ["Lt. Vincent Hanna"] }}"#,
+            actor_id
+        ));
+        let heat = run(
+            &context,
+            r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") {
+                roles { actor { id, birth_date, imdb_id, biography, aliases } }
+            } }"#,
+        );
+        assert_eq!(
+            serde_json::json!({ "actor": {
+                "id": actor_id,
+                "birth_date": "1940-04-25",
+                "imdb_id": "nm0000199",
+                "biography": "Actor from New York.",
+                "aliases": ["Alfredo James Pacino"]
+            } }),
+            heat["data"]["get_movie"]["roles"][0]
+        );
+
+        let both = put(&format!(
+            r#"{{ actor_id: "{}", actor_last_name: "Pacino", character_names: ["Hanna"] }}"#,
+            actor_id
+        ));
+        assert_eq!(
+            "roles[0]",
+            both["errors"][0]["extensions"]["violations"][0]["field"]
+        );
+        // the same actor by id and by name
+        let twice = put(&format!(
+            r#"{{ actor_id: "{}", character_names: ["Hanna"] }},
+               {{ actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Hanna"] }}"#,
+            actor_id
+        ));
+        assert_eq!(
+            "roles[1]",
+            twice["errors"][0]["extensions"]["violations"][0]["field"]
+        );
+        let missing = put(&format!(
+            r#"{{ actor_id: "{}", character_names: ["Hanna"] }}"#,
+            uuid::Uuid::nil()
+        ));
+        assert_eq!("NOT_FOUND", missing["errors"][0]["extensions"]["code"]);
+
+        let update = |request: &str, expected_version: i32| {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ update_actor(id: "{}", request: {{ {} }}, expected_version: {}) }}"#,
+                    actor_id, request, expected_version
+                ),
+            )
+        };
+        let both = update(r#"biography: "Actor.", clear: [BIOGRAPHY]"#, 2);
+        assert_eq!(
+            "clear",
+            both["errors"][0]["extensions"]["violations"][0]["field"]
+        );
+        let cleared = update("clear: [BIRTH_DATE, BIOGRAPHY]", 2);
+        assert!(cleared["errors"].is_null(), "{}", cleared);
+        let actor = run(
+            &context,
+            r#"query {
+                get_actor(last_name: "Pacino", first_name: "Al") { birth_date, imdb_id, biography }
+                movie_by_imdb_id(imdb_id: "nm0000199") { id }
+            }"#,
+        );
+        assert_eq!(
+            serde_json::json!({ "data": {
+                "get_actor": { "birth_date": null, "imdb_id": "nm0000199", "biography": null },
+                "movie_by_imdb_id": null
+            } }),
+            actor
+        );
+
+        // actors may share a name, naming them is ambiguous then
+        let namesake = run(&context, create);
+        assert!(namesake["errors"].is_null(), "{}", namesake);
+        assert_ne!(actor_id, namesake["data"]["create_actor"]["id"]);
+        let ambiguous = run(
+            &context,
+            r#"query { get_actor(last_name: "Pacino", first_name: "Al") { id } }"#,
+        );
+        assert_eq!("CONFLICT", ambiguous["errors"][0]["extensions"]["code"]);
+        let ambiguous = put(
+            r#"{ actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Hanna"] }"#,
+        );
+        assert_eq!("CONFLICT", ambiguous["errors"][0]["extensions"]["code"]);
+        let by_id = put(&format!(
+            r#"{{ actor_id: "{}", character_names: ["Lt. Vincent Hanna"] }}"#,
+            actor_id
+        ));
+        assert!(by_id["errors"].is_null(), "{}", by_id);
+    }
+
+    #[test]
+    fn test_handler_crud_mutations() {
+        let context = context();
+        let put = |roles: &str| {
+            format!(
+                r#"mutation {{ put_movie(request: {{
+                    title: "Heat", imdb_id: "tt0113277", published_at: "1995-12-15T00:00:00Z", roles: [{}]
+                }}) }}"#,
+                roles
+            )
+        };
+        run(
+            &context,
+            &put(r#"
+                { actor_last_name: "Deniro", actor_first_name: "Robert", character_names: ["Neil McCauley"] },
+                { actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Lt. Vincent Hanna"] }
+            "#),
+        );
+        // re-submitting with a smaller cast removes the role that is gone
+        run(
+            &context,
+            &put(
+                r#"{ actor_last_name: "Pacino", actor_first_name: "Al", character_names: ["Lt. Vincent Hanna"] }"#,
+            ),
+        );
+        let movie = || {
+            run(
+                &context,
+                r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") {
+                    id, meta { imdb_id }, roles { actor { id, last_name }, characters { name } }
+                } }"#,
+            )["data"]["get_movie"]
+                .clone()
+        };
+        let heat = movie();
+        assert_eq!(1, heat["roles"].as_array().unwrap().len());
+        let movie_id = heat["id"].as_str().unwrap().to_owned();
+        let pacino_id = heat["roles"][0]["actor"]["id"].as_str().unwrap().to_owned();
+        let deniro_id = run(
+            &context,
+            r#"query { get_actor(last_name: "Deniro", first_name: "Robert") { id } }"#,
+        )["data"]["get_actor"]["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+
+        let mutate = |mutation: String| {
+            let response = run(&context, &format!("mutation {{ {} }}", mutation));
+            assert!(response["errors"].is_null(), "{}", response);
+        };
+        mutate(format!(
+            r#"update_movie_metadata(id: "{}", request: {{ imdb_id: "tt0000001" }})"#,
+            movie_id
+        ));
+        mutate(format!(
+            r#"update_role_characters(movie_id: "{}", actor_id: "{}", character_names: ["Vincent Hanna"])"#,
+            movie_id, pacino_id
+        ));
+        mutate(format!(
+            r#"add_role(movie_id: "{}", actor_id: "{}", character_names: ["Neil McCauley"])"#,
+            movie_id, deniro_id
+        ));
+        mutate(format!(
+            r#"update_actor(id: "{}", request: {{ last_name: "De Niro" }})"#,
+            deniro_id
+        ));
+        let again = run(
+            &context,
+            &format!(
+                r#"mutation {{ add_role(movie_id: "{}", actor_id: "{}", character_names: ["Neil"]) }}"#,
+                movie_id, deniro_id
+            ),
+        );
+        assert_eq!("CONFLICT", again["errors"][0]["extensions"]["code"]);
+        let credits = || {
+            run(
+                &context,
+                r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") {
+                    roles { actor { last_name }, billing_order, credited_as, voice }
+                } }"#,
+            )["data"]["get_movie"]["roles"]
+                .clone()
+        };
+        mutate(format!(
+            r#"update_role_characters(movie_id: "{}", actor_id: "{}", character_names: ["Vincent Hanna"], credit: {{ billing_order: 2 }})"#,
+            movie_id, pacino_id
+        ));
+        mutate(format!(
+            r#"remove_role(movie_id: "{}", actor_id: "{}")"#,
+            movie_id, deniro_id
+        ));
+        mutate(format!(
+            r#"add_role(movie_id: "{}", actor_id: "{}", character_names: ["Neil McCauley"], credit: {{ billing_order: 1, credited_as: "Robert De Niro" }})"#,
+            movie_id, deniro_id
+        ));
+        assert_eq!(
+            serde_json::json!([
+                { "actor": { "last_name": "De Niro" }, "billing_order": 1, "credited_as": "Robert De Niro", "voice": false },
+                { "actor": { "last_name": "Pacino" }, "billing_order": 2, "credited_as": null, "voice": false }
+            ]),
+            credits()
+        );
+        // fields of the credit that are not given are kept
+        mutate(format!(
+            r#"update_role_characters(movie_id: "{}", actor_id: "{}", character_names: ["Neil McCauley"], credit: {{ voice: true, clear: [CREDITED_AS] }})"#,
+            movie_id, deniro_id
+        ));
+        assert_eq!(
+            serde_json::json!({ "actor": { "last_name": "De Niro" }, "billing_order": 1, "credited_as": null, "voice": true }),
+            credits()[0]
+        );
+        let both = run(
+            &context,
+            &format!(
+                r#"mutation {{ update_role_characters(movie_id: "{}", actor_id: "{}", character_names: ["Neil"], credit: {{ billing_order: 3, clear: [BILLING_ORDER] }}) }}"#,
+                movie_id, deniro_id
+            ),
+        );
+        assert_eq!(
+            "credit.clear",
+            both["errors"][0]["extensions"]["violations"][0]["field"]
+        );
+        let heat = movie();
+        assert_eq!("tt0000001", heat["meta"]["imdb_id"]);
+        let mut roles = heat["roles"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                format!(
+                    "{}: {}",
+                    r["actor"]["last_name"].as_str().unwrap(),
+                    r["characters"][0]["name"].as_str().unwrap()
+                )
+            })
+            .collect::<Vec<String>>();
+        roles.sort();
+        assert_eq!(
+            vec!["De Niro: Neil McCauley", "Pacino: Vincent Hanna"],
+            roles
+        );
+
+        mutate(format!(
+            r#"update_movie_metadata(id: "{}", request: {{ clear: [IMDB_ID] }})"#,
+            movie_id
+        ));
+        assert!(movie()["meta"]["imdb_id"].is_null());
+
+        mutate(format!(
+            r#"remove_role(movie_id: "{}", actor_id: "{}")"#,
+            movie_id, pacino_id
+        ));
+        mutate(format!(r#"delete_actor(id: "{}")"#, deniro_id));
+        assert_eq!(0, movie()["roles"].as_array().unwrap().len());
+        let pacino = run(
+            &context,
+            &format!(
+                r#"query {{ actor(id: "{}") {{ movies {{ characters {{ name }} }} }} }}"#,
+                pacino_id
+            ),
+        );
+        assert_eq!(
+            0,
+            pacino["data"]["actor"]["movies"].as_array().unwrap().len()
+        );
+
+        mutate(format!(r#"delete_movie(id: "{}")"#, movie_id));
+        assert!(movie().is_null());
+        let removed = run(
+            &context,
+            &format!(
+                r#"mutation {{ remove_role(movie_id: "{}", actor_id: "{}") }}"#,
+                movie_id, pacino_id
+            ),
+        );
+        assert!(removed["errors"].is_array());
+    }
+
+    #[test]
+    fn test_handler_concurrent_modification() {
+        let context = context();
+        let put = |expected_version: &str| {
+            run(
+                &context,
+                &format!(
+                    r#"mutation {{ put_movie(request: {{
+                        title: "Heat", published_at: "1995-12-15T00:00:00Z", imdb_id: "tt0113277",
+                        roles: [], expected_version: {}
+                    }}) }}"#,
+                    expected_version
+                ),
+            )
+        };
+        let version = || {
+            run(
+                &context,
+                r#"query { get_movie(title: "Heat", published_at: "1995-12-15T00:00:00Z") { version } }"#,
+            )["data"]["get_movie"]["version"]
+                .clone()
+        };
+        assert_eq!(
+            serde_json::json!({ "data": { "put_movie": true } }),
+            put("0")
+        );
+        assert_eq!(1, version());
+        assert_eq!(
+            serde_json::json!({ "data": { "put_movie": true } }),
+            put("1")
+        );
+        assert_eq!(2, version());
+
+        // a client still holding version 1 must not overwrite the movie
+        let stale = put("1");
+        assert_eq!(
+            "CONCURRENT_MODIFICATION",
+            stale["errors"][0]["extensions"]["code"]
+        );
+        assert_eq!(2, version());
+    }
+}
@@ -0,0 +1,1350 @@
+use crate::config::UnknownAttributes;
+use crate::data::{
+    Actor, Character, Collection, Credit, CrewCredit, Episode, Movie, MovieMetadata, Production,
+    Review, Role, Season, Series,
+};
+use crate::dynamo::*;
+use crate::error::{Error, Result};
+use crate::pagination::{decode_cursor, Key, Page};
+use crate::storage::{
+    decode_between_cursor, inverted_cursor, movies_cursor, partition_cursor, role_conflict,
+    search_cursor, title_cursor, year_cursor, MovieStore, SEARCH_INDEX_KEY, TABLE_KEY,
+    TITLE_INDEX_KEY, YEAR_INDEX_KEY,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Utc};
+use rusoto_dynamodb::AttributeValue;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+use uuid::Uuid;
+
+type Item = HashMap<String, AttributeValue>;
+
+/// A `MovieStore` keeping the serialized table items in memory.
+/// Items are stored under the same pk/sk layout as in DynamoDB, partitions are
+/// keyed by pk and sorted by sk so that `begins_with` queries behave the same.
+/// Rows with legacy keys are skipped by the emulated indexes like by the DynamoDB reads.
+#[derive(Default)]
+pub struct InMemoryMovieStore {
+    partitions: Mutex<HashMap<String, BTreeMap<String, Item>>>,
+    unknown_attributes: UnknownAttributes,
+}
+
+impl InMemoryMovieStore {
+    pub fn new() -> Self {
+        Self::with_unknown_attributes(UnknownAttributes::default())
+    }
+
+    pub fn with_unknown_attributes(unknown_attributes: UnknownAttributes) -> Self {
+        InMemoryMovieStore {
+            partitions: Mutex::new(HashMap::new()),
+            unknown_attributes,
+        }
+    }
+
+    fn parse(&self, item: Item) -> Result<DynamoTableItem> {
+        DynamoTableItem::from_item(item, self.unknown_attributes)
+    }
+
+    fn get_item(&self, pk: &str, sk: &str) -> Option<Item> {
+        let partitions = self.partitions.lock().unwrap();
+        partitions.get(pk).and_then(|p| p.get(sk)).cloned()
+    }
+
+    fn query(&self, pk: &str, sk_prefix: &str) -> Vec<Item> {
+        self.query_page(pk, sk_prefix, None, usize::MAX)
+    }
+
+    /// Reads up to `limit` items of a partition whose sk starts with `sk_prefix`,
+    /// starting after the sk of `exclusive_start_key` like a DynamoDB query does.
+    fn query_page(
+        &self,
+        pk: &str,
+        sk_prefix: &str,
+        exclusive_start_key: Option<&Key>,
+        limit: usize,
+    ) -> Vec<Item> {
+        let start = exclusive_start_key
+            .and_then(|k| k.get("sk"))
+            .and_then(|sk| sk.s.clone());
+        let partitions = self.partitions.lock().unwrap();
+        match partitions.get(pk) {
+            Some(p) => p
+                .range(sk_prefix.to_owned()..)
+                .take_while(|(sk, _)| sk.starts_with(sk_prefix))
+                .filter(|(sk, _)| start.as_ref().is_none_or(|start| *sk > start))
+                .take(limit)
+                .map(|(_, item)| item.clone())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Emulates a query on the inverted index, reading up to `limit` items with the given
+    /// sk whose pk starts with `pk_prefix`, ordered by pk.
+    fn query_inverted_page(
+        &self,
+        sk: &str,
+        pk_prefix: &str,
+        exclusive_start_key: Option<&Key>,
+        limit: usize,
+    ) -> Vec<Item> {
+        let start = exclusive_start_key
+            .and_then(|k| k.get("pk"))
+            .and_then(|pk| pk.s.clone());
+        let partitions = self.partitions.lock().unwrap();
+        let mut pks = partitions
+            .keys()
+            .filter(|pk| pk.starts_with(pk_prefix))
+            .filter(|pk| start.as_ref().is_none_or(|start| *pk > start))
+            .collect::<Vec<&String>>();
+        pks.sort();
+        pks.into_iter()
+            .filter_map(|pk| partitions[pk].get(sk).cloned())
+            .take(limit)
+            .collect()
+    }
+
+    /// Emulates a query on the search index: the movies whose normalized title starts with
+    /// the normalized prefix, ordered by normalized title.
+    fn query_search_page(
+        &self,
+        prefix: &str,
+        exclusive_start_key: Option<&Key>,
+        limit: usize,
+    ) -> Vec<Item> {
+        let sort_key = |item: &Item| {
+            let attribute = |name: &str| item.get(name).and_then(|a| a.s.clone());
+            (attribute("search_title"), attribute("pk"))
+        };
+        let start = exclusive_start_key.map(sort_key);
+        let partitions = self.partitions.lock().unwrap();
+        let mut items = partitions
+            .values()
+            .flat_map(|p| p.values())
+            .filter(|item| !DynamoTableItem::has_legacy_key(item))
+            .filter(|item| {
+                item.get("search_title")
+                    .and_then(|t| t.s.as_deref())
+                    .is_some_and(|title| title.starts_with(prefix))
+            })
+            .cloned()
+            .collect::<Vec<Item>>();
+        items.sort_by_key(sort_key);
+        items
+            .into_iter()
+            .filter(|item| start.as_ref().is_none_or(|start| sort_key(item) > *start))
+            .take(limit)
+            .collect()
+    }
+
+    /// Emulates a query on the title index: the releases of a title ordered by release date.
+    fn query_title_page(
+        &self,
+        title: &str,
+        exclusive_start_key: Option<&Key>,
+        limit: usize,
+    ) -> Vec<Item> {
+        let sort_key = |item: &Item| {
+            let attribute = |name: &str| item.get(name).and_then(|a| a.s.clone());
+            (attribute("published_at"), attribute("pk"))
+        };
+        let start = exclusive_start_key.map(sort_key);
+        let mut items = self.query_index("title", title);
+        items.sort_by_key(sort_key);
+        items
+            .into_iter()
+            .filter(|item| start.as_ref().is_none_or(|start| sort_key(item) > *start))
+            .take(limit)
+            .collect()
+    }
+
+    /// Emulates a query on the year index: the movies of a year ordered by release date,
+    /// only those released between both dates if given.
+    fn query_year_page(
+        &self,
+        year: i32,
+        between: Option<(&DateTime<Utc>, &DateTime<Utc>)>,
+        exclusive_start_key: Option<&Key>,
+        limit: usize,
+    ) -> Vec<Item> {
+        let sort_key = |item: &Item| {
+            let attribute = |name: &str| item.get(name).and_then(|a| a.s.clone());
+            (attribute("published_at"), attribute("pk"))
+        };
+        let start = exclusive_start_key.map(sort_key);
+        let between = between.map(|(from, to)| {
+            (
+                DynamoTableItem::published_at_value(from),
+                DynamoTableItem::published_at_value(to),
+            )
+        });
+        let year = year.to_string();
+        let partitions = self.partitions.lock().unwrap();
+        let mut items = partitions
+            .values()
+            .flat_map(|p| p.values())
+            .filter(|item| !DynamoTableItem::has_legacy_key(item))
+            .filter(|item| item.get("published_year").and_then(|y| y.n.as_deref()) == Some(&year))
+            .filter(|item| {
+                between.as_ref().is_none_or(|(from, to)| {
+                    let published_at = sort_key(item).0.unwrap_or_default();
+                    *from <= published_at && published_at <= *to
+                })
+            })
+            .cloned()
+            .collect::<Vec<Item>>();
+        items.sort_by_key(sort_key);
+        items
+            .into_iter()
+            .filter(|item| start.as_ref().is_none_or(|start| sort_key(item) > *start))
+            .take(limit)
+            .collect()
+    }
+
+    /// Emulates a query on the lookup index, reading every item with the given `lookup` value.
+    fn query_lookup(&self, lookup: &str) -> Vec<Item> {
+        self.query_index("lookup", lookup)
+    }
+
+    /// Reads the items whose string attribute has the given value, like a query on an
+    /// index with that attribute as hash key.
+    fn query_index(&self, attribute: &str, value: &str) -> Vec<Item> {
+        let partitions = self.partitions.lock().unwrap();
+        let mut items = partitions
+            .values()
+            .flat_map(|p| p.values())
+            .filter(|item| !DynamoTableItem::has_legacy_key(item))
+            .filter(|item| item.get(attribute).and_then(|a| a.s.as_deref()) == Some(value))
+            .cloned()
+            .collect::<Vec<Item>>();
+        items.sort_by_key(|item| item.get("pk").and_then(|pk| pk.s.clone()));
+        items
+    }
+
+    /// Reads the ids and positions of the movies of a collection ordered by position.
+    fn read_collection_positions(&self, id: &Uuid) -> Result<Vec<(Uuid, i32)>> {
+        let mut movies = self
+            .query(
+                &DynamoTableItem::collection_pk(id),
+                &DynamoTableRowKind::CollectionMovie.get_prefixes().1,
+            )
+            .into_iter()
+            .map(|item| self.parse(item)?.into_collection_movie())
+            .collect::<Result<Vec<_>>>()?;
+        movies.sort_by_key(|(id, position)| (*position, *id));
+        Ok(movies)
+    }
+
+    /// Reads the collections of the collection movie rows read through the inverted index.
+    fn read_membership_collections(&self, memberships: &[Attributes]) -> Result<Vec<Collection>> {
+        let mut collections = vec![];
+        for item in memberships {
+            let collection_id = DynamoTableItem::movie_collection_id(item)?;
+            if let Some(item) = self.get_item(
+                &DynamoTableItem::collection_pk(&collection_id),
+                &DynamoTableRowKind::CollectionMeta.get_prefixes().1,
+            ) {
+                collections.push(self.parse(item)?.into_collection()?);
+            }
+        }
+        Ok(collections)
+    }
+
+    fn join_movies_metadata<T>(&self, pairs: Vec<(Uuid, T)>) -> Result<Vec<(MovieMetadata, T)>> {
+        pairs
+            .into_iter()
+            .map(|(id, value)| {
+                let item = self
+                    .get_item(
+                        &DynamoTableItem::movie_pk(&id),
+                        &DynamoTableRowKind::MovieMeta.get_prefixes().1,
+                    )
+                    .ok_or_else(|| Error::NotFound(format!("movie {}", id)))?;
+                let meta = self.parse(item)?.into_movie_metadata()?;
+                Ok((meta, value))
+            })
+            .collect()
+    }
+
+    /// Carries the attributes unknown to this version of the stored rows over to the items
+    /// replacing them, like the DynamoDB store does for the rows built without them.
+    fn keep_unknown_attributes(&self, items: &mut [DynamoTableItem]) -> Result<()> {
+        for item in items.iter_mut() {
+            if item.expected_version().is_some() || !item.unknown.is_empty() {
+                continue;
+            }
+            if let Some(stored) = self.get_item(&item.pk, &item.sk) {
+                item.unknown = self.parse(stored)?.unknown;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the titles of the given pairs with the movies and episodes.
+    fn join_productions<T>(&self, pairs: Vec<(PortrayalTitle, T)>) -> Result<Vec<(Production, T)>> {
+        pairs
+            .into_iter()
+            .map(|(title, value)| {
+                let production = match title {
+                    PortrayalTitle::Movie(id) => {
+                        let (meta, _) = self.join_movies_metadata(vec![(id, ())])?.remove(0);
+                        Production::Movie(meta)
+                    }
+                    PortrayalTitle::Episode((pk, sk)) => {
+                        let item = self
+                            .get_item(&pk, &sk)
+                            .ok_or_else(|| Error::NotFound(format!("episode {}", sk)))?;
+                        Production::Episode(self.parse(item)?.into_episode()?)
+                    }
+                };
+                Ok((production, value))
+            })
+            .collect()
+    }
+
+    /// Writes and deletes all items under a single lock, mirroring the atomicity of a
+    /// transaction. Nothing is written if the version of a guarded item does not match.
+    fn write_items(
+        &self,
+        puts: &[DynamoTableItem],
+        deletes: &[((String, String), Option<i32>)],
+    ) -> Result<()> {
+        self.write_checked_items(&[], puts, deletes)
+    }
+
+    /// Writes the items like `write_items` if the checked items exist, a missing item is
+    /// reported as not found with its description. Movie meta rows are updated in place like
+    /// in DynamoDB, they keep their rating counters and the attributes they do not name.
+    fn write_checked_items(
+        &self,
+        checks: &[((String, String), String)],
+        puts: &[DynamoTableItem],
+        deletes: &[((String, String), Option<i32>)],
+    ) -> Result<()> {
+        let serialized = puts
+            .iter()
+            .map(|item| Ok((item.to_item()?, item.catalog_attributes()?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut partitions = self.partitions.lock().unwrap();
+        for ((pk, sk), missing) in checks {
+            if !partitions.get(pk).is_some_and(|p| p.contains_key(sk)) {
+                return Err(Error::NotFound(missing.clone()));
+            }
+        }
+        let conditions = puts
+            .iter()
+            .map(|item| ((item.pk.clone(), item.sk.clone()), item.expected_version()))
+            .chain(deletes.iter().cloned());
+        for ((pk, sk), expected_version) in conditions {
+            let stored_version = partitions
+                .get(&pk)
+                .and_then(|p| p.get(&sk))
+                .and_then(|item| item.get("version"))
+                .and_then(|v| v.n.as_ref())
+                .map(|n| n.parse::<i32>().unwrap_or_default())
+                .unwrap_or_default();
+            if expected_version.is_some_and(|v| v != stored_version) {
+                return Err(Error::ConcurrentModification);
+            }
+        }
+        for (item, (serialized, update)) in puts.iter().zip(serialized) {
+            let partition = partitions.entry(item.pk.clone()).or_default();
+            let row = match (update, partition.get(&item.sk)) {
+                (Some((set, remove)), Some(stored)) => {
+                    let mut row = stored.clone();
+                    for name in remove {
+                        row.remove(name);
+                    }
+                    row.extend(set);
+                    row
+                }
+                (Some((set, _)), None) => set,
+                (None, _) => serialized,
+            };
+            partition.insert(item.sk.clone(), row);
+        }
+        for ((pk, sk), _) in deletes {
+            if let Some(partition) = partitions.get_mut(pk) {
+                partition.remove(sk);
+                if partition.is_empty() {
+                    partitions.remove(pk);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MovieStore for InMemoryMovieStore {
+    async fn read_movie_metadata(&self, id: &Uuid) -> Result<Option<MovieMetadata>> {
+        self.get_item(
+            &DynamoTableItem::movie_pk(id),
+            &DynamoTableRowKind::MovieMeta.get_prefixes().1,
+        )
+        .map(|item| self.parse(item)?.into_movie_metadata())
+        .transpose()
+    }
+
+    async fn movie_exists(&self, id: &Uuid) -> Result<bool> {
+        Ok(self
+            .get_item(
+                &DynamoTableItem::movie_pk(id),
+                &DynamoTableRowKind::MovieMeta.get_prefixes().1,
+            )
+            .is_some())
+    }
+
+    async fn find_movies(
+        &self,
+        title: &str,
+        published_at: &DateTime<Utc>,
+    ) -> Result<Vec<MovieMetadata>> {
+        self.query_lookup(&DynamoTableItem::movie_lookup(title, published_at))
+            .into_iter()
+            .map(|item| self.parse(item)?.into_movie_metadata())
+            .collect()
+    }
+
+    async fn find_movies_by_title(
+        &self,
+        title: &str,
+        published_at: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<MovieMetadata>> {
+        let mut movies = self
+            .query_index("title", title)
+            .into_iter()
+            .map(|item| self.parse(item)?.into_movie_metadata())
+            .collect::<Result<Vec<MovieMetadata>>>()?;
+        movies.retain(|meta| published_at.is_none_or(|p| meta.published_at == *p));
+        movies.sort_by_key(|meta| meta.published_at);
+        Ok(movies)
+    }
+
+    async fn read_movies_by_title_page(
+        &self,
+        title: &str,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let after = after
+            .map(|c| decode_cursor(&c, TITLE_INDEX_KEY, &title_cursor(title)))
+            .transpose()?;
+        let items = self.query_title_page(title, after.as_ref(), first + 1);
+        Page::from_items(items, first, TITLE_INDEX_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn find_movies_by_imdb_id(&self, imdb_id: &str) -> Result<Vec<MovieMetadata>> {
+        self.query_index("imdb_id", imdb_id)
+            .into_iter()
+            .map(|item| self.parse(item)?.into_movie_metadata())
+            .collect()
+    }
+
+    async fn read_movies_by_year_page(
+        &self,
+        year: i32,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let after = after
+            .map(|c| decode_cursor(&c, YEAR_INDEX_KEY, &year_cursor(year)))
+            .transpose()?;
+        let items = self.query_year_page(year, None, after.as_ref(), first + 1);
+        Page::from_items(items, first, YEAR_INDEX_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn read_movies_between_page(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let mut start = after
+            .map(|c| decode_between_cursor(&c, from, to))
+            .transpose()?;
+        let mut year = start.as_ref().map_or(from.year(), |(year, _)| *year);
+        let mut items = vec![];
+        while year <= to.year() && items.len() <= first {
+            let key = start.take().map(|(_, key)| key);
+            let limit = first + 1 - items.len();
+            items.extend(self.query_year_page(year, Some((from, to)), key.as_ref(), limit));
+            year += 1;
+        }
+        Page::from_items(items, first, YEAR_INDEX_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn search_movies_page(
+        &self,
+        prefix: &str,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let after = after
+            .map(|c| decode_cursor(&c, SEARCH_INDEX_KEY, &search_cursor(prefix)))
+            .transpose()?;
+        let items = self.query_search_page(prefix, after.as_ref(), first + 1);
+        Page::from_items(items, first, SEARCH_INDEX_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn read_unsearchable_movies_page(
+        &self,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let after = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &movies_cursor()))
+            .transpose()?;
+        let start = after
+            .as_ref()
+            .and_then(|key| key.get("pk"))
+            .and_then(|pk| pk.s.clone());
+        let pk_prefix = DynamoTableRowKind::MovieMeta.get_prefixes().0;
+        let meta_sk = DynamoTableRowKind::MovieMeta.get_prefixes().1;
+        let mut items = {
+            let partitions = self.partitions.lock().unwrap();
+            partitions
+                .iter()
+                .filter(|(pk, _)| pk.starts_with(&pk_prefix))
+                .filter(|(pk, _)| start.as_ref().is_none_or(|start| *pk > start))
+                .filter_map(|(_, partition)| partition.get(&meta_sk))
+                .filter(|item| !DynamoTableItem::has_legacy_key(item))
+                .filter(|item| !item.contains_key("search_prefix"))
+                .cloned()
+                .collect::<Vec<Item>>()
+        };
+        items.sort_by_key(|item| item.get("pk").and_then(|pk| pk.s.clone()));
+        items.truncate(first + 1);
+        Page::from_items(items, first, TABLE_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn read_actor_metadata(&self, id: &Uuid) -> Result<Option<Actor>> {
+        self.get_item(
+            &DynamoTableItem::actor_pk(id),
+            &DynamoTableRowKind::ActorMeta.get_prefixes().1,
+        )
+        .map(|item| self.parse(item)?.into_actor())
+        .transpose()
+    }
+
+    async fn find_actors(&self, last_name: &str, first_name: &str) -> Result<Vec<Actor>> {
+        self.query_lookup(&DynamoTableItem::actor_lookup(last_name, first_name))
+            .into_iter()
+            .map(|item| self.parse(item)?.into_actor())
+            .collect()
+    }
+
+    async fn read_movies_metadata(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, MovieMetadata>> {
+        let mut result = HashMap::new();
+        for id in ids {
+            if let Some(item) = self.get_item(
+                &DynamoTableItem::movie_pk(id),
+                &DynamoTableRowKind::MovieMeta.get_prefixes().1,
+            ) {
+                let meta = self.parse(item)?.into_movie_metadata()?;
+                result.insert(*id, meta);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn read_actors_metadata(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Actor>> {
+        let mut result = HashMap::new();
+        for id in ids {
+            if let Some(item) = self.get_item(
+                &DynamoTableItem::actor_pk(id),
+                &DynamoTableRowKind::ActorMeta.get_prefixes().1,
+            ) {
+                let actor = self.parse(item)?.into_actor()?;
+                result.insert(*id, actor);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn read_movie_roles(&self, id: &Uuid) -> Result<Vec<(Uuid, Credit)>> {
+        let items = self.query(
+            &DynamoTableItem::movie_pk(id),
+            &DynamoTableRowKind::MovieActor.get_prefixes().1,
+        );
+        items
+            .into_iter()
+            .map(|item| self.parse(item)?.into_movie_actor())
+            .collect()
+    }
+
+    async fn read_movie_roles_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(Uuid, Credit)>> {
+        let pk = DynamoTableItem::movie_pk(id);
+        let sk_prefix = DynamoTableRowKind::MovieActor.get_prefixes().1;
+        let conditions = partition_cursor(pk.clone(), sk_prefix.clone());
+        let after = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_page(&pk, &sk_prefix, after.as_ref(), first + 1);
+        Page::from_items(items, first, TABLE_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_actor())
+    }
+
+    async fn read_actor_movies(&self, id: &Uuid) -> Result<Vec<(MovieMetadata, Vec<Character>)>> {
+        let roles = self
+            .query_inverted_page(
+                &DynamoTableItem::movie_actor_sk(id),
+                &DynamoTableRowKind::MovieMeta.get_prefixes().0,
+                None,
+                usize::MAX,
+            )
+            .into_iter()
+            .map(|item| self.parse(item)?.into_actor_movie())
+            .collect::<Result<Vec<_>>>()?;
+        self.join_movies_metadata(roles)
+    }
+
+    async fn read_actor_movies_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(MovieMetadata, Vec<Character>)>> {
+        let sk = DynamoTableItem::movie_actor_sk(id);
+        let pk_prefix = DynamoTableRowKind::MovieMeta.get_prefixes().0;
+        let conditions = inverted_cursor(sk.clone(), pk_prefix.clone());
+        let after = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_inverted_page(&sk, &pk_prefix, after.as_ref(), first + 1);
+        let page = Page::from_items(items, first, TABLE_KEY)?
+            .try_map(|item| self.parse(item)?.into_actor_movie())?;
+        let (cursors, roles): (Vec<String>, Vec<_>) = page.items.into_iter().unzip();
+        let movies = self.join_movies_metadata(roles)?;
+        Ok(Page {
+            items: cursors.into_iter().zip(movies).collect(),
+            has_next_page: page.has_next_page,
+        })
+    }
+
+    async fn read_movie_role(&self, movie_id: &Uuid, actor_id: &Uuid) -> Result<Credit> {
+        let item = self
+            .get_item(
+                &DynamoTableItem::movie_pk(movie_id),
+                &DynamoTableItem::movie_actor_sk(actor_id),
+            )
+            .ok_or_else(|| {
+                Error::NotFound(format!("role of actor {} in movie {}", actor_id, movie_id))
+            })?;
+        let (_, credit) = self.parse(item)?.into_movie_actor()?;
+        Ok(credit)
+    }
+
+    async fn read_movie_crew(&self, id: &Uuid) -> Result<Vec<CrewCredit>> {
+        self.query(
+            &DynamoTableItem::movie_pk(id),
+            &DynamoTableRowKind::MovieCrew.get_prefixes().1,
+        )
+        .into_iter()
+        .map(|item| self.parse(item)?.into_crew_credit())
+        .collect()
+    }
+
+    async fn read_character(&self, id: &Uuid) -> Result<Option<Character>> {
+        self.get_item(
+            &DynamoTableItem::character_pk(id),
+            &DynamoTableRowKind::CharacterMeta.get_prefixes().1,
+        )
+        .map(|item| self.parse(item)?.into_character())
+        .transpose()
+    }
+
+    async fn find_characters(&self, name: &str) -> Result<Vec<Character>> {
+        self.query_lookup(&DynamoTableItem::character_lookup(name))
+            .into_iter()
+            .map(|item| self.parse(item)?.into_character())
+            .collect()
+    }
+
+    async fn read_characters(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Character>> {
+        let mut result = HashMap::new();
+        for id in ids {
+            if let Some(character) = self.read_character(id).await? {
+                result.insert(*id, character);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn store_character(&self, character: Character) -> Result<()> {
+        self.write_items(&[DynamoTableItem::new_character(&character)], &[])
+    }
+
+    async fn read_character_portrayals(&self, id: &Uuid) -> Result<Vec<(Production, Uuid)>> {
+        let portrayals = self
+            .query(
+                &DynamoTableItem::character_pk(id),
+                &DynamoTableItem::character_portrayals_sk(),
+            )
+            .into_iter()
+            .map(|item| self.parse(item)?.into_character_portrayal())
+            .collect::<Result<Vec<_>>>()?;
+        self.join_productions(portrayals)
+    }
+
+    async fn read_character_portrayals_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(Production, Uuid)>> {
+        let pk = DynamoTableItem::character_pk(id);
+        let sk_prefix = DynamoTableItem::character_portrayals_sk();
+        let conditions = partition_cursor(pk.clone(), sk_prefix.clone());
+        let after = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_page(&pk, &sk_prefix, after.as_ref(), first + 1);
+        let page = Page::from_items(items, first, TABLE_KEY)?
+            .try_map(|item| self.parse(item)?.into_character_portrayal())?;
+        let (cursors, portrayals): (Vec<String>, Vec<_>) = page.items.into_iter().unzip();
+        let productions = self.join_productions(portrayals)?;
+        Ok(Page {
+            items: cursors.into_iter().zip(productions).collect(),
+            has_next_page: page.has_next_page,
+        })
+    }
+
+    async fn store_movie(&self, movie: Movie) -> Result<()> {
+        let mut items = DynamoTableItem::new_movie(&movie);
+        self.keep_unknown_attributes(&mut items)?;
+        // the roles and crew credits, the meta row is always part of the items and the
+        // reviews are kept
+        let pk = DynamoTableItem::movie_pk(&movie.meta.id);
+        let credits = self
+            .query(&pk, &DynamoTableRowKind::MovieActor.get_prefixes().1)
+            .into_iter()
+            .chain(self.query(&pk, &DynamoTableRowKind::MovieCrew.get_prefixes().1))
+            .collect();
+        let stored = DynamoTableItem::row_keys(credits)?;
+        let stale = DynamoTableItem::stale_keys(stored, &items)
+            .into_iter()
+            .map(|key| (key, None))
+            .collect::<Vec<_>>();
+        self.write_items(&items, &stale)
+    }
+
+    async fn store_movie_metadata(&self, meta: MovieMetadata) -> Result<()> {
+        self.write_items(&[DynamoTableItem::new_movie_metadata(&meta)], &[])
+    }
+
+    async fn delete_movie(&self, meta: MovieMetadata) -> Result<()> {
+        let pk = DynamoTableItem::movie_pk(&meta.id);
+        let meta_sk = DynamoTableRowKind::MovieMeta.get_prefixes().1;
+        let memberships = self.query_inverted_page(
+            &DynamoTableItem::collection_movie_sk(&meta.id),
+            &DynamoTableRowKind::CollectionMeta.get_prefixes().0,
+            None,
+            usize::MAX,
+        );
+        let mut keys = DynamoTableItem::row_keys(self.query(&pk, ""))?
+            .into_iter()
+            .chain(DynamoTableItem::membership_keys(&meta.id, &memberships)?)
+            .filter(|(row_pk, sk)| *row_pk != pk || *sk != meta_sk)
+            .map(|key| (key, None))
+            .collect::<Vec<_>>();
+        keys.push(((pk, meta_sk), Some(meta.version)));
+        let collections = self
+            .read_membership_collections(&memberships)?
+            .iter()
+            .map(DynamoTableItem::new_collection)
+            .collect::<Vec<DynamoTableItem>>();
+        self.write_items(&collections, &keys)
+    }
+
+    async fn store_movie_role(
+        &self,
+        meta: MovieMetadata,
+        actor_id: &Uuid,
+        credit: Credit,
+    ) -> Result<()> {
+        let mut items = vec![
+            DynamoTableItem::new_movie_metadata(&meta),
+            DynamoTableItem::new_movie_actor(&meta, actor_id, &credit),
+        ];
+        items.extend(DynamoTableItem::new_character_movies(
+            &meta, actor_id, &credit,
+        ));
+        self.keep_unknown_attributes(&mut items)?;
+        items.extend(DynamoTableItem::new_characters(Some(&credit)));
+        let role = self.get_item(
+            &DynamoTableItem::movie_pk(&meta.id),
+            &DynamoTableItem::movie_actor_sk(actor_id),
+        );
+        let stale =
+            DynamoTableItem::stale_keys(DynamoTableItem::role_character_keys(role)?, &items)
+                .into_iter()
+                .map(|key| (key, None))
+                .collect::<Vec<_>>();
+        self.write_items(&items, &stale)
+    }
+
+    async fn add_movie_role(
+        &self,
+        meta: MovieMetadata,
+        actor_id: &Uuid,
+        credit: Credit,
+    ) -> Result<()> {
+        let role = DynamoTableItem::new_movie_actor(&meta, actor_id, &credit);
+        if self.get_item(&role.pk, &role.sk).is_some() {
+            return Err(Error::Conflict(role_conflict(&meta.id, actor_id)));
+        }
+        let mut items = vec![DynamoTableItem::new_movie_metadata(&meta), role];
+        items.extend(DynamoTableItem::new_character_movies(
+            &meta, actor_id, &credit,
+        ));
+        items.extend(DynamoTableItem::new_characters(Some(&credit)));
+        self.write_items(&items, &[])
+    }
+
+    async fn delete_movie_role(&self, meta: MovieMetadata, actor_id: &Uuid) -> Result<()> {
+        let role = self.get_item(
+            &DynamoTableItem::movie_pk(&meta.id),
+            &DynamoTableItem::movie_actor_sk(actor_id),
+        );
+        let mut keys = DynamoTableItem::role_character_keys(role)?
+            .into_iter()
+            .map(|key| (key, None))
+            .collect::<Vec<_>>();
+        keys.push((
+            (
+                DynamoTableItem::movie_pk(&meta.id),
+                DynamoTableItem::movie_actor_sk(actor_id),
+            ),
+            None,
+        ));
+        self.write_items(&[DynamoTableItem::new_movie_metadata(&meta)], &keys)
+    }
+
+    async fn store_actor(&self, actor: Actor) -> Result<()> {
+        self.write_items(&[DynamoTableItem::new_actor(&actor)], &[])
+    }
+
+    async fn delete_actor(&self, actor: Actor) -> Result<()> {
+        let mut roles = self.query_inverted_page(
+            &DynamoTableItem::movie_actor_sk(&actor.id),
+            &DynamoTableRowKind::MovieMeta.get_prefixes().0,
+            None,
+            usize::MAX,
+        );
+        roles.extend(self.query_inverted_page(
+            &DynamoTableItem::movie_actor_sk(&actor.id),
+            &DynamoTableRowKind::EpisodeActor.get_prefixes().0,
+            None,
+            usize::MAX,
+        ));
+        let mut keys = DynamoTableItem::row_keys(roles)?
+            .into_iter()
+            .map(|key| (key, None))
+            .collect::<Vec<_>>();
+        keys.push((
+            (
+                DynamoTableItem::actor_pk(&actor.id),
+                DynamoTableRowKind::ActorMeta.get_prefixes().1,
+            ),
+            Some(actor.version),
+        ));
+        self.write_items(&[], &keys)
+    }
+
+    async fn read_collection(&self, id: &Uuid) -> Result<Option<Collection>> {
+        self.get_item(
+            &DynamoTableItem::collection_pk(id),
+            &DynamoTableRowKind::CollectionMeta.get_prefixes().1,
+        )
+        .map(|item| self.parse(item)?.into_collection())
+        .transpose()
+    }
+
+    async fn read_collection_movies(&self, id: &Uuid) -> Result<Vec<(MovieMetadata, i32)>> {
+        let movies = self.read_collection_positions(id)?;
+        self.join_movies_metadata(movies)
+    }
+
+    async fn read_movie_collections(&self, id: &Uuid) -> Result<Vec<Collection>> {
+        let memberships = self.query_inverted_page(
+            &DynamoTableItem::collection_movie_sk(id),
+            &DynamoTableRowKind::CollectionMeta.get_prefixes().0,
+            None,
+            usize::MAX,
+        );
+        let mut collections = self.read_membership_collections(&memberships)?;
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(collections)
+    }
+
+    async fn store_collection(&self, collection: Collection) -> Result<()> {
+        self.write_items(&[DynamoTableItem::new_collection(&collection)], &[])
+    }
+
+    async fn store_collection_movie(
+        &self,
+        collection: Collection,
+        movie_id: &Uuid,
+        position: i32,
+    ) -> Result<()> {
+        let stored = self.read_collection_positions(&collection.id)?;
+        let mut items =
+            DynamoTableItem::new_collection_movies(&collection, &stored, movie_id, position);
+        self.keep_unknown_attributes(&mut items)?;
+        items.insert(0, DynamoTableItem::new_collection(&collection));
+        self.write_items(&items, &[])
+    }
+
+    async fn read_series(&self, id: &Uuid) -> Result<Option<Series>> {
+        self.get_item(
+            &DynamoTableItem::series_pk(id),
+            &DynamoTableRowKind::SeriesMeta.get_prefixes().1,
+        )
+        .map(|item| self.parse(item)?.into_series())
+        .transpose()
+    }
+
+    async fn read_series_seasons(&self, id: &Uuid) -> Result<Vec<Season>> {
+        self.query(
+            &DynamoTableItem::series_pk(id),
+            &DynamoTableRowKind::SeriesSeason.get_prefixes().1,
+        )
+        .into_iter()
+        .map(|item| self.parse(item)?.into_season())
+        .collect()
+    }
+
+    async fn read_series_episodes(&self, id: &Uuid, season: Option<i32>) -> Result<Vec<Episode>> {
+        let sk = match season {
+            Some(number) => DynamoTableItem::series_season_episodes_sk(number),
+            None => DynamoTableRowKind::SeriesEpisode.get_prefixes().1,
+        };
+        self.query(&DynamoTableItem::series_pk(id), &sk)
+            .into_iter()
+            .map(|item| self.parse(item)?.into_episode())
+            .collect()
+    }
+
+    async fn read_series_episodes_page(
+        &self,
+        id: &Uuid,
+        season: Option<i32>,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<Episode>> {
+        let pk = DynamoTableItem::series_pk(id);
+        let sk = match season {
+            Some(number) => DynamoTableItem::series_season_episodes_sk(number),
+            None => DynamoTableRowKind::SeriesEpisode.get_prefixes().1,
+        };
+        let conditions = partition_cursor(pk.clone(), sk.clone());
+        let after = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_page(&pk, &sk, after.as_ref(), first + 1);
+        Page::from_items(items, first, TABLE_KEY)?.try_map(|item| self.parse(item)?.into_episode())
+    }
+
+    async fn read_episode(
+        &self,
+        series_id: &Uuid,
+        season_number: i32,
+        episode_number: i32,
+    ) -> Result<Option<Episode>> {
+        self.get_item(
+            &DynamoTableItem::series_pk(series_id),
+            &DynamoTableItem::series_episode_sk(season_number, episode_number),
+        )
+        .map(|item| self.parse(item)?.into_episode())
+        .transpose()
+    }
+
+    async fn read_episode_roles(
+        &self,
+        series_id: &Uuid,
+        season_number: i32,
+        episode_number: i32,
+    ) -> Result<Vec<(Uuid, Credit)>> {
+        self.query(
+            &DynamoTableItem::episode_pk(series_id, season_number, episode_number),
+            "",
+        )
+        .into_iter()
+        .map(|item| self.parse(item)?.into_episode_actor())
+        .collect()
+    }
+
+    async fn read_actor_episodes(&self, id: &Uuid) -> Result<Vec<(Episode, Vec<Character>)>> {
+        let mut episodes = vec![];
+        for item in self.query_inverted_page(
+            &DynamoTableItem::movie_actor_sk(id),
+            &DynamoTableRowKind::EpisodeActor.get_prefixes().0,
+            None,
+            usize::MAX,
+        ) {
+            let ((pk, sk), characters) = self.parse(item)?.into_actor_episode()?;
+            if let Some(item) = self.get_item(&pk, &sk) {
+                episodes.push((self.parse(item)?.into_episode()?, characters));
+            }
+        }
+        Ok(episodes)
+    }
+
+    async fn store_series(&self, series: Series) -> Result<()> {
+        self.write_items(&[DynamoTableItem::new_series(&series)], &[])
+    }
+
+    async fn store_season(&self, series: Series, season: Season) -> Result<()> {
+        self.write_items(
+            &[
+                DynamoTableItem::new_series(&series),
+                DynamoTableItem::new_season(&series, &season),
+            ],
+            &[],
+        )
+    }
+
+    async fn store_episode(
+        &self,
+        series: Series,
+        episode: Episode,
+        roles: Vec<Role>,
+    ) -> Result<()> {
+        let mut items = DynamoTableItem::new_episode(&series, &episode, &roles);
+        self.keep_unknown_attributes(&mut items)?;
+        let pk =
+            DynamoTableItem::episode_pk(&series.id, episode.season_number, episode.episode_number);
+        let stale =
+            DynamoTableItem::stale_keys(DynamoTableItem::row_keys(self.query(&pk, ""))?, &items)
+                .into_iter()
+                .map(|key| (key, None))
+                .collect::<Vec<_>>();
+        let season = (
+            (
+                DynamoTableItem::series_pk(&series.id),
+                DynamoTableItem::series_season_sk(episode.season_number),
+            ),
+            format!("season {} of series {}", episode.season_number, series.id),
+        );
+        self.write_checked_items(&[season], &items, &stale)
+    }
+
+    async fn read_movie_reviews(&self, id: &Uuid) -> Result<Vec<Review>> {
+        self.query(
+            &DynamoTableItem::movie_pk(id),
+            &DynamoTableRowKind::MovieReview.get_prefixes().1,
+        )
+        .into_iter()
+        .map(|item| self.parse(item)?.into_review())
+        .collect()
+    }
+
+    async fn read_movie_reviews_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<Review>> {
+        let pk = DynamoTableItem::movie_pk(id);
+        let sk_prefix = DynamoTableRowKind::MovieReview.get_prefixes().1;
+        let conditions = partition_cursor(pk.clone(), sk_prefix.clone());
+        let after = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_page(&pk, &sk_prefix, after.as_ref(), first + 1);
+        Page::from_items(items, first, TABLE_KEY)?.try_map(|item| self.parse(item)?.into_review())
+    }
+
+    async fn read_movie_review(&self, movie_id: &Uuid, user_id: &str) -> Result<Option<Review>> {
+        self.get_item(
+            &DynamoTableItem::movie_pk(movie_id),
+            &DynamoTableItem::movie_review_sk(user_id),
+        )
+        .map(|item| self.parse(item)?.into_review())
+        .transpose()
+    }
+
+    /// Counts the rating into the stored aggregates in place, the version of the meta row
+    /// is neither checked nor changed.
+    async fn store_movie_review(&self, review: Review, previous_rating: Option<i32>) -> Result<()> {
+        let item = DynamoTableItem::new_movie_review(&review);
+        let serialized = item.to_item()?;
+        let meta_sk = DynamoTableRowKind::MovieMeta.get_prefixes().1;
+        let missing = || Error::NotFound(format!("movie {}", review.movie_id));
+        let mut partitions = self.partitions.lock().unwrap();
+        let partition = partitions.get_mut(&item.pk).ok_or_else(missing)?;
+        if !partition.contains_key(&meta_sk) {
+            return Err(missing());
+        }
+        let stored_version = partition
+            .get(&item.sk)
+            .and_then(|item| item.get("version"))
+            .and_then(|v| v.n.as_ref())
+            .map(|n| n.parse::<i32>().unwrap_or_default())
+            .unwrap_or_default();
+        if item.expected_version() != Some(stored_version) {
+            return Err(Error::ConcurrentModification);
+        }
+        let stored_meta = partition.get_mut(&meta_sk).ok_or_else(missing)?;
+        // only the counters change, the version of the movie stays the same
+        let mut meta = DynamoTableItem::from_item(stored_meta.clone(), UnknownAttributes::Ignore)?
+            .into_movie_metadata()?;
+        meta.ratings.rate(review.rating, previous_rating);
+        let counters = DynamoTableItem::new_movie_metadata(&meta).to_item()?;
+        stored_meta.retain(|name, _| !is_rating_attribute(name));
+        stored_meta.extend(
+            counters
+                .into_iter()
+                .filter(|(name, _)| is_rating_attribute(name)),
+        );
+        partition.insert(item.sk.clone(), serialized);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::InMemoryMovieStore;
+    use crate::storage::MovieStore;
+    use chrono::{DateTime, Utc};
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_store_movie_layout() {
+        let store = InMemoryMovieStore::new();
+        let movie = crate::data::Movie {
+            meta: crate::data::MovieMetadata {
+                imdb_id: Some("tt0113277".to_owned()),
+                ..crate::data::MovieMetadata::new(
+                    Uuid::from_u128(1),
+                    "Heat".to_owned(),
+                    DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap(),
+                )
+            },
+            crew: vec![],
+            roles: vec![crate::data::Role {
+                actor: crate::data::Actor {
+                    id: Uuid::from_u128(2),
+                    version: 0,
+                    last_name: "Pacino".to_owned(),
+                    first_name: "Al".to_owned(),
+                    ..Default::default()
+                },
+                credit: crate::data::Credit {
+                    characters: vec![crate::data::Character {
+                        id: Uuid::from_u128(101),
+                        version: 0,
+                        name: "Lt. Vincent Hanna".to_owned(),
+                    }],
+                    ..Default::default()
+                },
+            }],
+        };
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(store.store_movie(movie)).unwrap();
+
+        let partitions = store.partitions.lock().unwrap();
+        let mut keys = partitions
+            .iter()
+            .flat_map(|(pk, p)| p.keys().map(move |sk| format!("{}|{}", pk, sk)))
+            .collect::<Vec<String>>();
+        keys.sort();
+        assert_eq!(
+            vec![
+                "actor::00000000-0000-0000-0000-000000000002|meta",
+                "character::00000000-0000-0000-0000-000000000065|meta",
+                "character::00000000-0000-0000-0000-000000000065|portrayal::movie::00000000-0000-0000-0000-000000000001#00000000-0000-0000-0000-000000000002",
+                "movie::00000000-0000-0000-0000-000000000001|actor::00000000-0000-0000-0000-000000000002",
+                "movie::00000000-0000-0000-0000-000000000001|meta",
+            ],
+            keys
+        );
+    }
+
+    #[test]
+    fn test_stale_write_is_rejected() {
+        let store = InMemoryMovieStore::new();
+        let meta = crate::data::MovieMetadata::new(
+            Uuid::from_u128(1),
+            "Heat".to_owned(),
+            DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap(),
+        );
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        // two writers read version 0, only the first one may write
+        runtime
+            .block_on(store.store_movie_metadata(meta.clone()))
+            .unwrap();
+        let result = runtime.block_on(store.store_movie_metadata(meta));
+        match result.unwrap_err() {
+            crate::error::Error::ConcurrentModification => {}
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn test_movie_meta_rows_are_updated_in_place() {
+        let store =
+            InMemoryMovieStore::with_unknown_attributes(crate::config::UnknownAttributes::Ignore);
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut meta = crate::data::MovieMetadata::new(
+            Uuid::from_u128(1),
+            "Heat".to_owned(),
+            DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap(),
+        );
+        meta.synopsis = Some("A heist".to_owned());
+        runtime
+            .block_on(store.store_movie_metadata(meta.clone()))
+            .unwrap();
+        let attribute = rusoto_dynamodb::AttributeValue {
+            n: Some("5".to_owned()),
+            ..Default::default()
+        };
+        store
+            .partitions
+            .lock()
+            .unwrap()
+            .values_mut()
+            .for_each(|partition| {
+                partition
+                    .get_mut("meta")
+                    .unwrap()
+                    .insert("popularity".to_owned(), attribute.clone());
+            });
+        meta.version = 1;
+        meta.synopsis = None;
+        runtime.block_on(store.store_movie_metadata(meta)).unwrap();
+        // the cleared synopsis is removed, the attribute the update does not name is kept
+        let partitions = store.partitions.lock().unwrap();
+        let row = partitions.values().next().unwrap().get("meta").unwrap();
+        assert!(!row.contains_key("synopsis"));
+        assert_eq!(Some(&attribute), row.get("popularity"));
+        assert_eq!(Some("2"), row["version"].n.as_deref());
+    }
+
+    #[test]
+    fn test_review_of_missing_movie_is_not_found() {
+        let store = InMemoryMovieStore::new();
+        let review = crate::data::Review {
+            movie_id: Uuid::from_u128(1),
+            user_id: "moviegoer".to_owned(),
+            version: 0,
+            rating: 8,
+            text: None,
+            reviewed_at: DateTime::<Utc>::from_str("2020-01-01T00:00:00Z").unwrap(),
+        };
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(store.store_movie_review(review, None));
+        match result.unwrap_err() {
+            crate::error::Error::NotFound(_) => {}
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn test_unsearchable_movies_are_listed_until_written_again() {
+        let store = InMemoryMovieStore::new();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        for (id, title) in &[(1, "Heat"), (2, "Casino"), (3, "Se7en")] {
+            let meta = crate::data::MovieMetadata::new(
+                Uuid::from_u128(*id),
+                title.to_string(),
+                DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap(),
+            );
+            runtime.block_on(store.store_movie_metadata(meta)).unwrap();
+        }
+        // written before searching was introduced
+        for partition in store.partitions.lock().unwrap().values_mut() {
+            let meta = partition.get_mut("meta").unwrap();
+            meta.remove("search_prefix");
+            meta.remove("search_title");
+        }
+        let page = runtime
+            .block_on(store.read_unsearchable_movies_page(2, None))
+            .unwrap();
+        assert!(page.has_next_page);
+        let (cursor, heat) = page.items[0].clone();
+        assert_eq!("Heat", heat.title);
+        runtime.block_on(store.store_movie_metadata(heat)).unwrap();
+        let page = runtime
+            .block_on(store.read_unsearchable_movies_page(2, Some(cursor)))
+            .unwrap();
+        assert_eq!(
+            vec!["Casino", "Se7en"],
+            page.items
+                .iter()
+                .map(|(_, meta)| meta.title.as_str())
+                .collect::<Vec<&str>>()
+        );
+        let page = runtime
+            .block_on(store.read_unsearchable_movies_page(5, None))
+            .unwrap();
+        assert_eq!(2, page.items.len());
+        assert!(!page.has_next_page);
+    }
+    #[test]
+    fn test_rows_with_legacy_keys_are_skipped() {
+        let store = InMemoryMovieStore::new();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let published_at = DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap();
+        let meta =
+            crate::data::MovieMetadata::new(Uuid::from_u128(1), "Heat".to_owned(), published_at);
+        runtime.block_on(store.store_movie_metadata(meta)).unwrap();
+        // a meta row as the first version wrote it, keyed by title and year
+        let string = |s: &str| rusoto_dynamodb::AttributeValue {
+            s: Some(s.to_owned()),
+            ..Default::default()
+        };
+        let legacy = vec![
+            ("pk".to_owned(), string("movie::Heat#1995")),
+            ("sk".to_owned(), string("meta")),
+            ("title".to_owned(), string("Heat")),
+            ("published_at".to_owned(), string("1995-12-15T00:00:00Z")),
+            (
+                "published_year".to_owned(),
+                rusoto_dynamodb::AttributeValue {
+                    n: Some("1995".to_owned()),
+                    ..Default::default()
+                },
+            ),
+        ];
+        store
+            .partitions
+            .lock()
+            .unwrap()
+            .entry("movie::Heat#1995".to_owned())
+            .or_default()
+            .insert("meta".to_owned(), legacy.into_iter().collect());
+
+        let movies = runtime
+            .block_on(store.find_movies_by_title("Heat", None))
+            .unwrap();
+        assert_eq!(
+            vec![Uuid::from_u128(1)],
+            movies.iter().map(|m| m.id).collect::<Vec<_>>()
+        );
+        let page = runtime
+            .block_on(store.read_movies_by_year_page(1995, 10, None))
+            .unwrap();
+        assert_eq!(1, page.items.len());
+        let page = runtime
+            .block_on(store.read_unsearchable_movies_page(10, None))
+            .unwrap();
+        assert!(page.items.is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::error::{Error, Result};
+use juniper::GraphQLObject;
+use rusoto_dynamodb::AttributeValue;
+use std::collections::HashMap;
+
+pub type Key = HashMap<String, AttributeValue>;
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// A slice of a query result, every item carries the cursor pointing at it.
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<(String, T)>,
+    pub has_next_page: bool,
+}
+
+impl<T> Page<T> {
+    pub fn try_map<U, F>(self, mut f: F) -> Result<Page<U>>
+    where
+        F: FnMut(T) -> Result<U>,
+    {
+        Ok(Page {
+            items: self
+                .items
+                .into_iter()
+                .map(|(cursor, item)| Ok((cursor, f(item)?)))
+                .collect::<Result<_>>()?,
+            has_next_page: self.has_next_page,
+        })
+    }
+}
+
+impl Page<HashMap<String, AttributeValue>> {
+    /// Builds a page out of up to `first + 1` queried items, the surplus item only
+    /// signals that there is a next page. Cursors encode the given key attributes.
+    pub fn from_items(
+        mut items: Vec<HashMap<String, AttributeValue>>,
+        first: usize,
+        key_attributes: &[&str],
+    ) -> Result<Self> {
+        let has_next_page = items.len() > first;
+        items.truncate(first);
+        Ok(Page {
+            items: items
+                .into_iter()
+                .map(|item| Ok((encode_cursor(&item_key(&item, key_attributes))?, item)))
+                .collect::<Result<_>>()?,
+            has_next_page,
+        })
+    }
+}
+
+/// Encodes a DynamoDB key as an opaque cursor.
+pub fn encode_cursor(key: &Key) -> Result<String> {
+    let json = serde_json::to_vec(key).map_err(|e| Error::CorruptItem(e.to_string()))?;
+    Ok(base64::encode_config(json, base64::URL_SAFE_NO_PAD))
+}
+
+/// A condition the key of a cursor has to meet to continue a query, mirroring the key
+/// condition of the query so that a cursor of another query is rejected instead of
+/// moving the query to another partition.
+pub enum KeyCondition<'a> {
+    Equals(&'a str, String),
+    BeginsWith(&'a str, String),
+}
+
+impl KeyCondition<'_> {
+    fn matches(&self, key: &Key) -> bool {
+        let value = |name: &str| {
+            key.get(name)
+                .and_then(|value| value.s.as_ref().or(value.n.as_ref()))
+        };
+        match self {
+            KeyCondition::Equals(name, expected) => value(name) == Some(expected),
+            KeyCondition::BeginsWith(name, prefix) => {
+                value(name).is_some_and(|value| value.starts_with(prefix.as_str()))
+            }
+        }
+    }
+}
+
+/// Decodes a cursor of a query whose items are keyed by the given attributes, the key has
+/// to meet the key conditions of the query.
+pub fn decode_cursor(
+    cursor: &str,
+    key_attributes: &[&str],
+    conditions: &[KeyCondition],
+) -> Result<Key> {
+    let invalid = || Error::Validation("invalid cursor".to_owned());
+    let json = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+    let key: Key = serde_json::from_slice(&json).map_err(|_| invalid())?;
+    if key.len() != key_attributes.len()
+        || !key_attributes.iter().all(|a| key.contains_key(*a))
+        || !conditions.iter().all(|c| c.matches(&key))
+    {
+        return Err(invalid());
+    }
+    Ok(key)
+}
+
+/// Picks the given key attributes out of an item.
+pub fn item_key(item: &HashMap<String, AttributeValue>, attributes: &[&str]) -> Key {
+    attributes
+        .iter()
+        .filter_map(|a| item.get(*a).map(|v| ((*a).to_owned(), v.clone())))
+        .collect()
+}
+
+/// Validates the `first` argument of a connection field.
+pub fn page_size(first: Option<i32>) -> Result<usize> {
+    match first {
+        None => Ok(DEFAULT_PAGE_SIZE),
+        Some(n) if n >= 0 && n as usize <= MAX_PAGE_SIZE => Ok(n as usize),
+        Some(_) => Err(Error::Validation(format!(
+            "first must be between 0 and {}",
+            MAX_PAGE_SIZE
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct PageInfo {
+    #[graphql(name = "has_next_page")]
+    pub has_next_page: bool,
+    #[graphql(name = "has_previous_page")]
+    pub has_previous_page: bool,
+    #[graphql(name = "start_cursor")]
+    pub start_cursor: Option<String>,
+    #[graphql(name = "end_cursor")]
+    pub end_cursor: Option<String>,
+}
+
+impl PageInfo {
+    pub fn new<T>(page: &Page<T>, after: &Option<String>) -> Self {
+        PageInfo {
+            has_next_page: page.has_next_page,
+            has_previous_page: after.is_some(),
+            start_cursor: page.items.first().map(|(c, _)| c.clone()),
+            end_cursor: page.items.last().map(|(c, _)| c.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pagination::{decode_cursor, encode_cursor, KeyCondition};
+    use rusoto_dynamodb::AttributeValue;
+
+    #[test]
+    fn test_cursor_roundtrip() {
+        let key = map! {
+            "pk".to_owned() => AttributeValue {
+                s: Some("movie::The Irishman#2019".to_owned()),
+                ..Default::default()
+            },
+            "sk".to_owned() => AttributeValue {
+                s: Some("actor::Deniro Robert".to_owned()),
+                ..Default::default()
+            }
+        };
+        let cursor = encode_cursor(&key).unwrap();
+        let conditions = |pk: &str| {
+            vec![
+                KeyCondition::Equals("pk", pk.to_owned()),
+                KeyCondition::BeginsWith("sk", "actor::".to_owned()),
+            ]
+        };
+        assert_eq!(
+            key,
+            decode_cursor(
+                &cursor,
+                &["pk", "sk"],
+                &conditions("movie::The Irishman#2019")
+            )
+            .unwrap()
+        );
+        // a cursor of another movie or of another index is rejected
+        assert!(decode_cursor(&cursor, &["pk", "sk"], &conditions("movie::Heat#1995")).is_err());
+        assert!(decode_cursor(&cursor, &["pk", "sk", "title"], &[]).is_err());
+        assert!(decode_cursor("not a cursor", &["pk", "sk"], &[]).is_err());
+    }
+}
@@ -1,95 +1,2415 @@
-use chrono::{prelude::*, DateTime, Utc};
-use juniper::{FieldResult, GraphQLInputObject, RootNode};
+use crate::config::Config;
+use crate::data::{
+    Actor, Character, Collection, Credit, CrewCredit, Department, Episode, MovieMetadata,
+    Production, RatingSummary, ReleaseDate, Review, Role, Season, Series, MAX_RATING,
+};
+use crate::error::{Error, Result};
+use crate::loader::ActorLoader;
+use crate::pagination::{page_size, Page};
+use crate::search::{normalize_title, search_prefix};
+use crate::storage::{role_conflict, MovieStore};
+use crate::validation::{Validator, Violation};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use juniper::{EmptySubscription, GraphQLEnum, GraphQLInputObject, GraphQLObject, RootNode, ID};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
 
-error_chain! {
-    types {
-        Error, ErrorKind, ResultExt, Result;
+/// State of the lambda function, built once per cold start and reused by all
+/// requests handled by the same instance. The runtime drives the asynchronous
+/// execution of a whole GraphQL request, resolvers await the storage directly.
+pub struct Environment {
+    config: Arc<Config>,
+    store: Arc<dyn MovieStore>,
+    runtime: Mutex<Runtime>,
+}
+
+impl Environment {
+    pub fn new(config: Config, store: Box<dyn MovieStore>) -> std::io::Result<Self> {
+        Ok(Environment {
+            config: Arc::new(config),
+            store: Arc::from(store),
+            runtime: Mutex::new(Runtime::new()?),
+        })
+    }
+
+    /// Every request is executed with its own context, sharing the store but not the
+    /// caches.
+    pub fn for_request(&self) -> Context {
+        Context {
+            config: Arc::clone(&self.config),
+            store: Arc::clone(&self.store),
+            actors: ActorLoader::new(Arc::clone(&self.store)),
+        }
+    }
+
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.lock().unwrap().block_on(future)
+    }
+}
+
+/// Context of a single GraphQL request.
+pub struct Context {
+    pub config: Arc<Config>,
+    pub store: Arc<dyn MovieStore>,
+    pub actors: ActorLoader,
+}
+
+impl juniper::Context for Context {}
+
+impl Context {
+    /// Reads a movie that has to exist, e.g. because it is about to be changed.
+    async fn read_movie(&self, id: &Uuid) -> Result<MovieMetadata> {
+        self.store
+            .read_movie_metadata(id)
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("movie {}", id)))
+    }
+
+    /// Reads a collection that has to exist, e.g. because it is about to be changed.
+    async fn read_collection(&self, id: &Uuid) -> Result<Collection> {
+        self.store
+            .read_collection(id)
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("collection {}", id)))
+    }
+
+    /// Reads a series that has to exist, e.g. because it is about to be changed.
+    async fn read_series(&self, id: &Uuid) -> Result<Series> {
+        self.store
+            .read_series(id)
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("series {}", id)))
+    }
+
+    /// Reads an actor that has to exist, e.g. because it is about to be changed.
+    async fn read_actor(&self, id: &Uuid) -> Result<Actor> {
+        self.store
+            .read_actor_metadata(id)
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("actor {}", id)))
+    }
+
+    /// Returns the actor with the given name, or a new actor that is not stored yet.
+    /// Fails if several actors have the name, the role has to refer to one by id then.
+    async fn resolve_actor(&self, name: (String, String)) -> Result<Actor> {
+        let actors = self.store.find_actors(&name.0, &name.1).await?;
+        let actor = match single(actors, || format!("the actor named {} {}", name.1, name.0))? {
+            Some(actor) => actor,
+            None => Actor {
+                id: Uuid::new_v4(),
+                last_name: name.0,
+                first_name: name.1,
+                ..Default::default()
+            },
+        };
+        Ok(actor)
+    }
+
+    /// Returns the character with the given name, or a new character that is not stored
+    /// yet. Fails if several characters have the name, the role has to refer to one by id then.
+    async fn resolve_character(&self, name: String) -> Result<Character> {
+        let characters = self.store.find_characters(&name).await?;
+        let character = match single(characters, || format!("the character named {}", name))? {
+            Some(character) => character,
+            None => Character {
+                id: Uuid::new_v4(),
+                version: 0,
+                name,
+            },
+        };
+        Ok(character)
+    }
+
+    /// Resolves the characters of roles given as pairs of names and ids, the named ones
+    /// first. Characters that are named but not stored yet are new characters, roles naming
+    /// the same new character share it.
+    async fn resolve_characters(
+        &self,
+        roles: &[(&[String], &[ID])],
+    ) -> Result<Vec<Vec<Character>>> {
+        let mut names = roles
+            .iter()
+            .flat_map(|(names, _)| names.iter().cloned())
+            .collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+        let characters_by_name = futures::future::try_join_all(
+            names.into_iter().map(|name| self.resolve_character(name)),
+        )
+        .await?
+        .into_iter()
+        .map(|character| (character.name.clone(), character))
+        .collect::<HashMap<String, Character>>();
+        let ids = roles
+            .iter()
+            .flat_map(|(_, ids)| ids.iter())
+            .map(parse_id)
+            .collect::<Result<Vec<Uuid>>>()?;
+        let characters_by_id = self.store.read_characters(&ids).await?;
+        roles
+            .iter()
+            .map(|(names, ids)| {
+                let mut characters = names
+                    .iter()
+                    .map(|name| characters_by_name[name].clone())
+                    .collect::<Vec<Character>>();
+                for id in ids.iter() {
+                    let id = parse_id(id)?;
+                    let character = characters_by_id
+                        .get(&id)
+                        .cloned()
+                        .ok_or_else(|| Error::NotFound(format!("character {}", id)))?;
+                    characters.push(character);
+                }
+                Ok(characters)
+            })
+            .collect()
+    }
+
+    /// Links the characters of roles written before characters were stored on their own
+    /// to the stored character of the same name. A name without a single stored character
+    /// reads as a new character, like a name `resolve_character` finds nothing for.
+    async fn link_characters(&self, characters: Vec<Character>) -> Result<Vec<Character>> {
+        futures::future::try_join_all(characters.into_iter().map(|character| async move {
+            if character.is_linked() {
+                return Ok(character);
+            }
+            let mut stored = self.store.find_characters(&character.name).await?;
+            if stored.len() == 1 {
+                Ok(stored.remove(0))
+            } else {
+                Ok(Character {
+                    id: Uuid::new_v4(),
+                    ..character
+                })
+            }
+        }))
+        .await
+    }
+
+    /// Links the characters played in titles like `link_characters`.
+    async fn link_appearances<T>(
+        &self,
+        appearances: Vec<(T, Vec<Character>)>,
+    ) -> Result<Vec<(T, Vec<Character>)>> {
+        let mut linked = Vec::with_capacity(appearances.len());
+        for (title, characters) in appearances {
+            linked.push((title, self.link_characters(characters).await?));
+        }
+        Ok(linked)
+    }
+
+    /// Resolves the actors of the roles of a request, either by id or by name. Actors
+    /// that are named but not stored yet are new actors, characters are resolved alike.
+    async fn resolve_roles(&self, roles: &[PutMovieRequestRole]) -> Result<Vec<Role>> {
+        let mut names = roles
+            .iter()
+            .filter_map(PutMovieRequestRole::actor_name)
+            .collect::<Vec<(String, String)>>();
+        names.sort();
+        names.dedup();
+        let actors_by_name =
+            futures::future::try_join_all(names.into_iter().map(|name| self.resolve_actor(name)))
+                .await?
+                .into_iter()
+                .map(|actor| ((actor.last_name.clone(), actor.first_name.clone()), actor))
+                .collect::<HashMap<(String, String), Actor>>();
+        let ids = roles
+            .iter()
+            .filter_map(|r| r.actor_id.as_ref())
+            .map(parse_id)
+            .collect::<Result<Vec<Uuid>>>()?;
+        let actors_by_id = self
+            .actors
+            .load_many(&ids)
+            .await?
+            .into_iter()
+            .map(|actor| (actor.id, actor))
+            .collect::<HashMap<Uuid, Actor>>();
+        let characters = self
+            .resolve_characters(
+                &roles
+                    .iter()
+                    .map(|r| (r.character_names.as_slice(), r.character_ids()))
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+        let mut resolved = Vec::with_capacity(roles.len());
+        let mut actor_ids = HashSet::new();
+        for ((i, role), characters) in roles.iter().enumerate().zip(characters) {
+            let actor = match &role.actor_id {
+                Some(id) => actors_by_id[&parse_id(id)?].clone(),
+                None => actors_by_name[&role.actor_name().unwrap_or_default()].clone(),
+            };
+            // a role may name an actor another role refers to by id
+            if !actor_ids.insert(actor.id) {
+                return Err(Error::InvalidInput(vec![Violation {
+                    field: format!("roles[{}]", i),
+                    message: DUPLICATE_ACTOR.to_owned(),
+                }]));
+            }
+            resolved.push(Role {
+                actor,
+                credit: role.credit(characters),
+            });
+        }
+        Ok(resolved)
+    }
+
+    /// Joins role rows with their actors through the request's actor loader.
+    async fn load_roles(&self, roles: Vec<(Uuid, Credit)>) -> Result<Vec<Role>> {
+        let ids = roles.iter().map(|(id, _)| *id).collect::<Vec<Uuid>>();
+        let actors = self.actors.load_many(&ids).await?;
+        let mut loaded = Vec::with_capacity(roles.len());
+        for (actor, (_, mut credit)) in actors.into_iter().zip(roles) {
+            credit.characters = self.link_characters(credit.characters).await?;
+            loaded.push(Role { actor, credit });
+        }
+        Ok(loaded)
+    }
+
+    /// Loads roles ordered by billing, unbilled roles last, and by the names of the actors.
+    async fn load_billed_roles(&self, roles: Vec<(Uuid, Credit)>) -> Result<Vec<Role>> {
+        let mut roles = self.load_roles(roles).await?;
+        roles.sort_by(|a, b| {
+            let billing = |role: &Role| {
+                (
+                    role.credit.billing_order.is_none(),
+                    role.credit.billing_order,
+                )
+            };
+            billing(a)
+                .cmp(&billing(b))
+                .then_with(|| a.actor.last_name.cmp(&b.actor.last_name))
+                .then_with(|| a.actor.first_name.cmp(&b.actor.first_name))
+        });
+        Ok(roles)
+    }
+}
+
+connection!(RoleConnection, RoleEdge, Role, Context);
+connection!(AppearanceConnection, AppearanceEdge, Appearance, Context);
+connection!(MovieConnection, MovieEdge, MovieNode, Context);
+connection!(PortrayalConnection, PortrayalEdge, Portrayal, Context);
+connection!(EpisodeConnection, EpisodeEdge, Episode, Context);
+connection!(ReviewConnection, ReviewEdge, Review, Context);
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl Role {
+    #[graphql(name = "actor")]
+    fn actor(&self) -> &Actor {
+        &self.actor
+    }
+
+    #[graphql(name = "characters")]
+    fn characters(&self) -> &Vec<Character> {
+        &self.credit.characters
+    }
+
+    #[graphql(name = "billing_order")]
+    fn billing_order(&self) -> Option<i32> {
+        self.credit.billing_order
+    }
+
+    #[graphql(name = "credited_as")]
+    fn credited_as(&self) -> Option<&str> {
+        self.credit.credited_as.as_deref()
+    }
+
+    #[graphql(name = "uncredited")]
+    fn uncredited(&self) -> bool {
+        self.credit.uncredited
+    }
+
+    #[graphql(name = "voice")]
+    fn voice(&self) -> bool {
+        self.credit.voice
+    }
+
+    #[graphql(name = "cameo")]
+    fn cameo(&self) -> bool {
+        self.credit.cameo
+    }
+}
+
+/// The crew members of a department.
+#[derive(GraphQLObject)]
+pub struct CrewDepartment {
+    #[graphql(name = "department")]
+    department: Department,
+    #[graphql(name = "members")]
+    members: Vec<CrewCredit>,
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl Actor {
+    #[graphql(name = "id")]
+    fn id(&self) -> ID {
+        ID::from(self.id.to_string())
+    }
+
+    #[graphql(name = "version")]
+    fn version(&self) -> i32 {
+        self.version
+    }
+
+    #[graphql(name = "last_name")]
+    fn last_name(&self) -> &str {
+        &self.last_name
+    }
+
+    #[graphql(name = "first_name")]
+    fn first_name(&self) -> &str {
+        &self.first_name
+    }
+
+    #[graphql(name = "birth_date")]
+    fn birth_date(&self) -> Option<NaiveDate> {
+        self.birth_date
+    }
+
+    #[graphql(name = "imdb_id")]
+    fn imdb_id(&self) -> Option<&str> {
+        self.imdb_id.as_deref()
+    }
+
+    #[graphql(name = "biography")]
+    fn biography(&self) -> Option<&str> {
+        self.biography.as_deref()
+    }
+
+    #[graphql(name = "aliases")]
+    fn aliases(&self) -> &Vec<String> {
+        &self.aliases
+    }
+
+    #[graphql(name = "movies")]
+    async fn movies(&self, context: &Context) -> Result<Vec<Appearance>> {
+        let movies = context.store.read_actor_movies(&self.id).await?;
+        let movies = context.link_appearances(movies).await?;
+        Ok(movies.into_iter().map(Appearance::from).collect())
+    }
+
+    #[graphql(name = "movies_connection")]
+    async fn movies_connection(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<AppearanceConnection> {
+        let page = context
+            .store
+            .read_actor_movies_page(&self.id, page_size(first)?, after.clone())
+            .await?;
+        let (cursors, movies): (Vec<String>, Vec<_>) = page.items.into_iter().unzip();
+        let movies = context.link_appearances(movies).await?;
+        let page = Page {
+            items: cursors.into_iter().zip(movies).collect(),
+            has_next_page: page.has_next_page,
+        }
+        .try_map(|movie| Ok(Appearance::from(movie)))?;
+        Ok(AppearanceConnection::new(page, &after))
+    }
+
+    /// The movies and episodes the actor appears in, oldest first.
+    #[graphql(name = "filmography")]
+    async fn filmography(&self, context: &Context) -> Result<Vec<FilmographyEntry>> {
+        let movies = context.store.read_actor_movies(&self.id).await?;
+        let movies = context.link_appearances(movies).await?;
+        let episodes = context.store.read_actor_episodes(&self.id).await?;
+        let episodes = context.link_appearances(episodes).await?;
+        let mut entries = movies
+            .into_iter()
+            .map(|(meta, characters)| {
+                let published_at = meta.published_at;
+                (
+                    published_at,
+                    TitleValue::from(MovieNode { meta }),
+                    characters,
+                )
+            })
+            .chain(episodes.into_iter().map(|(episode, characters)| {
+                (episode.published_at, TitleValue::from(episode), characters)
+            }))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(published_at, _, _)| *published_at);
+        Ok(entries
+            .into_iter()
+            .map(|(_, title, characters)| FilmographyEntry { title, characters })
+            .collect())
+    }
+}
+
+/// A movie or an episode an actor appears in, together with the characters played.
+pub struct FilmographyEntry {
+    title: TitleValue,
+    characters: Vec<Character>,
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl FilmographyEntry {
+    #[graphql(name = "title")]
+    fn title(&self) -> &TitleValue {
+        &self.title
+    }
+
+    #[graphql(name = "characters")]
+    fn characters(&self) -> &Vec<Character> {
+        &self.characters
+    }
+}
+
+/// A movie an actor appears in, together with the characters played.
+pub struct Appearance {
+    movie: MovieNode,
+    characters: Vec<Character>,
+}
+
+impl From<(MovieMetadata, Vec<Character>)> for Appearance {
+    fn from((meta, characters): (MovieMetadata, Vec<Character>)) -> Self {
+        Appearance {
+            movie: MovieNode { meta },
+            characters,
+        }
+    }
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl Appearance {
+    #[graphql(name = "movie")]
+    fn movie(&self) -> &MovieNode {
+        &self.movie
+    }
+
+    #[graphql(name = "characters")]
+    fn characters(&self) -> &Vec<Character> {
+        &self.characters
+    }
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl Character {
+    #[graphql(name = "id")]
+    fn id(&self) -> ID {
+        ID::from(self.id.to_string())
+    }
+
+    #[graphql(name = "name")]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Every movie or episode and actor portraying the character, oldest title first.
+    #[graphql(name = "portrayals")]
+    async fn portrayals(&self, context: &Context) -> Result<Vec<Portrayal>> {
+        let mut portrayals = context.store.read_character_portrayals(&self.id).await?;
+        portrayals.sort_by_key(|(production, actor_id)| (production.published_at(), *actor_id));
+        let ids = portrayals.iter().map(|(_, id)| *id).collect::<Vec<Uuid>>();
+        let actors = context.actors.load_many(&ids).await?;
+        Ok(portrayals
+            .into_iter()
+            .zip(actors)
+            .map(|((production, _), actor)| Portrayal { production, actor })
+            .collect())
+    }
+
+    /// The portrayals of the character, the episodes ordered by series, season and episode
+    /// number first and then the movies ordered by id.
+    #[graphql(name = "portrayals_connection")]
+    async fn portrayals_connection(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<PortrayalConnection> {
+        let page = context
+            .store
+            .read_character_portrayals_page(&self.id, page_size(first)?, after.clone())
+            .await?;
+        let (cursors, portrayals): (Vec<String>, Vec<_>) = page.items.into_iter().unzip();
+        let ids = portrayals.iter().map(|(_, id)| *id).collect::<Vec<Uuid>>();
+        let actors = context.actors.load_many(&ids).await?;
+        let page = Page {
+            items: cursors
+                .into_iter()
+                .zip(portrayals.into_iter().zip(actors))
+                .map(|(cursor, ((production, _), actor))| (cursor, Portrayal { production, actor }))
+                .collect(),
+            has_next_page: page.has_next_page,
+        };
+        Ok(PortrayalConnection::new(page, &after))
+    }
+}
+
+/// An actor playing a character in a movie or an episode.
+pub struct Portrayal {
+    production: Production,
+    actor: Actor,
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl Portrayal {
+    /// The movie or episode the character is portrayed in.
+    #[graphql(name = "title")]
+    fn title(&self) -> TitleValue {
+        match self.production.clone() {
+            Production::Movie(meta) => TitleValue::from(MovieNode { meta }),
+            Production::Episode(episode) => TitleValue::from(episode),
+        }
+    }
+
+    /// The movie, `null` if the character is portrayed in an episode.
+    #[graphql(name = "movie")]
+    fn movie(&self) -> Option<MovieNode> {
+        match &self.production {
+            Production::Movie(meta) => Some(MovieNode { meta: meta.clone() }),
+            Production::Episode(_) => None,
+        }
+    }
+
+    #[graphql(name = "actor")]
+    fn actor(&self) -> &Actor {
+        &self.actor
+    }
+}
+
+/// Movies and episodes of series are both titles, so they can be listed together.
+#[juniper::graphql_interface(for = [MovieNode, Series, Episode], context = Context)]
+pub trait Title {
+    #[graphql(name = "id")]
+    fn id(&self) -> ID;
+
+    #[graphql(name = "title")]
+    fn title(&self) -> &str;
+
+    #[graphql(name = "published_at")]
+    fn published_at(&self) -> DateTime<Utc>;
+}
+
+/// A movie as exposed through GraphQL, the roles are only read when requested.
+pub struct MovieNode {
+    meta: MovieMetadata,
+}
+
+#[juniper::graphql_interface]
+impl Title for MovieNode {
+    fn id(&self) -> ID {
+        ID::from(self.meta.id.to_string())
+    }
+
+    fn title(&self) -> &str {
+        &self.meta.title
+    }
+
+    fn published_at(&self) -> DateTime<Utc> {
+        self.meta.published_at
+    }
+}
+
+#[juniper::graphql_object(Context = Context, name = "Movie", rename = "none", impl = TitleValue)]
+impl MovieNode {
+    #[graphql(name = "id")]
+    fn id(&self) -> ID {
+        ID::from(self.meta.id.to_string())
+    }
+
+    #[graphql(name = "title")]
+    fn title(&self) -> &str {
+        &self.meta.title
+    }
+
+    #[graphql(name = "published_at")]
+    fn published_at(&self) -> DateTime<Utc> {
+        self.meta.published_at
+    }
+
+    #[graphql(name = "version")]
+    fn version(&self) -> i32 {
+        self.meta.version
+    }
+
+    #[graphql(name = "meta")]
+    fn meta(&self) -> &MovieMetadata {
+        &self.meta
+    }
+
+    /// The cast in billing order, roles without a billing order come last ordered by the
+    /// names of the actors.
+    #[graphql(name = "roles")]
+    async fn roles(&self, context: &Context) -> Result<Vec<Role>> {
+        let roles = context.store.read_movie_roles(&self.meta.id).await?;
+        context.load_billed_roles(roles).await
+    }
+
+    /// The collections the movie belongs to ordered by name.
+    #[graphql(name = "collections")]
+    async fn collections(&self, context: &Context) -> Result<Vec<Collection>> {
+        context.store.read_movie_collections(&self.meta.id).await
+    }
+
+    /// The crew grouped by department.
+    #[graphql(name = "crew")]
+    async fn crew(&self, context: &Context) -> Result<Vec<CrewDepartment>> {
+        let mut departments = Vec::<CrewDepartment>::new();
+        for credit in context.store.read_movie_crew(&self.meta.id).await? {
+            match departments.last_mut() {
+                Some(group) if group.department == credit.department => group.members.push(credit),
+                _ => departments.push(CrewDepartment {
+                    department: credit.department,
+                    members: vec![credit],
+                }),
+            }
+        }
+        departments.sort_by_key(|group| group.department);
+        Ok(departments)
+    }
+
+    /// The reviews of users, the latest first.
+    #[graphql(name = "reviews")]
+    async fn reviews(&self, context: &Context) -> Result<Vec<Review>> {
+        let mut reviews = context.store.read_movie_reviews(&self.meta.id).await?;
+        reviews.sort_by_key(|review| std::cmp::Reverse(review.reviewed_at));
+        Ok(reviews)
+    }
+
+    #[graphql(name = "rating_summary")]
+    fn rating_summary(&self) -> &RatingSummary {
+        &self.meta.ratings
+    }
+
+    /// The roles in the order of the actor ids, unlike `roles` not by billing. The sort key
+    /// of a role is the key of the inverted index of the movies of an actor, it can't
+    /// hold the billing order.
+    #[graphql(name = "roles_connection")]
+    async fn roles_connection(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<RoleConnection> {
+        let page = context
+            .store
+            .read_movie_roles_page(&self.meta.id, page_size(first)?, after.clone())
+            .await?;
+        let (cursors, roles): (Vec<String>, Vec<_>) = page.items.into_iter().unzip();
+        let roles = context.load_roles(roles).await?;
+        let page = Page {
+            items: cursors.into_iter().zip(roles).collect(),
+            has_next_page: page.has_next_page,
+        };
+        Ok(RoleConnection::new(page, &after))
+    }
+
+    /// The reviews in the order of the user ids, unlike `reviews` not the latest first.
+    /// Reviews are keyed by user, their sort key can't hold the time of the review.
+    #[graphql(name = "reviews_connection")]
+    async fn reviews_connection(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<ReviewConnection> {
+        let page = context
+            .store
+            .read_movie_reviews_page(&self.meta.id, page_size(first)?, after.clone())
+            .await?;
+        Ok(ReviewConnection::new(page, &after))
+    }
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl Review {
+    #[graphql(name = "user_id")]
+    fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    #[graphql(name = "version")]
+    fn version(&self) -> i32 {
+        self.version
+    }
+
+    #[graphql(name = "rating")]
+    fn rating(&self) -> i32 {
+        self.rating
+    }
+
+    #[graphql(name = "text")]
+    fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    #[graphql(name = "reviewed_at")]
+    fn reviewed_at(&self) -> DateTime<Utc> {
+        self.reviewed_at
+    }
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl RatingSummary {
+    #[graphql(name = "count")]
+    fn count(&self) -> i32 {
+        self.count
+    }
+
+    /// The average rating, `null` if the movie has not been rated yet.
+    #[graphql(name = "mean")]
+    fn mean(&self) -> Option<f64> {
+        self.mean()
+    }
+
+    /// The number of ratings for every rating from 1 to 10.
+    #[graphql(name = "histogram")]
+    fn histogram(&self) -> Vec<RatingCount> {
+        (1..=MAX_RATING)
+            .zip(self.histogram.iter())
+            .map(|(rating, count)| RatingCount {
+                rating,
+                count: *count,
+            })
+            .collect()
+    }
+}
+
+/// The number of ratings with a rating.
+#[derive(GraphQLObject)]
+pub struct RatingCount {
+    #[graphql(name = "rating")]
+    rating: i32,
+    #[graphql(name = "count")]
+    count: i32,
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl Collection {
+    #[graphql(name = "id")]
+    fn id(&self) -> ID {
+        ID::from(self.id.to_string())
+    }
+
+    #[graphql(name = "version")]
+    fn version(&self) -> i32 {
+        self.version
+    }
+
+    #[graphql(name = "name")]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The movies of the collection ordered by their position.
+    #[graphql(name = "movies")]
+    async fn movies(&self, context: &Context) -> Result<Vec<CollectionEntry>> {
+        let movies = context.store.read_collection_movies(&self.id).await?;
+        Ok(movies
+            .into_iter()
+            .map(|(meta, position)| CollectionEntry {
+                movie: MovieNode { meta },
+                position,
+            })
+            .collect())
+    }
+}
+
+/// A movie of a collection together with its position in the collection.
+pub struct CollectionEntry {
+    movie: MovieNode,
+    position: i32,
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl CollectionEntry {
+    #[graphql(name = "movie")]
+    fn movie(&self) -> &MovieNode {
+        &self.movie
+    }
+
+    #[graphql(name = "position")]
+    fn position(&self) -> i32 {
+        self.position
+    }
+}
+
+#[juniper::graphql_interface]
+impl Title for Series {
+    fn id(&self) -> ID {
+        ID::from(self.id.to_string())
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn published_at(&self) -> DateTime<Utc> {
+        self.published_at
+    }
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none", impl = TitleValue)]
+impl Series {
+    #[graphql(name = "id")]
+    fn id(&self) -> ID {
+        ID::from(self.id.to_string())
+    }
+
+    #[graphql(name = "version")]
+    fn version(&self) -> i32 {
+        self.version
+    }
+
+    #[graphql(name = "title")]
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    #[graphql(name = "published_at")]
+    fn published_at(&self) -> DateTime<Utc> {
+        self.published_at
+    }
+
+    #[graphql(name = "seasons")]
+    async fn seasons(&self, context: &Context) -> Result<Vec<Season>> {
+        context.store.read_series_seasons(&self.id).await
+    }
+
+    /// The episodes ordered by season and episode number, only those of a season if given.
+    #[graphql(name = "episodes")]
+    async fn episodes(&self, context: &Context, season: Option<i32>) -> Result<Vec<Episode>> {
+        context.store.read_series_episodes(&self.id, season).await
+    }
+
+    #[graphql(name = "episodes_connection")]
+    async fn episodes_connection(
+        &self,
+        context: &Context,
+        season: Option<i32>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<EpisodeConnection> {
+        let page = context
+            .store
+            .read_series_episodes_page(&self.id, season, page_size(first)?, after.clone())
+            .await?;
+        Ok(EpisodeConnection::new(page, &after))
+    }
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
+impl Season {
+    #[graphql(name = "number")]
+    fn number(&self) -> i32 {
+        self.number
+    }
+
+    #[graphql(name = "title")]
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    #[graphql(name = "episodes")]
+    async fn episodes(&self, context: &Context) -> Result<Vec<Episode>> {
+        context
+            .store
+            .read_series_episodes(&self.series_id, Some(self.number))
+            .await
+    }
+
+    #[graphql(name = "episodes_connection")]
+    async fn episodes_connection(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<EpisodeConnection> {
+        let page = context
+            .store
+            .read_series_episodes_page(
+                &self.series_id,
+                Some(self.number),
+                page_size(first)?,
+                after.clone(),
+            )
+            .await?;
+        Ok(EpisodeConnection::new(page, &after))
+    }
+}
+
+/// Episodes are identified by their series and numbers, like `<series id>/1/3`.
+fn episode_id(episode: &Episode) -> ID {
+    ID::from(format!(
+        "{}/{}/{}",
+        episode.series_id, episode.season_number, episode.episode_number
+    ))
+}
+
+#[juniper::graphql_interface]
+impl Title for Episode {
+    fn id(&self) -> ID {
+        episode_id(self)
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn published_at(&self) -> DateTime<Utc> {
+        self.published_at
+    }
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none", impl = TitleValue)]
+impl Episode {
+    #[graphql(name = "id")]
+    fn id(&self) -> ID {
+        episode_id(self)
+    }
+
+    #[graphql(name = "title")]
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    #[graphql(name = "published_at")]
+    fn published_at(&self) -> DateTime<Utc> {
+        self.published_at
+    }
+
+    #[graphql(name = "season_number")]
+    fn season_number(&self) -> i32 {
+        self.season_number
+    }
+
+    #[graphql(name = "episode_number")]
+    fn episode_number(&self) -> i32 {
+        self.episode_number
+    }
+
+    #[graphql(name = "series")]
+    async fn series(&self, context: &Context) -> Result<Series> {
+        context.read_series(&self.series_id).await
+    }
+
+    /// The cast in billing order, roles without a billing order come last ordered by the
+    /// names of the actors.
+    #[graphql(name = "roles")]
+    async fn roles(&self, context: &Context) -> Result<Vec<Role>> {
+        let roles = context
+            .store
+            .read_episode_roles(&self.series_id, self.season_number, self.episode_number)
+            .await?;
+        context.load_billed_roles(roles).await
+    }
+}
+
+/// Every year of a `movies_between` range is a query of its own, a page may have to query
+/// the years without movies one after another, so ranges are limited.
+const MAX_YEARS_BETWEEN: i32 = 100;
+
+pub struct QueryRoot;
+pub struct MutationRoot;
+pub type Schema = RootNode<'static, QueryRoot, MutationRoot, EmptySubscription<Context>>;
+
+pub fn create_schema() -> Schema {
+    Schema::new(QueryRoot {}, MutationRoot {}, EmptySubscription::new())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct PutMovieRequestRole {
+    /// Refers to a stored actor, instead of the names of the actor.
+    #[graphql(name = "actor_id")]
+    actor_id: Option<ID>,
+    #[graphql(name = "actor_last_name")]
+    actor_last_name: Option<String>,
+    #[graphql(name = "actor_first_name")]
+    actor_first_name: Option<String>,
+    /// Characters are matched by name like actors, characters sharing a name have to be
+    /// referred to by id.
+    #[graphql(name = "character_names")]
+    character_names: Vec<String>,
+    /// Refers to stored characters, in addition to the named ones.
+    #[graphql(name = "character_ids")]
+    character_ids: Option<Vec<ID>>,
+    #[graphql(name = "billing_order")]
+    billing_order: Option<i32>,
+    #[graphql(name = "credited_as")]
+    credited_as: Option<String>,
+    #[graphql(name = "uncredited")]
+    uncredited: Option<bool>,
+    #[graphql(name = "voice")]
+    voice: Option<bool>,
+    #[graphql(name = "cameo")]
+    cameo: Option<bool>,
+}
+
+impl PutMovieRequestRole {
+    fn credit(&self, characters: Vec<Character>) -> Credit {
+        Credit {
+            characters,
+            billing_order: self.billing_order,
+            credited_as: self.credited_as.clone(),
+            uncredited: self.uncredited.unwrap_or_default(),
+            voice: self.voice.unwrap_or_default(),
+            cameo: self.cameo.unwrap_or_default(),
+            unknown: HashMap::new(),
+        }
+    }
+
+    fn character_ids(&self) -> &[ID] {
+        self.character_ids.as_deref().unwrap_or_default()
+    }
+
+    /// The names of the actor, if the role does not refer to an actor by id.
+    fn actor_name(&self) -> Option<(String, String)> {
+        match self.actor_id {
+            Some(_) => None,
+            None => Some((
+                self.actor_last_name.clone().unwrap_or_default(),
+                self.actor_first_name.clone().unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct PutMovieRequest {
+    #[graphql(name = "id")]
+    id: Option<ID>,
+    #[graphql(name = "title")]
+    title: String,
+    #[graphql(name = "imdb_id")]
+    imdb_id: Option<String>,
+    #[graphql(name = "published_at")]
+    published_at: DateTime<Utc>,
+    #[graphql(name = "genres")]
+    genres: Option<Vec<String>>,
+    #[graphql(name = "runtime_minutes")]
+    runtime_minutes: Option<i32>,
+    #[graphql(name = "original_language")]
+    original_language: Option<String>,
+    #[graphql(name = "countries")]
+    countries: Option<Vec<String>>,
+    #[graphql(name = "synopsis")]
+    synopsis: Option<String>,
+    #[graphql(name = "age_rating")]
+    age_rating: Option<String>,
+    #[graphql(name = "directors")]
+    directors: Option<Vec<String>>,
+    #[graphql(name = "writers")]
+    writers: Option<Vec<String>>,
+    #[graphql(name = "poster_url")]
+    poster_url: Option<String>,
+    #[graphql(name = "release_dates")]
+    release_dates: Option<Vec<ReleaseDateInput>>,
+    #[graphql(name = "roles")]
+    roles: Vec<PutMovieRequestRole>,
+    #[graphql(name = "crew")]
+    crew: Option<Vec<PutMovieRequestCrew>>,
+    #[graphql(name = "expected_version")]
+    expected_version: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct PutMovieRequestCrew {
+    #[graphql(name = "name")]
+    name: String,
+    #[graphql(name = "department")]
+    department: Department,
+    #[graphql(name = "job")]
+    job: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct ReleaseDateInput {
+    #[graphql(name = "country")]
+    country: String,
+    #[graphql(name = "released_on")]
+    released_on: NaiveDate,
+}
+
+/// Every actor has one role per movie, all characters played are part of it.
+const DUPLICATE_ACTOR: &str = "the actor already has a role in the movie";
+
+impl PutMovieRequest {
+    /// The request as an update of the stored movie, optional fields that are not given
+    /// keep their stored values.
+    fn update(&self) -> UpdateMovieMetadataRequest {
+        UpdateMovieMetadataRequest {
+            title: Some(self.title.clone()),
+            imdb_id: self.imdb_id.clone(),
+            published_at: Some(self.published_at),
+            genres: self.genres.clone(),
+            runtime_minutes: self.runtime_minutes,
+            original_language: self.original_language.clone(),
+            countries: self.countries.clone(),
+            synopsis: self.synopsis.clone(),
+            age_rating: self.age_rating.clone(),
+            directors: self.directors.clone(),
+            writers: self.writers.clone(),
+            poster_url: self.poster_url.clone(),
+            release_dates: self.release_dates.clone(),
+            clear: None,
+        }
     }
 
-    links {
-        Data(crate::data::Error, crate::data::ErrorKind);
+    /// Checks all fields of the written movie and the roles and crew of the request,
+    /// reporting every violation at once.
+    fn validate(&self, meta: &MovieMetadata) -> Result<()> {
+        let mut validator = Validator::new();
+        validate_movie(&mut validator, meta);
+        validate_roles(&mut validator, &self.roles);
+        let mut crew = HashSet::new();
+        for (i, credit) in self.crew.iter().flatten().enumerate() {
+            let field = format!("crew[{}]", i);
+            validator.required(&format!("{}.name", field), &credit.name);
+            // crew members are keyed by department and name
+            if !crew.insert((credit.department, &credit.name)) {
+                validator.violation(&field, "the crew member is already part of the department");
+            }
+        }
+        validator.finish()
     }
 }
 
-pub struct QueryRoot;
-pub struct MutationRoot;
-pub type Schema = RootNode<'static, QueryRoot, MutationRoot>;
+/// Checks the fields of a movie, they are named like the fields of the requests.
+fn validate_movie(validator: &mut Validator, meta: &MovieMetadata) {
+    validator.title("title", &meta.title, &meta.published_at);
+    if let Some(imdb_id) = &meta.imdb_id {
+        validator.imdb_id("imdb_id", imdb_id);
+    }
+    validator.published_at("published_at", &meta.published_at);
+    for (i, genre) in meta.genres.iter().enumerate() {
+        validator.required(&format!("genres[{}]", i), genre);
+    }
+    if let Some(runtime_minutes) = meta.runtime_minutes {
+        validator.positive("runtime_minutes", runtime_minutes);
+    }
+    if let Some(language) = &meta.original_language {
+        validator.language("original_language", language);
+    }
+    for (i, country) in meta.countries.iter().enumerate() {
+        validator.country(&format!("countries[{}]", i), country);
+    }
+    for (i, name) in meta.directors.iter().enumerate() {
+        validator.required(&format!("directors[{}]", i), name);
+    }
+    for (i, name) in meta.writers.iter().enumerate() {
+        validator.required(&format!("writers[{}]", i), name);
+    }
+    if let Some(poster_url) = &meta.poster_url {
+        validator.url("poster_url", poster_url);
+    }
+    for (i, release) in meta.release_dates.iter().enumerate() {
+        validator.country(&format!("release_dates[{}].country", i), &release.country);
+    }
+}
 
-pub fn create_schema() -> Schema {
-    Schema::new(QueryRoot {}, MutationRoot {})
+/// Optional fields of a movie an update can clear.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, GraphQLEnum)]
+#[serde(rename_all = "snake_case")]
+enum MovieMetadataField {
+    #[graphql(name = "IMDB_ID")]
+    ImdbId,
+    #[graphql(name = "RUNTIME_MINUTES")]
+    RuntimeMinutes,
+    #[graphql(name = "ORIGINAL_LANGUAGE")]
+    OriginalLanguage,
+    #[graphql(name = "SYNOPSIS")]
+    Synopsis,
+    #[graphql(name = "AGE_RATING")]
+    AgeRating,
+    #[graphql(name = "POSTER_URL")]
+    PosterUrl,
 }
 
+/// Fields that are not given are kept, optional fields listed in `clear` are removed.
+/// Lists are replaced as a whole, an empty list removes all entries.
 #[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
 #[serde(rename_all = "snake_case")]
-struct PutMovieRequestRole {
-    #[graphql(name = "actor_last_name")]
-    actor_last_name: String,
-    #[graphql(name = "actor_first_name")]
-    actor_first_name: String,
-    #[graphql(name = "character_names")]
-    character_names: Vec<String>,
+struct UpdateMovieMetadataRequest {
+    #[graphql(name = "title")]
+    title: Option<String>,
+    #[graphql(name = "imdb_id")]
+    imdb_id: Option<String>,
+    #[graphql(name = "published_at")]
+    published_at: Option<DateTime<Utc>>,
+    #[graphql(name = "genres")]
+    genres: Option<Vec<String>>,
+    #[graphql(name = "runtime_minutes")]
+    runtime_minutes: Option<i32>,
+    #[graphql(name = "original_language")]
+    original_language: Option<String>,
+    #[graphql(name = "countries")]
+    countries: Option<Vec<String>>,
+    #[graphql(name = "synopsis")]
+    synopsis: Option<String>,
+    #[graphql(name = "age_rating")]
+    age_rating: Option<String>,
+    #[graphql(name = "directors")]
+    directors: Option<Vec<String>>,
+    #[graphql(name = "writers")]
+    writers: Option<Vec<String>>,
+    #[graphql(name = "poster_url")]
+    poster_url: Option<String>,
+    #[graphql(name = "release_dates")]
+    release_dates: Option<Vec<ReleaseDateInput>>,
+    #[graphql(name = "clear")]
+    clear: Option<Vec<MovieMetadataField>>,
+}
+
+impl UpdateMovieMetadataRequest {
+    fn clears(&self, field: MovieMetadataField) -> bool {
+        self.clear.as_ref().is_some_and(|c| c.contains(&field))
+    }
+
+    /// Applies the given fields to the movie and clears the listed ones.
+    fn apply(&self, meta: &mut MovieMetadata) {
+        if let Some(title) = &self.title {
+            meta.title = title.clone();
+        }
+        if let Some(imdb_id) = &self.imdb_id {
+            meta.imdb_id = Some(imdb_id.clone());
+        }
+        if let Some(published_at) = self.published_at {
+            meta.published_at = published_at;
+        }
+        if let Some(genres) = &self.genres {
+            meta.genres = genres.clone();
+        }
+        if let Some(runtime_minutes) = self.runtime_minutes {
+            meta.runtime_minutes = Some(runtime_minutes);
+        }
+        if let Some(language) = &self.original_language {
+            meta.original_language = Some(language.clone());
+        }
+        if let Some(countries) = &self.countries {
+            meta.countries = countries.clone();
+        }
+        if let Some(synopsis) = &self.synopsis {
+            meta.synopsis = Some(synopsis.clone());
+        }
+        if let Some(age_rating) = &self.age_rating {
+            meta.age_rating = Some(age_rating.clone());
+        }
+        if let Some(directors) = &self.directors {
+            meta.directors = directors.clone();
+        }
+        if let Some(writers) = &self.writers {
+            meta.writers = writers.clone();
+        }
+        if let Some(poster_url) = &self.poster_url {
+            meta.poster_url = Some(poster_url.clone());
+        }
+        if let Some(release_dates) = &self.release_dates {
+            meta.release_dates = release_dates
+                .iter()
+                .map(|release| ReleaseDate {
+                    country: release.country.clone(),
+                    released_on: release.released_on,
+                })
+                .collect();
+        }
+        for field in self.clear.iter().flatten() {
+            match field {
+                MovieMetadataField::ImdbId => meta.imdb_id = None,
+                MovieMetadataField::RuntimeMinutes => meta.runtime_minutes = None,
+                MovieMetadataField::OriginalLanguage => meta.original_language = None,
+                MovieMetadataField::Synopsis => meta.synopsis = None,
+                MovieMetadataField::AgeRating => meta.age_rating = None,
+                MovieMetadataField::PosterUrl => meta.poster_url = None,
+            }
+        }
+    }
+
+    /// Checks the request and the updated movie.
+    fn validate(&self, meta: &MovieMetadata) -> Result<()> {
+        let mut validator = Validator::new();
+        let given = [
+            (
+                MovieMetadataField::ImdbId,
+                "imdb_id",
+                self.imdb_id.is_some(),
+            ),
+            (
+                MovieMetadataField::RuntimeMinutes,
+                "runtime_minutes",
+                self.runtime_minutes.is_some(),
+            ),
+            (
+                MovieMetadataField::OriginalLanguage,
+                "original_language",
+                self.original_language.is_some(),
+            ),
+            (
+                MovieMetadataField::Synopsis,
+                "synopsis",
+                self.synopsis.is_some(),
+            ),
+            (
+                MovieMetadataField::AgeRating,
+                "age_rating",
+                self.age_rating.is_some(),
+            ),
+            (
+                MovieMetadataField::PosterUrl,
+                "poster_url",
+                self.poster_url.is_some(),
+            ),
+        ];
+        for (field, name, given) in &given {
+            if *given && self.clears(*field) {
+                validator.violation("clear", format!("must not list {}, it is given", name));
+            }
+        }
+        validate_movie(&mut validator, meta);
+        validator.finish()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
 #[serde(rename_all = "snake_case")]
-struct PutMovieRequest {
-    #[graphql(name = "title")]
-    title: String,
+struct CreateActorRequest {
+    #[graphql(name = "last_name")]
+    last_name: String,
+    #[graphql(name = "first_name")]
+    first_name: String,
+    #[graphql(name = "birth_date")]
+    birth_date: Option<NaiveDate>,
     #[graphql(name = "imdb_id")]
     imdb_id: Option<String>,
+    #[graphql(name = "biography")]
+    biography: Option<String>,
+    #[graphql(name = "aliases")]
+    aliases: Option<Vec<String>>,
+}
+
+/// Optional fields of an actor an update can clear.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, GraphQLEnum)]
+#[serde(rename_all = "snake_case")]
+enum ActorField {
+    #[graphql(name = "BIRTH_DATE")]
+    BirthDate,
+    #[graphql(name = "IMDB_ID")]
+    ImdbId,
+    #[graphql(name = "BIOGRAPHY")]
+    Biography,
+}
+
+/// Fields that are not given are kept, optional fields listed in `clear` are removed.
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct UpdateActorRequest {
+    #[graphql(name = "last_name")]
+    last_name: Option<String>,
+    #[graphql(name = "first_name")]
+    first_name: Option<String>,
+    #[graphql(name = "birth_date")]
+    birth_date: Option<NaiveDate>,
+    #[graphql(name = "imdb_id")]
+    imdb_id: Option<String>,
+    #[graphql(name = "biography")]
+    biography: Option<String>,
+    #[graphql(name = "aliases")]
+    aliases: Option<Vec<String>>,
+    #[graphql(name = "clear")]
+    clear: Option<Vec<ActorField>>,
+}
+
+impl UpdateActorRequest {
+    fn clears(&self, field: ActorField) -> bool {
+        self.clear.as_ref().is_some_and(|c| c.contains(&field))
+    }
+
+    /// Applies the given fields to the actor and clears the listed ones.
+    fn apply(self, actor: &mut Actor) {
+        let (clears_birth_date, clears_imdb_id, clears_biography) = (
+            self.clears(ActorField::BirthDate),
+            self.clears(ActorField::ImdbId),
+            self.clears(ActorField::Biography),
+        );
+        if let Some(last_name) = self.last_name {
+            actor.last_name = last_name;
+        }
+        if let Some(first_name) = self.first_name {
+            actor.first_name = first_name;
+        }
+        if let Some(birth_date) = self.birth_date {
+            actor.birth_date = Some(birth_date);
+        } else if clears_birth_date {
+            actor.birth_date = None;
+        }
+        if let Some(imdb_id) = self.imdb_id {
+            actor.imdb_id = Some(imdb_id);
+        } else if clears_imdb_id {
+            actor.imdb_id = None;
+        }
+        if let Some(biography) = self.biography {
+            actor.biography = Some(biography);
+        } else if clears_biography {
+            actor.biography = None;
+        }
+        if let Some(aliases) = self.aliases {
+            actor.aliases = aliases;
+        }
+    }
+
+    /// Checks that no field is both given and cleared, the fields themselves are checked
+    /// in the updated actor.
+    fn validate(&self) -> Result<()> {
+        let mut validator = Validator::new();
+        for (field, given, name) in &[
+            (
+                ActorField::BirthDate,
+                self.birth_date.is_some(),
+                "birth_date",
+            ),
+            (ActorField::ImdbId, self.imdb_id.is_some(), "imdb_id"),
+            (ActorField::Biography, self.biography.is_some(), "biography"),
+        ] {
+            if *given && self.clears(*field) {
+                validator.violation("clear", format!("must not list {}, it is given", name));
+            }
+        }
+        validator.finish()
+    }
+}
+
+/// Optional fields of a credit an update can clear.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, GraphQLEnum)]
+#[serde(rename_all = "snake_case")]
+enum CreditField {
+    #[graphql(name = "BILLING_ORDER")]
+    BillingOrder,
+    #[graphql(name = "CREDITED_AS")]
+    CreditedAs,
+}
+
+/// How an actor is credited for a role, besides the characters. Fields that are not given
+/// are kept, optional fields listed in `clear` are removed.
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct RoleCreditRequest {
+    #[graphql(name = "billing_order")]
+    billing_order: Option<i32>,
+    #[graphql(name = "credited_as")]
+    credited_as: Option<String>,
+    #[graphql(name = "uncredited")]
+    uncredited: Option<bool>,
+    #[graphql(name = "voice")]
+    voice: Option<bool>,
+    #[graphql(name = "cameo")]
+    cameo: Option<bool>,
+    #[graphql(name = "clear")]
+    clear: Option<Vec<CreditField>>,
+}
+
+impl RoleCreditRequest {
+    fn clears(&self, field: CreditField) -> bool {
+        self.clear.as_ref().is_some_and(|c| c.contains(&field))
+    }
+
+    /// Applies the given fields to the credit and clears the listed ones.
+    fn apply(&self, credit: &mut Credit) {
+        if let Some(billing_order) = self.billing_order {
+            credit.billing_order = Some(billing_order);
+        } else if self.clears(CreditField::BillingOrder) {
+            credit.billing_order = None;
+        }
+        if let Some(credited_as) = &self.credited_as {
+            credit.credited_as = Some(credited_as.clone());
+        } else if self.clears(CreditField::CreditedAs) {
+            credit.credited_as = None;
+        }
+        if let Some(uncredited) = self.uncredited {
+            credit.uncredited = uncredited;
+        }
+        if let Some(voice) = self.voice {
+            credit.voice = voice;
+        }
+        if let Some(cameo) = self.cameo {
+            credit.cameo = cameo;
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut validator = Validator::new();
+        for (field, given, name) in &[
+            (
+                CreditField::BillingOrder,
+                self.billing_order.is_some(),
+                "billing_order",
+            ),
+            (
+                CreditField::CreditedAs,
+                self.credited_as.is_some(),
+                "credited_as",
+            ),
+        ] {
+            if *given && self.clears(*field) {
+                validator.violation(
+                    "credit.clear",
+                    format!("must not list {}, it is given", name),
+                );
+            }
+        }
+        if let Some(billing_order) = self.billing_order {
+            validator.positive("credit.billing_order", billing_order);
+        }
+        if let Some(credited_as) = &self.credited_as {
+            validator.required("credit.credited_as", credited_as);
+        }
+        validator.finish()
+    }
+}
+
+/// Checks the roles of a movie or an episode.
+fn validate_roles(validator: &mut Validator, roles: &[PutMovieRequestRole]) {
+    let (mut ids, mut names) = (HashSet::new(), HashSet::new());
+    for (i, role) in roles.iter().enumerate() {
+        let field = format!("roles[{}]", i);
+        let unique = match &role.actor_id {
+            Some(_) if role.actor_last_name.is_some() || role.actor_first_name.is_some() => {
+                validator.violation(&field, "must refer to the actor either by id or by name");
+                true
+            }
+            Some(id) => match Uuid::parse_str(id) {
+                Ok(id) => ids.insert(id),
+                Err(_) => {
+                    validator.violation(&format!("{}.actor_id", field), "invalid id");
+                    true
+                }
+            },
+            None => {
+                let name = role.actor_name().unwrap_or_default();
+                validator.actor_name(
+                    (
+                        &format!("{}.actor_last_name", field),
+                        &format!("{}.actor_first_name", field),
+                    ),
+                    &name.0,
+                    &name.1,
+                );
+                names.insert(name)
+            }
+        };
+        if !unique {
+            validator.violation(&field, DUPLICATE_ACTOR);
+        }
+        validate_characters(
+            validator,
+            &format!("{}.", field),
+            &role.character_names,
+            role.character_ids(),
+        );
+        if let Some(billing_order) = role.billing_order {
+            validator.positive(&format!("{}.billing_order", field), billing_order);
+        }
+        if let Some(credited_as) = &role.credited_as {
+            validator.required(&format!("{}.credited_as", field), credited_as);
+        }
+    }
+}
+
+/// Checks the characters of a role given by name or by id, the fields are named after the
+/// prefix.
+fn validate_characters(validator: &mut Validator, prefix: &str, names: &[String], ids: &[ID]) {
+    if names.is_empty() && ids.is_empty() {
+        validator.violation(
+            &format!("{}character_names", prefix),
+            "must not be empty without character_ids",
+        );
+    }
+    for (j, name) in names.iter().enumerate() {
+        validator.required(&format!("{}character_names[{}]", prefix, j), name);
+    }
+    for (j, id) in ids.iter().enumerate() {
+        if Uuid::parse_str(id).is_err() {
+            validator.violation(&format!("{}character_ids[{}]", prefix, j), "invalid id");
+        }
+    }
+}
+
+/// Checks the characters passed to the role mutations.
+fn validate_role_characters(names: &[String], ids: &[ID]) -> Result<()> {
+    let mut validator = Validator::new();
+    validate_characters(&mut validator, "", names, ids);
+    validator.finish()
+}
+
+/// Checks the fields of an actor about to be written.
+fn validate_actor(actor: &Actor) -> Result<()> {
+    let mut validator = Validator::new();
+    validator.actor_name(
+        ("last_name", "first_name"),
+        &actor.last_name,
+        &actor.first_name,
+    );
+    if let Some(birth_date) = &actor.birth_date {
+        validator.birth_date("birth_date", birth_date);
+    }
+    if let Some(imdb_id) = &actor.imdb_id {
+        validator.imdb_name_id("imdb_id", imdb_id);
+    }
+    for (i, alias) in actor.aliases.iter().enumerate() {
+        validator.required(&format!("aliases[{}]", i), alias);
+    }
+    validator.finish()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct CreateCollectionRequest {
+    #[graphql(name = "name")]
+    name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct CreateSeriesRequest {
+    #[graphql(name = "title")]
+    title: String,
+    #[graphql(name = "published_at")]
+    published_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct PutSeasonRequest {
+    #[graphql(name = "number")]
+    number: i32,
+    #[graphql(name = "title")]
+    title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct PutEpisodeRequest {
+    #[graphql(name = "season_number")]
+    season_number: i32,
+    #[graphql(name = "episode_number")]
+    episode_number: i32,
+    #[graphql(name = "title")]
+    title: String,
     #[graphql(name = "published_at")]
     published_at: DateTime<Utc>,
     #[graphql(name = "roles")]
     roles: Vec<PutMovieRequestRole>,
 }
 
-#[juniper::object]
+impl PutEpisodeRequest {
+    fn validate(&self) -> Result<()> {
+        let mut validator = Validator::new();
+        validator.episode_number("season_number", self.season_number);
+        validator.episode_number("episode_number", self.episode_number);
+        validator.required("title", &self.title);
+        validator.published_at("published_at", &self.published_at);
+        validate_roles(&mut validator, &self.roles);
+        validator.finish()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, GraphQLInputObject)]
+#[serde(rename_all = "snake_case")]
+struct ReviewMovieRequest {
+    #[graphql(name = "rating")]
+    rating: i32,
+    #[graphql(name = "text")]
+    text: String,
+}
+
+/// Writes the review of a user, replacing the rating of the stored review of the user.
+async fn store_review(
+    context: &Context,
+    movie_id: Uuid,
+    user_id: String,
+    rating: i32,
+    text: Option<String>,
+    stored: Option<Review>,
+) -> Result<Review> {
+    let review = Review {
+        movie_id,
+        user_id,
+        version: stored.as_ref().map_or(0, |review| review.version),
+        rating,
+        text,
+        reviewed_at: Utc::now(),
+    };
+    context
+        .store
+        .store_movie_review(review.clone(), stored.map(|review| review.rating))
+        .await?;
+    Ok(Review {
+        version: review.version + 1,
+        ..review
+    })
+}
+
+/// Returns the only record found by title or name, lookups that match several records
+/// fail instead of picking one of them.
+fn single<T>(records: Vec<T>, what: impl FnOnce() -> String) -> Result<Option<T>> {
+    let mut records = records.into_iter();
+    match (records.next(), records.next()) {
+        (record, None) => Ok(record),
+        _ => Err(Error::Conflict(format!(
+            "{} is ambiguous, refer to it by id",
+            what()
+        ))),
+    }
+}
+
+/// Fails with a `ConcurrentModification` error if the client expects another version than
+/// the one that was read. The writes are conditioned on the version read as well.
+fn check_version(expected: Option<i32>, actual: i32) -> Result<()> {
+    match expected {
+        Some(expected) if expected != actual => Err(Error::ConcurrentModification),
+        _ => Ok(()),
+    }
+}
+
+fn parse_id(id: &ID) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|_| Error::Validation("invalid id".to_owned()))
+}
+
+#[juniper::graphql_object(Context = Context, rename = "none")]
 impl QueryRoot {
+    /// Returns the movie with the id, `null` if there is none.
+    #[graphql(name = "movie")]
+    async fn movie(context: &Context, id: ID) -> Result<Option<MovieNode>> {
+        let meta = context.store.read_movie_metadata(&parse_id(&id)?).await?;
+        Ok(meta.map(|meta| MovieNode { meta }))
+    }
+
+    /// Checks whether a movie with the id exists, cheaper than reading the movie.
+    #[graphql(name = "movie_exists")]
+    async fn movie_exists(context: &Context, id: ID) -> Result<bool> {
+        context.store.movie_exists(&parse_id(&id)?).await
+    }
+
+    /// Returns the actor with the id, `null` if there is none.
+    #[graphql(name = "actor")]
+    async fn actor(context: &Context, id: ID) -> Result<Option<Actor>> {
+        context.actors.load(&parse_id(&id)?).await
+    }
+
+    /// Looks a movie up by title and exact release date, `null` if there is none. Fails if
+    /// several movies share both, they have to be read by id then.
     #[graphql(name = "get_movie")]
-    fn get_movie(title: String, published: i32) -> FieldResult<crate::data::Movie> {
-        let published_dt = DateTime::from_utc(
-            NaiveDate::from_ymd(published, 1, 1).and_time(NaiveTime::from_hms(0, 0, 0)),
-            Utc,
-        );
-        let mut runtime = tokio::runtime::Runtime::new()?;
-        match runtime.block_on(crate::storage::read_movie(&title, &published_dt)) {
-            Ok(m) => Ok(m),
-            Err(e) => Err(e.to_string().into()),
+    async fn get_movie(
+        context: &Context,
+        title: String,
+        published_at: DateTime<Utc>,
+    ) -> Result<Option<MovieNode>> {
+        let movies = context
+            .store
+            .find_movies_by_title(&title, Some(&published_at))
+            .await?;
+        let movie = single(movies, || {
+            format!("the movie {} of {}", title, published_at)
+        })?;
+        Ok(movie.map(|meta| MovieNode { meta }))
+    }
+
+    /// Lists the releases of a title, e.g. the original and its remakes, oldest first.
+    #[graphql(name = "movies_by_title")]
+    async fn movies_by_title(
+        context: &Context,
+        title: String,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<MovieConnection> {
+        let page = context
+            .store
+            .read_movies_by_title_page(&title, page_size(first)?, after.clone())
+            .await?;
+        let page = page.try_map(|meta| Ok(MovieNode { meta }))?;
+        Ok(MovieConnection::new(page, &after))
+    }
+
+    /// Looks a movie up by its IMDb id, `null` if there is none.
+    #[graphql(name = "movie_by_imdb_id")]
+    async fn movie_by_imdb_id(context: &Context, imdb_id: String) -> Result<Option<MovieNode>> {
+        let movies = context.store.find_movies_by_imdb_id(&imdb_id).await?;
+        let movie = single(movies, || format!("the movie with IMDb id {}", imdb_id))?;
+        Ok(movie.map(|meta| MovieNode { meta }))
+    }
+
+    /// Lists the movies released in a year ordered by release date.
+    #[graphql(name = "movies_by_year")]
+    async fn movies_by_year(
+        context: &Context,
+        year: i32,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<MovieConnection> {
+        let page = context
+            .store
+            .read_movies_by_year_page(year, page_size(first)?, after.clone())
+            .await?;
+        let page = page.try_map(|meta| Ok(MovieNode { meta }))?;
+        Ok(MovieConnection::new(page, &after))
+    }
+
+    /// Finds the movies whose title starts with the query, ignoring case, accents,
+    /// punctuation and a leading article. Ordered by the normalized title.
+    #[graphql(name = "search_movies")]
+    async fn search_movies(
+        context: &Context,
+        query: String,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<MovieConnection> {
+        let prefix = normalize_title(&query);
+        if prefix.is_empty() {
+            return Err(Error::Validation(
+                "the search query must contain a letter or digit".to_owned(),
+            ));
+        }
+        let page = context
+            .store
+            .search_movies_page(&prefix, page_size(first)?, after.clone())
+            .await?;
+        let page = page.try_map(|meta| Ok(MovieNode { meta }))?;
+        Ok(MovieConnection::new(page, &after))
+    }
+
+    /// Lists the movies released between both dates inclusively, ordered by release date.
+    #[graphql(name = "movies_between")]
+    async fn movies_between(
+        context: &Context,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<MovieConnection> {
+        if to < from {
+            return Err(Error::Validation("to must not be before from".to_owned()));
+        }
+        if to.year() - from.year() >= MAX_YEARS_BETWEEN {
+            return Err(Error::Validation(format!(
+                "the range must not span more than {} years",
+                MAX_YEARS_BETWEEN
+            )));
         }
+        let page = context
+            .store
+            .read_movies_between_page(&from, &to, page_size(first)?, after.clone())
+            .await?;
+        let page = page.try_map(|meta| Ok(MovieNode { meta }))?;
+        Ok(MovieConnection::new(page, &after))
+    }
+
+    /// Looks an actor up by name, `null` if there is none. Fails if several actors share
+    /// the name, they have to be read by id then.
+    #[graphql(name = "get_actor")]
+    async fn get_actor(
+        context: &Context,
+        last_name: String,
+        first_name: String,
+    ) -> Result<Option<Actor>> {
+        let actors = context.store.find_actors(&last_name, &first_name).await?;
+        single(actors, || {
+            format!("the actor named {} {}", first_name, last_name)
+        })
+    }
+
+    /// Returns the collection with the id, `null` if there is none.
+    #[graphql(name = "get_collection")]
+    async fn get_collection(context: &Context, id: ID) -> Result<Option<Collection>> {
+        context.store.read_collection(&parse_id(&id)?).await
+    }
+
+    /// Returns the series with the id, `null` if there is none.
+    #[graphql(name = "series")]
+    async fn series(context: &Context, id: ID) -> Result<Option<Series>> {
+        context.store.read_series(&parse_id(&id)?).await
+    }
+
+    /// Returns an episode of a series, `null` if there is none.
+    #[graphql(name = "episode")]
+    async fn episode(
+        context: &Context,
+        series_id: ID,
+        season_number: i32,
+        episode_number: i32,
+    ) -> Result<Option<Episode>> {
+        context
+            .store
+            .read_episode(&parse_id(&series_id)?, season_number, episode_number)
+            .await
+    }
+
+    /// Returns the character with the id, `null` if there is none.
+    #[graphql(name = "character")]
+    async fn character(context: &Context, id: ID) -> Result<Option<Character>> {
+        context.store.read_character(&parse_id(&id)?).await
+    }
+
+    /// Looks a character up by name, `null` if there is none. Fails if several characters
+    /// share the name, they have to be read by id then.
+    #[graphql(name = "get_character")]
+    async fn get_character(context: &Context, name: String) -> Result<Option<Character>> {
+        let characters = context.store.find_characters(&name).await?;
+        single(characters, || format!("the character named {}", name))
     }
 }
 
-#[juniper::object]
+#[juniper::graphql_object(Context = Context, rename = "none")]
 impl MutationRoot {
+    /// Writes a movie and its cast. Without an id the movie with the same title and
+    /// year is overwritten if there is one, actors are matched by name the same way.
+    /// Optional fields that are not given keep their stored values, `update_movie_metadata`
+    /// clears them. The cast and crew are replaced.
     #[graphql(name = "put_movie")]
-    fn put_movie(request: PutMovieRequest) -> FieldResult<bool> {
+    async fn put_movie(context: &Context, request: PutMovieRequest) -> Result<bool> {
+        let stored = match &request.id {
+            Some(id) => {
+                let id = parse_id(id)?;
+                context.store.read_movies_metadata(&[id]).await?.remove(&id)
+            }
+            None => {
+                let movies = context
+                    .store
+                    .find_movies(&request.title, &request.published_at)
+                    .await?;
+                single(movies, || {
+                    format!(
+                        "the movie {} of {}",
+                        request.title,
+                        request.published_at.year()
+                    )
+                })?
+            }
+        };
+        // the ratings and the optional fields that are not given are kept
+        let mut meta = match stored {
+            Some(meta) => meta,
+            None => {
+                let id = match &request.id {
+                    Some(id) => parse_id(id)?,
+                    None => Uuid::new_v4(),
+                };
+                MovieMetadata::new(id, request.title.clone(), request.published_at)
+            }
+        };
+        request.update().apply(&mut meta);
+        request.validate(&meta)?;
+        check_version(request.expected_version, meta.version)?;
+        let roles = context.resolve_roles(&request.roles).await?;
         let movie = crate::data::Movie {
-            meta: crate::data::MovieMetadata {
-                title: request.title,
-                imdb_id: request.imdb_id,
-                published_at: request.published_at,
-            },
-            roles: request
-                .roles
-                .iter()
-                .map(|r| crate::data::Role {
-                    actor: crate::data::Actor {
-                        first_name: r.actor_first_name.clone(),
-                        last_name: r.actor_last_name.clone(),
-                    },
-                    characters: r
-                        .character_names
-                        .iter()
-                        .map(|x| crate::data::Character { name: x.to_owned() })
-                        .collect(),
+            meta,
+            roles,
+            crew: request
+                .crew
+                .unwrap_or_default()
+                .into_iter()
+                .map(|credit| CrewCredit {
+                    name: credit.name,
+                    department: credit.department,
+                    job: credit.job,
+                    unknown: HashMap::new(),
                 })
                 .collect(),
         };
-        let mut runtime = tokio::runtime::Runtime::new()?;
-        match runtime.block_on(crate::storage::store_movie(movie)) {
-            Ok(_) => Ok(true),
-            Err(e) => Err(e.to_string().into()),
+        context.store.store_movie(movie).await?;
+        Ok(true)
+    }
+
+    /// Writes the movies stored before searching was introduced again, so that
+    /// `search_movies` finds them. Call it with the end cursor until there is no next page,
+    /// the movies of a page are written one after another.
+    #[graphql(name = "backfill_search")]
+    async fn backfill_search(
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<MovieConnection> {
+        let page = context
+            .store
+            .read_unsearchable_movies_page(page_size(first)?, after.clone())
+            .await?;
+        for (_, meta) in &page.items {
+            // titles without letters or digits are not searchable, writing them changes nothing
+            if search_prefix(&normalize_title(&meta.title)).is_some() {
+                context.store.store_movie_metadata(meta.clone()).await?;
+            }
+        }
+        let page = page.try_map(|meta| Ok(MovieNode { meta }))?;
+        Ok(MovieConnection::new(page, &after))
+    }
+
+    #[graphql(name = "delete_movie")]
+    async fn delete_movie(
+        context: &Context,
+        id: ID,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let meta = context.read_movie(&parse_id(&id)?).await?;
+        check_version(expected_version, meta.version)?;
+        context.store.delete_movie(meta).await?;
+        Ok(true)
+    }
+
+    /// Changes the given fields of a movie, fields that are not given are kept and the
+    /// optional fields listed in `clear` are removed.
+    #[graphql(name = "update_movie_metadata")]
+    async fn update_movie_metadata(
+        context: &Context,
+        id: ID,
+        request: UpdateMovieMetadataRequest,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let mut meta = context.read_movie(&parse_id(&id)?).await?;
+        check_version(expected_version, meta.version)?;
+        request.apply(&mut meta);
+        request.validate(&meta)?;
+        context.store.store_movie_metadata(meta).await?;
+        Ok(true)
+    }
+
+    /// Adds a role to a movie, fails with a conflict if the actor already has a role in
+    /// the movie, `update_role_characters` changes it.
+    #[graphql(name = "add_role")]
+    async fn add_role(
+        context: &Context,
+        movie_id: ID,
+        actor_id: ID,
+        character_names: Vec<String>,
+        character_ids: Option<Vec<ID>>,
+        credit: Option<RoleCreditRequest>,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let character_ids = character_ids.unwrap_or_default();
+        validate_role_characters(&character_names, &character_ids)?;
+        if let Some(credit) = &credit {
+            credit.validate()?;
+        }
+        let movie = context.read_movie(&parse_id(&movie_id)?).await?;
+        check_version(expected_version, movie.version)?;
+        let actor = context.read_actor(&parse_id(&actor_id)?).await?;
+        match context.store.read_movie_role(&movie.id, &actor.id).await {
+            Ok(_) => return Err(Error::Conflict(role_conflict(&movie.id, &actor.id))),
+            Err(Error::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+        let mut role = Credit {
+            characters: context
+                .resolve_characters(&[(&character_names, &character_ids)])
+                .await?
+                .remove(0),
+            ..Default::default()
+        };
+        if let Some(credit) = &credit {
+            credit.apply(&mut role);
+        }
+        context.store.add_movie_role(movie, &actor.id, role).await?;
+        Ok(true)
+    }
+
+    #[graphql(name = "remove_role")]
+    async fn remove_role(
+        context: &Context,
+        movie_id: ID,
+        actor_id: ID,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let (movie_id, actor_id) = (parse_id(&movie_id)?, parse_id(&actor_id)?);
+        let movie = context.read_movie(&movie_id).await?;
+        check_version(expected_version, movie.version)?;
+        context.store.read_movie_role(&movie_id, &actor_id).await?;
+        context.store.delete_movie_role(movie, &actor_id).await?;
+        Ok(true)
+    }
+
+    /// Replaces the characters of a role and changes the given fields of its credit, the
+    /// rest of the credit is kept.
+    #[graphql(name = "update_role_characters")]
+    async fn update_role_characters(
+        context: &Context,
+        movie_id: ID,
+        actor_id: ID,
+        character_names: Vec<String>,
+        character_ids: Option<Vec<ID>>,
+        credit: Option<RoleCreditRequest>,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let character_ids = character_ids.unwrap_or_default();
+        validate_role_characters(&character_names, &character_ids)?;
+        if let Some(credit) = &credit {
+            credit.validate()?;
+        }
+        let (movie_id, actor_id) = (parse_id(&movie_id)?, parse_id(&actor_id)?);
+        let movie = context.read_movie(&movie_id).await?;
+        check_version(expected_version, movie.version)?;
+        let mut role = context.store.read_movie_role(&movie_id, &actor_id).await?;
+        role.characters = context
+            .resolve_characters(&[(&character_names, &character_ids)])
+            .await?
+            .remove(0);
+        if let Some(credit) = &credit {
+            credit.apply(&mut role);
+        }
+        context
+            .store
+            .store_movie_role(movie, &actor_id, role)
+            .await?;
+        Ok(true)
+    }
+
+    /// Creates an actor that roles can refer to by id. Actors may share a name, roles and
+    /// lookups naming such an actor fail as ambiguous and have to refer to it by id.
+    #[graphql(name = "create_actor")]
+    async fn create_actor(context: &Context, request: CreateActorRequest) -> Result<Actor> {
+        let actor = Actor {
+            id: Uuid::new_v4(),
+            version: 0,
+            last_name: request.last_name,
+            first_name: request.first_name,
+            birth_date: request.birth_date,
+            imdb_id: request.imdb_id,
+            biography: request.biography,
+            aliases: request.aliases.unwrap_or_default(),
+            unknown: HashMap::new(),
+        };
+        validate_actor(&actor)?;
+        context.store.store_actor(actor.clone()).await?;
+        Ok(Actor {
+            version: actor.version + 1,
+            ..actor
+        })
+    }
+
+    /// Creates a character that roles can refer to by id. Characters may share a name like
+    /// actors, roles and lookups naming such a character have to refer to it by id.
+    #[graphql(name = "create_character")]
+    async fn create_character(context: &Context, name: String) -> Result<Character> {
+        let mut validator = Validator::new();
+        validator.required("name", &name);
+        validator.finish()?;
+        let character = Character {
+            id: Uuid::new_v4(),
+            version: 0,
+            name,
+        };
+        context.store.store_character(character.clone()).await?;
+        Ok(Character {
+            version: character.version + 1,
+            ..character
+        })
+    }
+
+    /// Changes the given fields of an actor, fields that are not given are kept and the
+    /// optional fields listed in `clear` are removed.
+    #[graphql(name = "update_actor")]
+    async fn update_actor(
+        context: &Context,
+        id: ID,
+        request: UpdateActorRequest,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let mut actor = context.read_actor(&parse_id(&id)?).await?;
+        check_version(expected_version, actor.version)?;
+        request.validate()?;
+        request.apply(&mut actor);
+        validate_actor(&actor)?;
+        context.store.store_actor(actor).await?;
+        Ok(true)
+    }
+
+    /// Deletes an actor and removes the actor from the cast of all movies.
+    #[graphql(name = "delete_actor")]
+    async fn delete_actor(
+        context: &Context,
+        id: ID,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let actor = context.read_actor(&parse_id(&id)?).await?;
+        check_version(expected_version, actor.version)?;
+        context.store.delete_actor(actor).await?;
+        Ok(true)
+    }
+
+    #[graphql(name = "create_collection")]
+    async fn create_collection(
+        context: &Context,
+        request: CreateCollectionRequest,
+    ) -> Result<Collection> {
+        let mut validator = Validator::new();
+        validator.required("name", &request.name);
+        validator.finish()?;
+        let collection = Collection {
+            id: Uuid::new_v4(),
+            version: 0,
+            name: request.name,
+            unknown: HashMap::new(),
+        };
+        context.store.store_collection(collection.clone()).await?;
+        Ok(Collection {
+            version: collection.version + 1,
+            ..collection
+        })
+    }
+
+    /// Adds a movie to a collection at the given position, by default after the last movie.
+    /// A movie that already is part of the collection is moved to the position. The movies
+    /// from that position on move back, positions are renumbered from 1.
+    #[graphql(name = "add_to_collection")]
+    async fn add_to_collection(
+        context: &Context,
+        collection_id: ID,
+        movie_id: ID,
+        position: Option<i32>,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let collection = context.read_collection(&parse_id(&collection_id)?).await?;
+        check_version(expected_version, collection.version)?;
+        let movie = context.read_movie(&parse_id(&movie_id)?).await?;
+        let position = match position {
+            Some(position) => {
+                let mut validator = Validator::new();
+                validator.positive("position", position);
+                validator.finish()?;
+                position
+            }
+            None => {
+                let movies = context.store.read_collection_movies(&collection.id).await?;
+                match movies.iter().find(|(meta, _)| meta.id == movie.id) {
+                    Some((_, position)) => *position,
+                    None => movies.last().map_or(1, |(_, position)| position + 1),
+                }
+            }
+        };
+        context
+            .store
+            .store_collection_movie(collection, &movie.id, position)
+            .await?;
+        Ok(true)
+    }
+
+    #[graphql(name = "create_series")]
+    async fn create_series(context: &Context, request: CreateSeriesRequest) -> Result<Series> {
+        let mut validator = Validator::new();
+        validator.required("title", &request.title);
+        validator.published_at("published_at", &request.published_at);
+        validator.finish()?;
+        let series = Series {
+            id: Uuid::new_v4(),
+            version: 0,
+            title: request.title,
+            published_at: request.published_at,
+            unknown: HashMap::new(),
+        };
+        context.store.store_series(series.clone()).await?;
+        Ok(Series {
+            version: series.version + 1,
+            ..series
+        })
+    }
+
+    /// Writes a season of a series, an existing season with the number is overwritten.
+    #[graphql(name = "put_season")]
+    async fn put_season(
+        context: &Context,
+        series_id: ID,
+        request: PutSeasonRequest,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        let mut validator = Validator::new();
+        validator.episode_number("number", request.number);
+        if let Some(title) = &request.title {
+            validator.required("title", title);
         }
+        validator.finish()?;
+        let series = context.read_series(&parse_id(&series_id)?).await?;
+        check_version(expected_version, series.version)?;
+        let season = Season {
+            series_id: series.id,
+            number: request.number,
+            title: request.title,
+        };
+        context.store.store_season(series, season).await?;
+        Ok(true)
+    }
+
+    /// Writes an episode and its cast, the season has to exist. An existing episode with
+    /// the numbers is overwritten, actors are matched by name like for movies. Episodes do
+    /// not change the version of the series.
+    #[graphql(name = "put_episode")]
+    async fn put_episode(
+        context: &Context,
+        series_id: ID,
+        request: PutEpisodeRequest,
+        expected_version: Option<i32>,
+    ) -> Result<bool> {
+        request.validate()?;
+        let series = context.read_series(&parse_id(&series_id)?).await?;
+        check_version(expected_version, series.version)?;
+        let roles = context.resolve_roles(&request.roles).await?;
+        let episode = Episode {
+            series_id: series.id,
+            season_number: request.season_number,
+            episode_number: request.episode_number,
+            title: request.title,
+            published_at: request.published_at,
+        };
+        context.store.store_episode(series, episode, roles).await?;
+        Ok(true)
+    }
+
+    /// Rates a movie on behalf of a user, the text of an earlier review of the user is kept.
+    /// The expected version is the one of the review of the user, 0 if there is none.
+    #[graphql(name = "rate_movie")]
+    async fn rate_movie(
+        context: &Context,
+        movie_id: ID,
+        user_id: String,
+        rating: i32,
+        expected_version: Option<i32>,
+    ) -> Result<Review> {
+        let mut validator = Validator::new();
+        validator.user_id("user_id", &user_id);
+        validator.rating("rating", rating);
+        validator.finish()?;
+        let movie = context.read_movie(&parse_id(&movie_id)?).await?;
+        let stored = context.store.read_movie_review(&movie.id, &user_id).await?;
+        check_version(expected_version, stored.as_ref().map_or(0, |r| r.version))?;
+        let text = stored.as_ref().and_then(|review| review.text.clone());
+        store_review(context, movie.id, user_id, rating, text, stored).await
+    }
+
+    /// Reviews a movie on behalf of a user, an earlier review of the user is replaced.
+    #[graphql(name = "review_movie")]
+    async fn review_movie(
+        context: &Context,
+        movie_id: ID,
+        user_id: String,
+        request: ReviewMovieRequest,
+        expected_version: Option<i32>,
+    ) -> Result<Review> {
+        let mut validator = Validator::new();
+        validator.user_id("user_id", &user_id);
+        validator.rating("rating", request.rating);
+        validator.review_text("text", &request.text);
+        validator.finish()?;
+        let movie = context.read_movie(&parse_id(&movie_id)?).await?;
+        let stored = context.store.read_movie_review(&movie.id, &user_id).await?;
+        check_version(expected_version, stored.as_ref().map_or(0, |r| r.version))?;
+        store_review(
+            context,
+            movie.id,
+            user_id,
+            request.rating,
+            Some(request.text),
+            stored,
+        )
+        .await
     }
 }
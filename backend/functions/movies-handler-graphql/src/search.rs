@@ -0,0 +1,59 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Leading articles that are not part of the searchable title, "The Irishman" is found
+/// by typing "irish".
+const ARTICLES: &[&str] = &["the", "a", "an"];
+
+/// Folds a title or a search query into the form titles are matched in: compatibility
+/// decomposed without accents, lower case, words separated by single spaces and without
+/// a leading article. A prefix of a title folds into a prefix of the folded title as long
+/// as it does not end within the article.
+pub fn normalize_title(title: &str) -> String {
+    let folded = title
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>();
+    let words = folded.split_whitespace().collect::<Vec<&str>>();
+    match words.as_slice() {
+        [article, rest @ ..] if !rest.is_empty() && ARTICLES.contains(article) => rest.join(" "),
+        _ => words.join(" "),
+    }
+}
+
+/// Partition of the search index a normalized title is stored in, its first character.
+pub fn search_prefix(normalized: &str) -> Option<String> {
+    normalized.chars().next().map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::normalize_title;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!("irishman", normalize_title("The Irishman"));
+        assert_eq!("amelie", normalize_title("Amélie"));
+        assert_eq!(
+            "leon the professional",
+            normalize_title("Léon: The Professional")
+        );
+        assert_eq!(
+            "mission impossible",
+            normalize_title("  MISSION:   Impossible ")
+        );
+        assert_eq!("the", normalize_title("The"));
+        assert_eq!("", normalize_title("?!"));
+    }
+
+    proptest! {
+        #[test]
+        fn test_normalized_title_is_folded(title in any::<String>()) {
+            let normalized = normalize_title(&title);
+            prop_assert!(normalized.chars().all(|c| c == ' ' || c.is_alphanumeric()));
+            prop_assert!(normalized.split(' ').all(|word| !word.is_empty()) || normalized.is_empty());
+        }
+    }
+}
@@ -1,181 +1,3171 @@
-use crate::data::{Actor, Character, Movie, MovieMetadata, Role};
+use crate::config::{Config, UnknownAttributes};
+use crate::data::{
+    Actor, Character, Collection, Credit, CrewCredit, Episode, Movie, MovieMetadata, Production,
+    Review, Role, Season, Series,
+};
 use crate::dynamo::*;
+use crate::error::{Error, Result};
+use crate::pagination::{decode_cursor, Key, KeyCondition, Page};
+use crate::search::search_prefix;
+use async_trait::async_trait;
 use chrono::{DateTime, Datelike, Utc};
-use rusoto_core::Region;
+use rusoto_core::RusotoError;
 use rusoto_dynamodb::{
-    AttributeValue, DynamoDb, DynamoDbClient, GetItemInput, PutItemInput, QueryInput,
+    AttributeValue, BatchGetItemError, BatchGetItemInput, BatchWriteItemError, BatchWriteItemInput,
+    ConditionCheck, Delete, DeleteRequest, DynamoDb, DynamoDbClient, GetItemError, GetItemInput,
+    KeysAndAttributes, Put, PutRequest, QueryError, QueryInput, ScanError, ScanInput,
+    TransactWriteItem, TransactWriteItemsError, TransactWriteItemsInput, Update, UpdateItemError,
+    UpdateItemInput, WriteRequest,
 };
-use std::str::FromStr;
+use std::{collections::HashMap, convert::TryFrom, time::Duration};
+use tokio::time::delay_for;
+use uuid::Uuid;
+
+type Item = HashMap<String, AttributeValue>;
+
+/// Index with the keys of the table swapped, mapping actors to the movies and episodes they
+/// appear in and movies to the collections they belong to.
+const INVERTED_INDEX: &str = "GSI-1";
+/// Index on the `title` and `published_at` attributes of movie meta rows, ordered by release.
+const TITLE_INDEX: &str = "GSI-2";
+/// Attributes of the key of an item in the title index, cursors into the index encode them.
+pub const TITLE_INDEX_KEY: &[&str] = &["pk", "sk", "title", "published_at"];
+/// Index on the `imdb_id` attribute of movie meta rows.
+const IMDB_ID_INDEX: &str = "GSI-3";
+/// Index on the `published_year` and `published_at` attributes of movie meta rows,
+/// listing the movies of a year ordered by release.
+const YEAR_INDEX: &str = "GSI-4";
+/// Attributes of the key of an item in the year index, cursors into the index encode them.
+pub const YEAR_INDEX_KEY: &[&str] = &["pk", "sk", "published_year", "published_at"];
+/// Index on the `lookup` attribute of meta rows, finding movies and actors by title or name.
+const LOOKUP_INDEX: &str = "GSI-5";
+/// Index on the `search_prefix` and `search_title` attributes of movie meta rows, finding
+/// movies by a prefix of their normalized title.
+const SEARCH_INDEX: &str = "GSI-6";
+/// Attributes of the key of an item in the search index, cursors into the index encode them.
+pub const SEARCH_INDEX_KEY: &[&str] = &["pk", "sk", "search_prefix", "search_title"];
+/// Attributes of the key of an item in the table and in the inverted index.
+pub const TABLE_KEY: &[&str] = &["pk", "sk"];
+
+/// Maximum number of items DynamoDB accepts in a single `TransactWriteItems` call.
+const TRANSACT_WRITE_ITEMS_LIMIT: usize = 25;
+/// Maximum number of items DynamoDB accepts in a single `BatchWriteItem` call.
+const BATCH_WRITE_ITEMS_LIMIT: usize = 25;
+/// Maximum number of keys DynamoDB accepts in a single `BatchGetItem` call.
+const BATCH_GET_ITEMS_LIMIT: usize = 100;
+const BATCH_MAX_ATTEMPTS: u32 = 5;
+const BATCH_BACKOFF_MS: u64 = 25;
+
+#[async_trait]
+pub trait MovieStore: Send + Sync {
+    /// Reads a movie, `None` if there is no movie with the id.
+    async fn read_movie_metadata(&self, id: &Uuid) -> Result<Option<MovieMetadata>>;
+
+    /// Checks whether a movie exists without reading its attributes.
+    async fn movie_exists(&self, id: &Uuid) -> Result<bool>;
+
+    /// Finds the movies with the given title released in the year of `published_at`.
+    async fn find_movies(
+        &self,
+        title: &str,
+        published_at: &DateTime<Utc>,
+    ) -> Result<Vec<MovieMetadata>>;
+
+    /// Finds all releases of the title ordered by release date, or only those released
+    /// exactly at `published_at`.
+    async fn find_movies_by_title(
+        &self,
+        title: &str,
+        published_at: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<MovieMetadata>>;
+
+    /// Reads the releases of the title ordered by release date.
+    async fn read_movies_by_title_page(
+        &self,
+        title: &str,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>>;
+
+    /// Finds the movies with the given IMDb id.
+    async fn find_movies_by_imdb_id(&self, imdb_id: &str) -> Result<Vec<MovieMetadata>>;
+
+    /// Reads the movies released in a year ordered by release date.
+    async fn read_movies_by_year_page(
+        &self,
+        year: i32,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>>;
+
+    /// Reads the movies released between both dates inclusively, ordered by release date.
+    /// The years of the range are queried one after another until the page is full.
+    async fn read_movies_between_page(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>>;
+
+    /// Reads the movies whose normalized title starts with the normalized `prefix`,
+    /// ordered by normalized title.
+    async fn search_movies_page(
+        &self,
+        prefix: &str,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>>;
+
+    /// Reads the movies stored without the attributes of the search index, like the movies
+    /// written before searching was introduced, in no particular order. Movies still keyed
+    /// by title and year like the first version wrote them are skipped.
+    async fn read_unsearchable_movies_page(
+        &self,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>>;
+
+    /// Reads an actor, `None` if there is no actor with the id.
+    async fn read_actor_metadata(&self, id: &Uuid) -> Result<Option<Actor>>;
+
+    /// Finds the actors with the given name.
+    async fn find_actors(&self, last_name: &str, first_name: &str) -> Result<Vec<Actor>>;
+
+    /// Reads the movies with the given ids, movies that do not exist are missing in the result.
+    async fn read_movies_metadata(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, MovieMetadata>>;
+
+    /// Reads the actors with the given ids, actors that do not exist are missing in the result.
+    async fn read_actors_metadata(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Actor>>;
+
+    /// Reads the roles of a movie as pairs of actor id and credit.
+    async fn read_movie_roles(&self, id: &Uuid) -> Result<Vec<(Uuid, Credit)>>;
+
+    async fn read_movie_roles_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(Uuid, Credit)>>;
+
+    /// Reads the movies an actor appears in through the inverted index, together with
+    /// the characters played.
+    async fn read_actor_movies(&self, id: &Uuid) -> Result<Vec<(MovieMetadata, Vec<Character>)>>;
+
+    async fn read_actor_movies_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(MovieMetadata, Vec<Character>)>>;
+
+    /// Reads the credit of an actor in a movie.
+    async fn read_movie_role(&self, movie_id: &Uuid, actor_id: &Uuid) -> Result<Credit>;
+
+    /// Reads the crew of a movie ordered by department.
+    async fn read_movie_crew(&self, id: &Uuid) -> Result<Vec<CrewCredit>>;
+
+    /// Reads a character, `None` if there is no character with the id.
+    async fn read_character(&self, id: &Uuid) -> Result<Option<Character>>;
+
+    /// Finds the characters with the given name.
+    async fn find_characters(&self, name: &str) -> Result<Vec<Character>>;
+
+    /// Reads the characters with the given ids, characters that do not exist are missing in
+    /// the result.
+    async fn read_characters(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Character>>;
+
+    /// Writes a character that is not part of a role yet.
+    async fn store_character(&self, character: Character) -> Result<()>;
+
+    /// Reads every portrayal of a character as pairs of the movie or episode and actor id.
+    async fn read_character_portrayals(&self, id: &Uuid) -> Result<Vec<(Production, Uuid)>>;
+
+    /// Reads the portrayals of a character, the episodes ordered by series, season and
+    /// episode number first and then the movies ordered by id.
+    async fn read_character_portrayals_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(Production, Uuid)>>;
+
+    /// Writes a movie with its cast and crew, roles and crew credits that are not part of
+    /// the movie anymore are deleted together with their character rows. Reviews are kept.
+    /// Like all writes this fails with `ConcurrentModification` if the stored version of
+    /// the movie is not the version of the given movie anymore, in that case nothing is
+    /// written. Casts too large for a single transaction are completed in batches after the
+    /// meta row was written, if a batch fails the movie is left partially written.
+    async fn store_movie(&self, movie: Movie) -> Result<()>;
+
+    async fn store_movie_metadata(&self, meta: MovieMetadata) -> Result<()>;
 
-async fn get_region() -> Result<Region> {
-    match Region::from_str(std::env::var("AWS_REGION")?.as_ref()) {
-        Ok(r) => Ok(r),
-        Err(e) => Err(e.to_string().into()),
+    /// Deletes a movie with all its roles, characters, crew credits and reviews and removes
+    /// it from its collections, whose versions are incremented. The actors are kept.
+    async fn delete_movie(&self, meta: MovieMetadata) -> Result<()>;
+
+    /// Writes a role of a movie, the role is part of the movie so its version is incremented.
+    /// Characters the stored role lists but the given credit does not are dropped.
+    async fn store_movie_role(
+        &self,
+        meta: MovieMetadata,
+        actor_id: &Uuid,
+        credit: Credit,
+    ) -> Result<()>;
+
+    /// Adds a role to a movie like `store_movie_role`, but fails with `Conflict` if the
+    /// actor already has a role in the movie.
+    async fn add_movie_role(
+        &self,
+        meta: MovieMetadata,
+        actor_id: &Uuid,
+        credit: Credit,
+    ) -> Result<()>;
+
+    async fn delete_movie_role(&self, meta: MovieMetadata, actor_id: &Uuid) -> Result<()>;
+
+    async fn store_actor(&self, actor: Actor) -> Result<()>;
+
+    /// Deletes an actor together with all roles of the actor in movies and episodes and
+    /// their characters.
+    async fn delete_actor(&self, actor: Actor) -> Result<()>;
+
+    /// Reads a collection, `None` if there is no collection with the id.
+    async fn read_collection(&self, id: &Uuid) -> Result<Option<Collection>>;
+
+    /// Reads the movies of a collection ordered by their position, together with the position.
+    async fn read_collection_movies(&self, id: &Uuid) -> Result<Vec<(MovieMetadata, i32)>>;
+
+    /// Reads the collections a movie belongs to through the inverted index.
+    async fn read_movie_collections(&self, id: &Uuid) -> Result<Vec<Collection>>;
+
+    async fn store_collection(&self, collection: Collection) -> Result<()>;
+
+    /// Adds a movie to a collection or moves it to another position, the movie is part of
+    /// the collection so its version is incremented. The movies from that position on move
+    /// back and the positions are renumbered from 1 in the same write, a position after the
+    /// last movie appends the movie.
+    async fn store_collection_movie(
+        &self,
+        collection: Collection,
+        movie_id: &Uuid,
+        position: i32,
+    ) -> Result<()>;
+
+    /// Reads a series, `None` if there is no series with the id.
+    async fn read_series(&self, id: &Uuid) -> Result<Option<Series>>;
+
+    /// Reads the seasons of a series ordered by number.
+    async fn read_series_seasons(&self, id: &Uuid) -> Result<Vec<Season>>;
+
+    /// Reads the episodes of a series ordered by season and episode number, only those of
+    /// a season if given.
+    async fn read_series_episodes(&self, id: &Uuid, season: Option<i32>) -> Result<Vec<Episode>>;
+
+    async fn read_series_episodes_page(
+        &self,
+        id: &Uuid,
+        season: Option<i32>,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<Episode>>;
+
+    /// Reads an episode, `None` if the season has no episode with the number.
+    async fn read_episode(
+        &self,
+        series_id: &Uuid,
+        season_number: i32,
+        episode_number: i32,
+    ) -> Result<Option<Episode>>;
+
+    /// Reads the roles of an episode as pairs of actor id and credit.
+    async fn read_episode_roles(
+        &self,
+        series_id: &Uuid,
+        season_number: i32,
+        episode_number: i32,
+    ) -> Result<Vec<(Uuid, Credit)>>;
+
+    /// Reads the episodes an actor appears in through the inverted index, together with
+    /// the characters played.
+    async fn read_actor_episodes(&self, id: &Uuid) -> Result<Vec<(Episode, Vec<Character>)>>;
+
+    async fn store_series(&self, series: Series) -> Result<()>;
+
+    /// Writes a season, the season is part of the series so its version is incremented.
+    async fn store_season(&self, series: Series, season: Season) -> Result<()>;
+
+    /// Writes an episode with its cast, the version of the series is not changed. Fails with
+    /// `NotFound` unless the season of the episode is stored when the episode is written.
+    /// Roles that are not part of the episode anymore are deleted together with their
+    /// character rows.
+    async fn store_episode(&self, series: Series, episode: Episode, roles: Vec<Role>)
+        -> Result<()>;
+
+    /// Reads the reviews of a movie ordered by user.
+    async fn read_movie_reviews(&self, id: &Uuid) -> Result<Vec<Review>>;
+
+    async fn read_movie_reviews_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<Review>>;
+
+    /// Reads the review of a movie by a user, `None` if the user did not review the movie.
+    async fn read_movie_review(&self, movie_id: &Uuid, user_id: &str) -> Result<Option<Review>>;
+
+    /// Writes a review and counts its rating in the aggregates of the movie in a single
+    /// transaction, the previous rating of the user is replaced if given. The aggregates are
+    /// updated atomically without reading them and without changing the version of the
+    /// movie, writes of the movie set its other attributes only.
+    /// Fails with `ConcurrentModification` if the review changed and with `NotFound` if the
+    /// movie is gone.
+    async fn store_movie_review(&self, review: Review, previous_rating: Option<i32>) -> Result<()>;
+}
+
+pub struct DynamoMovieStore {
+    client: DynamoDbClient,
+    table_name: String,
+    unknown_attributes: UnknownAttributes,
+}
+
+impl DynamoMovieStore {
+    pub fn new(config: &Config) -> Self {
+        Self::with_client(DynamoDbClient::new(config.region.clone()), config)
+    }
+
+    pub fn with_client(client: DynamoDbClient, config: &Config) -> Self {
+        DynamoMovieStore {
+            client,
+            table_name: config.table_name.clone(),
+            unknown_attributes: config.unknown_attributes,
+        }
     }
 }
 
-async fn get_table_name() -> Result<String> {
-    match std::env::var("TABLE_NAME") {
-        Ok(r) => Ok(r),
-        Err(e) => Err(e.to_string().into()),
+#[async_trait]
+impl MovieStore for DynamoMovieStore {
+    async fn read_movie_metadata(&self, id: &Uuid) -> Result<Option<MovieMetadata>> {
+        self.get_item(
+            DynamoTableItem::movie_pk(id),
+            DynamoTableRowKind::MovieMeta.get_prefixes().1,
+            None,
+        )
+        .await?
+        .map(|item| self.parse(item)?.into_movie_metadata())
+        .transpose()
+    }
+
+    async fn movie_exists(&self, id: &Uuid) -> Result<bool> {
+        Ok(self
+            .get_item(
+                DynamoTableItem::movie_pk(id),
+                DynamoTableRowKind::MovieMeta.get_prefixes().1,
+                Some("pk"),
+            )
+            .await?
+            .is_some())
+    }
+
+    async fn find_movies(
+        &self,
+        title: &str,
+        published_at: &DateTime<Utc>,
+    ) -> Result<Vec<MovieMetadata>> {
+        self.query_items(
+            self.lookup_query(DynamoTableItem::movie_lookup(title, published_at)),
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|item| self.parse(item)?.into_movie_metadata())
+        .collect()
+    }
+
+    async fn find_movies_by_title(
+        &self,
+        title: &str,
+        published_at: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<MovieMetadata>> {
+        self.query_items(self.title_query(title, published_at), None)
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_movie_metadata())
+            .collect()
+    }
+
+    async fn read_movies_by_title_page(
+        &self,
+        title: &str,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let mut input = self.title_query(title, None);
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, TITLE_INDEX_KEY, &title_cursor(title)))
+            .transpose()?;
+        let items = self.query_items(input, Some(first + 1)).await?;
+        Page::from_items(items, first, TITLE_INDEX_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn find_movies_by_imdb_id(&self, imdb_id: &str) -> Result<Vec<MovieMetadata>> {
+        self.query_items(self.imdb_id_query(imdb_id), None)
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_movie_metadata())
+            .collect()
+    }
+
+    async fn read_movies_by_year_page(
+        &self,
+        year: i32,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let mut input = self.year_query(year, None);
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, YEAR_INDEX_KEY, &year_cursor(year)))
+            .transpose()?;
+        let items = self.query_items(input, Some(first + 1)).await?;
+        Page::from_items(items, first, YEAR_INDEX_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn read_movies_between_page(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let mut start = after
+            .map(|c| decode_between_cursor(&c, from, to))
+            .transpose()?;
+        let mut year = start.as_ref().map_or(from.year(), |(year, _)| *year);
+        let mut items = vec![];
+        while year <= to.year() && items.len() <= first {
+            let mut input = self.year_query(year, Some((from, to)));
+            input.exclusive_start_key = start.take().map(|(_, key)| key);
+            let limit = first + 1 - items.len();
+            items.extend(self.query_items(input, Some(limit)).await?);
+            year += 1;
+        }
+        Page::from_items(items, first, YEAR_INDEX_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn search_movies_page(
+        &self,
+        prefix: &str,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let mut input = self.search_query(prefix)?;
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, SEARCH_INDEX_KEY, &search_cursor(prefix)))
+            .transpose()?;
+        let items = self.query_items(input, Some(first + 1)).await?;
+        Page::from_items(items, first, SEARCH_INDEX_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn read_unsearchable_movies_page(
+        &self,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<MovieMetadata>> {
+        let mut input = self.unsearchable_movies_scan();
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &movies_cursor()))
+            .transpose()?;
+        let items = self.scan_items(input, first + 1).await?;
+        Page::from_items(items, first, TABLE_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_metadata())
+    }
+
+    async fn read_actor_metadata(&self, id: &Uuid) -> Result<Option<Actor>> {
+        self.get_item(
+            DynamoTableItem::actor_pk(id),
+            DynamoTableRowKind::ActorMeta.get_prefixes().1,
+            None,
+        )
+        .await?
+        .map(|item| self.parse(item)?.into_actor())
+        .transpose()
+    }
+
+    async fn find_actors(&self, last_name: &str, first_name: &str) -> Result<Vec<Actor>> {
+        self.query_items(
+            self.lookup_query(DynamoTableItem::actor_lookup(last_name, first_name)),
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|item| self.parse(item)?.into_actor())
+        .collect()
+    }
+
+    async fn read_movies_metadata(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, MovieMetadata>> {
+        let keys = ids
+            .iter()
+            .map(|id| {
+                key(
+                    DynamoTableItem::movie_pk(id),
+                    DynamoTableRowKind::MovieMeta.get_prefixes().1,
+                )
+            })
+            .collect::<Vec<Item>>();
+        let mut result = HashMap::new();
+        for item in self.batch_get_items(keys).await? {
+            let meta = self.parse(item)?.into_movie_metadata()?;
+            result.insert(meta.id, meta);
+        }
+        Ok(result)
+    }
+
+    async fn read_actors_metadata(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Actor>> {
+        let keys = ids
+            .iter()
+            .map(|id| {
+                key(
+                    DynamoTableItem::actor_pk(id),
+                    DynamoTableRowKind::ActorMeta.get_prefixes().1,
+                )
+            })
+            .collect::<Vec<Item>>();
+        let mut result = HashMap::new();
+        for item in self.batch_get_items(keys).await? {
+            let actor = self.parse(item)?.into_actor()?;
+            result.insert(actor.id, actor);
+        }
+        Ok(result)
+    }
+
+    async fn read_movie_roles(&self, id: &Uuid) -> Result<Vec<(Uuid, Credit)>> {
+        self.query_items(
+            self.movie_rows_query(id, DynamoTableRowKind::MovieActor),
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|item| self.parse(item)?.into_movie_actor())
+        .collect()
+    }
+
+    async fn read_movie_roles_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(Uuid, Credit)>> {
+        let mut input = self.movie_rows_query(id, DynamoTableRowKind::MovieActor);
+        let conditions = partition_cursor(
+            DynamoTableItem::movie_pk(id),
+            DynamoTableRowKind::MovieActor.get_prefixes().1,
+        );
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_items(input, Some(first + 1)).await?;
+        Page::from_items(items, first, TABLE_KEY)?
+            .try_map(|item| self.parse(item)?.into_movie_actor())
+    }
+
+    async fn read_actor_movies(&self, id: &Uuid) -> Result<Vec<(MovieMetadata, Vec<Character>)>> {
+        let roles = self
+            .query_items(
+                self.actor_roles_query(id, DynamoTableRowKind::MovieActor),
+                None,
+            )
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_actor_movie())
+            .collect::<Result<Vec<_>>>()?;
+        self.join_movies_metadata(roles).await
+    }
+
+    async fn read_actor_movies_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(MovieMetadata, Vec<Character>)>> {
+        let mut input = self.actor_roles_query(id, DynamoTableRowKind::MovieActor);
+        let conditions = inverted_cursor(
+            DynamoTableItem::movie_actor_sk(id),
+            DynamoTableRowKind::MovieActor.get_prefixes().0,
+        );
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_items(input, Some(first + 1)).await?;
+        let page = Page::from_items(items, first, TABLE_KEY)?
+            .try_map(|item| self.parse(item)?.into_actor_movie())?;
+        let (cursors, roles): (Vec<String>, Vec<_>) = page.items.into_iter().unzip();
+        let movies = self.join_movies_metadata(roles).await?;
+        Ok(Page {
+            items: cursors.into_iter().zip(movies).collect(),
+            has_next_page: page.has_next_page,
+        })
+    }
+
+    async fn read_movie_role(&self, movie_id: &Uuid, actor_id: &Uuid) -> Result<Credit> {
+        let item = self
+            .get_item(
+                DynamoTableItem::movie_pk(movie_id),
+                DynamoTableItem::movie_actor_sk(actor_id),
+                None,
+            )
+            .await?
+            .ok_or_else(|| {
+                Error::NotFound(format!("role of actor {} in movie {}", actor_id, movie_id))
+            })?;
+        let (_, credit) = self.parse(item)?.into_movie_actor()?;
+        Ok(credit)
+    }
+
+    async fn read_movie_crew(&self, id: &Uuid) -> Result<Vec<CrewCredit>> {
+        self.query_items(
+            self.movie_rows_query(id, DynamoTableRowKind::MovieCrew),
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|item| self.parse(item)?.into_crew_credit())
+        .collect()
+    }
+
+    async fn read_character(&self, id: &Uuid) -> Result<Option<Character>> {
+        self.get_item(
+            DynamoTableItem::character_pk(id),
+            DynamoTableRowKind::CharacterMeta.get_prefixes().1,
+            None,
+        )
+        .await?
+        .map(|item| self.parse(item)?.into_character())
+        .transpose()
+    }
+
+    async fn find_characters(&self, name: &str) -> Result<Vec<Character>> {
+        self.query_items(
+            self.lookup_query(DynamoTableItem::character_lookup(name)),
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|item| self.parse(item)?.into_character())
+        .collect()
+    }
+
+    async fn read_characters(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Character>> {
+        let keys = ids
+            .iter()
+            .map(|id| {
+                key(
+                    DynamoTableItem::character_pk(id),
+                    DynamoTableRowKind::CharacterMeta.get_prefixes().1,
+                )
+            })
+            .collect::<Vec<Item>>();
+        let mut result = HashMap::new();
+        for item in self.batch_get_items(keys).await? {
+            let character = self.parse(item)?.into_character()?;
+            result.insert(character.id, character);
+        }
+        Ok(result)
+    }
+
+    async fn store_character(&self, character: Character) -> Result<()> {
+        let write = Write::put(&DynamoTableItem::new_character(&character))?;
+        self.write_items(vec![write]).await
+    }
+
+    async fn read_character_portrayals(&self, id: &Uuid) -> Result<Vec<(Production, Uuid)>> {
+        let portrayals = self
+            .query_items(self.character_portrayals_query(id), None)
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_character_portrayal())
+            .collect::<Result<Vec<_>>>()?;
+        self.join_productions(portrayals).await
+    }
+
+    async fn read_character_portrayals_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<(Production, Uuid)>> {
+        let mut input = self.character_portrayals_query(id);
+        let conditions = partition_cursor(
+            DynamoTableItem::character_pk(id),
+            DynamoTableItem::character_portrayals_sk(),
+        );
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_items(input, Some(first + 1)).await?;
+        let page = Page::from_items(items, first, TABLE_KEY)?
+            .try_map(|item| self.parse(item)?.into_character_portrayal())?;
+        let (cursors, portrayals): (Vec<String>, Vec<_>) = page.items.into_iter().unzip();
+        let productions = self.join_productions(portrayals).await?;
+        Ok(Page {
+            items: cursors.into_iter().zip(productions).collect(),
+            has_next_page: page.has_next_page,
+        })
+    }
+
+    async fn store_movie(&self, movie: Movie) -> Result<()> {
+        let mut items = DynamoTableItem::new_movie(&movie);
+        self.keep_unknown_attributes(&mut items).await?;
+        let stored = self.read_movie_credit_keys(&movie.meta.id).await?;
+        let stale = DynamoTableItem::stale_keys(stored, &items)
+            .into_iter()
+            .map(|(pk, sk)| Write::delete(key(pk, sk), None));
+        // only the meta row is conditioned, actors and characters created with the movie
+        // have new ids and are written first so that no role refers to a missing one
+        let meta = (
+            DynamoTableItem::movie_pk(&movie.meta.id),
+            DynamoTableRowKind::MovieMeta.get_prefixes().1,
+        );
+        let actors = DynamoTableRowKind::ActorMeta.get_prefixes().0;
+        let characters = DynamoTableRowKind::CharacterMeta.get_prefixes().0;
+        let mut writes = items
+            .iter()
+            .map(|item| {
+                if (item.pk.clone(), item.sk.clone()) == meta {
+                    Write::put(item)
+                } else {
+                    Write::put(item).map(Write::unconditioned)
+                }
+            })
+            .collect::<Result<Vec<Write>>>()?;
+        writes.sort_by_key(|w| !(w.key_starts_with(&actors) || w.key_starts_with(&characters)));
+        writes.extend(stale);
+        self.write_items(writes).await
+    }
+
+    async fn store_movie_metadata(&self, meta: MovieMetadata) -> Result<()> {
+        let write = Write::put(&DynamoTableItem::new_movie_metadata(&meta))?;
+        self.write_items(vec![write]).await
+    }
+
+    async fn delete_movie(&self, meta: MovieMetadata) -> Result<()> {
+        let meta_key = (
+            DynamoTableItem::movie_pk(&meta.id),
+            DynamoTableRowKind::MovieMeta.get_prefixes().1,
+        );
+        let memberships = self
+            .query_items(self.movie_collections_query(&meta.id), None)
+            .await?;
+        let mut writes = self
+            .read_membership_collections(&memberships)
+            .await?
+            .iter()
+            .map(|collection| Write::put(&DynamoTableItem::new_collection(collection)))
+            .collect::<Result<Vec<Write>>>()?;
+        writes.extend(
+            self.read_movie_keys(&meta.id)
+                .await?
+                .into_iter()
+                .chain(DynamoTableItem::membership_keys(&meta.id, &memberships)?)
+                .filter(|k| *k != meta_key)
+                .map(|(pk, sk)| Write::delete(key(pk, sk), None)),
+        );
+        writes.push(Write::delete(
+            key(meta_key.0, meta_key.1),
+            Some(meta.version),
+        ));
+        self.write_items(writes).await
+    }
+
+    async fn store_movie_role(
+        &self,
+        meta: MovieMetadata,
+        actor_id: &Uuid,
+        credit: Credit,
+    ) -> Result<()> {
+        let mut characters = DynamoTableItem::new_character_movies(&meta, actor_id, &credit);
+        self.keep_unknown_attributes(&mut characters).await?;
+        characters.extend(DynamoTableItem::new_characters(Some(&credit)));
+        let role = self
+            .get_item(
+                DynamoTableItem::movie_pk(&meta.id),
+                DynamoTableItem::movie_actor_sk(actor_id),
+                None,
+            )
+            .await?;
+        let stale =
+            DynamoTableItem::stale_keys(DynamoTableItem::role_character_keys(role)?, &characters)
+                .into_iter()
+                .map(|(pk, sk)| Write::delete(key(pk, sk), None));
+        let mut writes = vec![
+            Write::put(&DynamoTableItem::new_movie_metadata(&meta))?,
+            Write::put(&DynamoTableItem::new_movie_actor(&meta, actor_id, &credit))?,
+        ];
+        for item in &characters {
+            writes.push(Write::put(item)?);
+        }
+        writes.extend(stale);
+        self.write_items(writes).await
+    }
+
+    async fn add_movie_role(
+        &self,
+        meta: MovieMetadata,
+        actor_id: &Uuid,
+        credit: Credit,
+    ) -> Result<()> {
+        let mut writes = vec![
+            Write::put(&DynamoTableItem::new_movie_metadata(&meta))?,
+            Write::create(
+                &DynamoTableItem::new_movie_actor(&meta, actor_id, &credit),
+                role_conflict(&meta.id, actor_id),
+            )?,
+        ];
+        let mut characters = DynamoTableItem::new_character_movies(&meta, actor_id, &credit);
+        characters.extend(DynamoTableItem::new_characters(Some(&credit)));
+        for item in &characters {
+            writes.push(Write::put(item)?);
+        }
+        self.write_items(writes).await
+    }
+
+    async fn delete_movie_role(&self, meta: MovieMetadata, actor_id: &Uuid) -> Result<()> {
+        let role = self
+            .get_item(
+                DynamoTableItem::movie_pk(&meta.id),
+                DynamoTableItem::movie_actor_sk(actor_id),
+                None,
+            )
+            .await?;
+        let mut writes = vec![
+            Write::put(&DynamoTableItem::new_movie_metadata(&meta))?,
+            Write::delete(
+                key(
+                    DynamoTableItem::movie_pk(&meta.id),
+                    DynamoTableItem::movie_actor_sk(actor_id),
+                ),
+                None,
+            ),
+        ];
+        writes.extend(
+            DynamoTableItem::role_character_keys(role)?
+                .into_iter()
+                .map(|(pk, sk)| Write::delete(key(pk, sk), None)),
+        );
+        self.write_items(writes).await
+    }
+
+    async fn store_actor(&self, actor: Actor) -> Result<()> {
+        let write = Write::put(&DynamoTableItem::new_actor(&actor))?;
+        self.write_items(vec![write]).await
+    }
+
+    async fn delete_actor(&self, actor: Actor) -> Result<()> {
+        let mut roles = self
+            .query_items(
+                self.actor_roles_query(&actor.id, DynamoTableRowKind::MovieActor),
+                None,
+            )
+            .await?;
+        roles.extend(
+            self.query_items(
+                self.actor_roles_query(&actor.id, DynamoTableRowKind::EpisodeActor),
+                None,
+            )
+            .await?,
+        );
+        let mut writes = DynamoTableItem::row_keys(roles)?
+            .into_iter()
+            .map(|(pk, sk)| Write::delete(key(pk, sk), None))
+            .collect::<Vec<Write>>();
+        writes.push(Write::delete(
+            key(
+                DynamoTableItem::actor_pk(&actor.id),
+                DynamoTableRowKind::ActorMeta.get_prefixes().1,
+            ),
+            Some(actor.version),
+        ));
+        self.write_items(writes).await
+    }
+
+    async fn read_collection(&self, id: &Uuid) -> Result<Option<Collection>> {
+        self.get_item(
+            DynamoTableItem::collection_pk(id),
+            DynamoTableRowKind::CollectionMeta.get_prefixes().1,
+            None,
+        )
+        .await?
+        .map(|item| self.parse(item)?.into_collection())
+        .transpose()
+    }
+
+    async fn read_collection_movies(&self, id: &Uuid) -> Result<Vec<(MovieMetadata, i32)>> {
+        let movies = self.read_collection_positions(id).await?;
+        self.join_movies_metadata(movies).await
+    }
+
+    async fn read_movie_collections(&self, id: &Uuid) -> Result<Vec<Collection>> {
+        let memberships = self
+            .query_items(self.movie_collections_query(id), None)
+            .await?;
+        let mut collections = self.read_membership_collections(&memberships).await?;
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(collections)
+    }
+
+    async fn store_collection(&self, collection: Collection) -> Result<()> {
+        let write = Write::put(&DynamoTableItem::new_collection(&collection))?;
+        self.write_items(vec![write]).await
+    }
+
+    async fn store_collection_movie(
+        &self,
+        collection: Collection,
+        movie_id: &Uuid,
+        position: i32,
+    ) -> Result<()> {
+        let stored = self.read_collection_positions(&collection.id).await?;
+        let mut items =
+            DynamoTableItem::new_collection_movies(&collection, &stored, movie_id, position);
+        self.keep_unknown_attributes(&mut items).await?;
+        items.insert(0, DynamoTableItem::new_collection(&collection));
+        let writes = items
+            .iter()
+            .map(Write::put)
+            .collect::<Result<Vec<Write>>>()?;
+        self.write_items(writes).await
+    }
+
+    async fn read_series(&self, id: &Uuid) -> Result<Option<Series>> {
+        self.get_item(
+            DynamoTableItem::series_pk(id),
+            DynamoTableRowKind::SeriesMeta.get_prefixes().1,
+            None,
+        )
+        .await?
+        .map(|item| self.parse(item)?.into_series())
+        .transpose()
+    }
+
+    async fn read_series_seasons(&self, id: &Uuid) -> Result<Vec<Season>> {
+        let sk = DynamoTableRowKind::SeriesSeason.get_prefixes().1;
+        self.query_items(self.series_rows_query(id, sk), None)
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_season())
+            .collect()
+    }
+
+    async fn read_series_episodes(&self, id: &Uuid, season: Option<i32>) -> Result<Vec<Episode>> {
+        let sk = match season {
+            Some(number) => DynamoTableItem::series_season_episodes_sk(number),
+            None => DynamoTableRowKind::SeriesEpisode.get_prefixes().1,
+        };
+        self.query_items(self.series_rows_query(id, sk), None)
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_episode())
+            .collect()
+    }
+
+    async fn read_series_episodes_page(
+        &self,
+        id: &Uuid,
+        season: Option<i32>,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<Episode>> {
+        let sk = match season {
+            Some(number) => DynamoTableItem::series_season_episodes_sk(number),
+            None => DynamoTableRowKind::SeriesEpisode.get_prefixes().1,
+        };
+        let conditions = partition_cursor(DynamoTableItem::series_pk(id), sk.clone());
+        let mut input = self.series_rows_query(id, sk);
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_items(input, Some(first + 1)).await?;
+        Page::from_items(items, first, TABLE_KEY)?.try_map(|item| self.parse(item)?.into_episode())
+    }
+
+    async fn read_episode(
+        &self,
+        series_id: &Uuid,
+        season_number: i32,
+        episode_number: i32,
+    ) -> Result<Option<Episode>> {
+        self.get_item(
+            DynamoTableItem::series_pk(series_id),
+            DynamoTableItem::series_episode_sk(season_number, episode_number),
+            None,
+        )
+        .await?
+        .map(|item| self.parse(item)?.into_episode())
+        .transpose()
+    }
+
+    async fn read_episode_roles(
+        &self,
+        series_id: &Uuid,
+        season_number: i32,
+        episode_number: i32,
+    ) -> Result<Vec<(Uuid, Credit)>> {
+        let pk = DynamoTableItem::episode_pk(series_id, season_number, episode_number);
+        self.query_items(self.episode_partition_query(pk), None)
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_episode_actor())
+            .collect()
+    }
+
+    async fn read_actor_episodes(&self, id: &Uuid) -> Result<Vec<(Episode, Vec<Character>)>> {
+        let roles = self
+            .query_items(
+                self.actor_roles_query(id, DynamoTableRowKind::EpisodeActor),
+                None,
+            )
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_actor_episode())
+            .collect::<Result<Vec<_>>>()?;
+        let episodes = self
+            .read_episodes(roles.iter().map(|(k, _)| k.clone()).collect())
+            .await?;
+        // roles of episodes deleted in the meantime are skipped
+        Ok(roles
+            .into_iter()
+            .filter_map(|(k, characters)| episodes.get(&k).map(|e| (e.clone(), characters)))
+            .collect())
+    }
+
+    async fn store_series(&self, series: Series) -> Result<()> {
+        let write = Write::put(&DynamoTableItem::new_series(&series))?;
+        self.write_items(vec![write]).await
+    }
+
+    async fn store_season(&self, series: Series, season: Season) -> Result<()> {
+        let writes = vec![
+            Write::put(&DynamoTableItem::new_series(&series))?,
+            Write::put(&DynamoTableItem::new_season(&series, &season))?,
+        ];
+        self.write_items(writes).await
+    }
+
+    async fn store_episode(
+        &self,
+        series: Series,
+        episode: Episode,
+        roles: Vec<Role>,
+    ) -> Result<()> {
+        let mut items = DynamoTableItem::new_episode(&series, &episode, &roles);
+        self.keep_unknown_attributes(&mut items).await?;
+        let pk =
+            DynamoTableItem::episode_pk(&series.id, episode.season_number, episode.episode_number);
+        let stored = self
+            .query_items(self.episode_partition_query(pk), None)
+            .await?;
+        let stale = DynamoTableItem::stale_keys(DynamoTableItem::row_keys(stored)?, &items)
+            .into_iter()
+            .map(|(pk, sk)| Write::delete(key(pk, sk), None));
+        let season = key(
+            DynamoTableItem::series_pk(&series.id),
+            DynamoTableItem::series_season_sk(episode.season_number),
+        );
+        let mut writes = vec![Write::check(
+            season,
+            format!("season {} of series {}", episode.season_number, series.id),
+        )];
+        for item in &items {
+            writes.push(Write::put(item)?);
+        }
+        writes.extend(stale);
+        self.write_items(writes).await
+    }
+
+    async fn read_movie_reviews(&self, id: &Uuid) -> Result<Vec<Review>> {
+        self.query_items(
+            self.movie_rows_query(id, DynamoTableRowKind::MovieReview),
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|item| self.parse(item)?.into_review())
+        .collect()
+    }
+
+    async fn read_movie_reviews_page(
+        &self,
+        id: &Uuid,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<Page<Review>> {
+        let mut input = self.movie_rows_query(id, DynamoTableRowKind::MovieReview);
+        let conditions = partition_cursor(
+            DynamoTableItem::movie_pk(id),
+            DynamoTableRowKind::MovieReview.get_prefixes().1,
+        );
+        input.exclusive_start_key = after
+            .map(|c| decode_cursor(&c, TABLE_KEY, &conditions))
+            .transpose()?;
+        let items = self.query_items(input, Some(first + 1)).await?;
+        Page::from_items(items, first, TABLE_KEY)?.try_map(|item| self.parse(item)?.into_review())
+    }
+
+    async fn read_movie_review(&self, movie_id: &Uuid, user_id: &str) -> Result<Option<Review>> {
+        self.get_item(
+            DynamoTableItem::movie_pk(movie_id),
+            DynamoTableItem::movie_review_sk(user_id),
+            None,
+        )
+        .await?
+        .map(|item| self.parse(item)?.into_review())
+        .transpose()
+    }
+
+    async fn store_movie_review(&self, review: Review, previous_rating: Option<i32>) -> Result<()> {
+        let (count, sum) = match previous_rating {
+            Some(previous) => (0, review.rating - previous),
+            None => (1, review.rating),
+        };
+        let mut values = map! {
+            ":count".to_owned() => number(count),
+            ":sum".to_owned() => number(sum)
+        };
+        let mut counters = vec![
+            "rating_count :count".to_owned(),
+            "rating_sum :sum".to_owned(),
+        ];
+        if previous_rating != Some(review.rating) {
+            let bucket = |rating: i32| {
+                usize::try_from(rating - 1)
+                    .ok()
+                    .and_then(|index| RATING_HISTOGRAM_ATTRIBUTES.get(index))
+                    .ok_or_else(|| Error::Validation(format!("invalid rating {}", rating)))
+            };
+            values.insert(":one".to_owned(), number(1));
+            counters.push(format!("{} :one", bucket(review.rating)?));
+            if let Some(previous) = previous_rating {
+                values.insert(":minus_one".to_owned(), number(-1));
+                counters.push(format!("{} :minus_one", bucket(previous)?));
+            }
+        }
+        let update = TransactWriteItem {
+            update: Some(Update {
+                table_name: self.table_name.clone(),
+                key: key(
+                    DynamoTableItem::movie_pk(&review.movie_id),
+                    DynamoTableRowKind::MovieMeta.get_prefixes().1,
+                ),
+                update_expression: format!("ADD {}", counters.join(", ")),
+                condition_expression: Some("attribute_exists(pk)".to_owned()),
+                expression_attribute_values: Some(values),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let put = Write::put(&DynamoTableItem::new_movie_review(&review))?
+            .into_transact_write_item(&self.table_name);
+        let missing = Error::NotFound(format!("movie {}", review.movie_id));
+        self.transact(vec![put, update], vec![None, Some(missing)])
+            .await
     }
 }
 
-pub async fn read_movie(title: &str, published_at: &DateTime<Utc>) -> Result<Movie> {
-    Ok(Movie {
-        meta: read_movie_metadata(title, published_at).await?,
-        roles: read_movie_roles(title, published_at).await?,
-    })
+/// The rows of a page of a query or scan but those with legacy keys, they are not counted
+/// towards the limit of a read either.
+fn without_legacy_rows(items: Option<Vec<Item>>) -> impl Iterator<Item = Item> {
+    items
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|item| !DynamoTableItem::has_legacy_key(item))
 }
 
-pub async fn read_movie_metadata(
-    title: &str,
-    published_at: &DateTime<Utc>,
-) -> Result<MovieMetadata> {
-    let client = DynamoDbClient::new(get_region().await?);
-    let input = GetItemInput {
-        table_name: get_table_name().await?,
-        key: map! {
-            "pk".to_owned() => AttributeValue {
-                s: Some(format!("{}{}#{}", DynamoTableRowKind::MovieMeta.get_prefixes().0, title, published_at.year()).to_owned()),
-                ..Default::default()
-            },
-            "sk".to_owned() => AttributeValue {
-                s: Some(DynamoTableRowKind::MovieMeta.get_prefixes().1),
-                ..Default::default()
-            }
-        },
-        ..Default::default()
-    };
+/// A put or delete of a single item, an update of some of its attributes or a check that
+/// an item exists. Writes with an expected version are conditioned on the stored version,
+/// items written before versioning was introduced count as version 0. Writes with a
+/// conflict must not overwrite an existing item, the conflict is reported if there is one.
+/// A failed check reports the missing item.
+#[derive(Clone)]
+struct Write {
+    request: WriteRequest,
+    update: Option<AttributeUpdate>,
+    check: Option<Item>,
+    expected_version: Option<i32>,
+    conflict: Option<String>,
+    missing: Option<String>,
+}
 
-    match client.get_item(input).await {
-        Ok(output) => {
-            let item = output
-                .item
-                .ok_or_else(|| Into::<Error>::into("none error"))?;
-            let meta: DynamoTableItem = serde_dynamodb::from_hashmap(item)?;
-            match meta.kind {
-                DynamoTableItemKind::Movie { kind, .. } => match kind {
-                    MovieKindItem::Meta {
-                        title,
-                        imdb_id,
-                        published_at,
-                    } => Ok(MovieMetadata {
-                        title,
-                        imdb_id,
-                        published_at,
-                    }),
-                    _ => Err("nope".into()),
-                },
-                _ => Err("nope".into()),
+/// Sets and removes attributes of an item, the attributes not named are kept.
+#[derive(Clone)]
+struct AttributeUpdate {
+    key: Item,
+    set: Item,
+    remove: Vec<&'static str>,
+}
+
+impl AttributeUpdate {
+    /// The update expression with the names and values it refers to. All names are
+    /// placeholders, some attribute names are reserved words.
+    fn expression(self) -> (String, HashMap<String, String>, Item) {
+        let mut names = HashMap::new();
+        let mut values = Item::new();
+        let mut set = vec![];
+        for (i, (name, value)) in self.set.into_iter().enumerate() {
+            if name == "pk" || name == "sk" {
+                continue;
             }
+            set.push(format!("#s{} = :s{}", i, i));
+            names.insert(format!("#s{}", i), name);
+            values.insert(format!(":s{}", i), value);
         }
-        Err(e) => Err(e.to_string().into()),
+        let mut remove = vec![];
+        for (i, name) in self.remove.into_iter().enumerate() {
+            remove.push(format!("#r{}", i));
+            names.insert(format!("#r{}", i), name.to_owned());
+        }
+        let mut expression = format!("SET {}", set.join(", "));
+        if !remove.is_empty() {
+            expression.push_str(&format!(" REMOVE {}", remove.join(", ")));
+        }
+        (expression, names, values)
     }
 }
 
-pub async fn read_actor_metadata(name: &str) -> Result<Actor> {
-    let client = DynamoDbClient::new(get_region().await?);
-    let input = GetItemInput {
-        table_name: get_table_name().await?,
-        key: map! {
-            "pk".to_owned() => AttributeValue {
-                s: Some(format!("{}{}", DynamoTableRowKind::ActorMeta.get_prefixes().0, name).to_owned()),
+impl Write {
+    /// Puts an item, movie meta rows are updated instead so that their rating counters,
+    /// which are not conditioned on the version, are kept.
+    fn put(item: &DynamoTableItem) -> Result<Self> {
+        let (request, update) = match item.catalog_attributes()? {
+            Some((set, remove)) => (
+                WriteRequest::default(),
+                Some(AttributeUpdate {
+                    key: key(item.pk.clone(), item.sk.clone()),
+                    set,
+                    remove,
+                }),
+            ),
+            None => (
+                WriteRequest {
+                    put_request: Some(PutRequest {
+                        item: item.to_item()?,
+                    }),
+                    ..Default::default()
+                },
+                None,
+            ),
+        };
+        Ok(Write {
+            request,
+            update,
+            check: None,
+            expected_version: item.expected_version(),
+            conflict: None,
+            missing: None,
+        })
+    }
+
+    /// Puts an item that must not exist yet, `conflict` describes the existing item.
+    fn create(item: &DynamoTableItem, conflict: String) -> Result<Self> {
+        Ok(Write {
+            conflict: Some(conflict),
+            ..Write::put(item)?
+        })
+    }
+
+    fn delete(key: Item, expected_version: Option<i32>) -> Self {
+        Write {
+            request: WriteRequest {
+                delete_request: Some(DeleteRequest { key }),
                 ..Default::default()
             },
-            "sk".to_owned() => AttributeValue {
-                s: Some(DynamoTableRowKind::ActorMeta.get_prefixes().1),
-                ..Default::default()
-            }
-        },
-        ..Default::default()
-    };
+            update: None,
+            check: None,
+            expected_version,
+            conflict: None,
+            missing: None,
+        }
+    }
 
-    match client.get_item(input).await {
-        Ok(output) => {
-            let item = output
-                .item
-                .ok_or_else(|| Into::<Error>::into("none error"))?;
-            let meta: DynamoTableItem = serde_dynamodb::from_hashmap(item)?;
-            match meta.kind {
-                DynamoTableItemKind::Actor { kind, .. } => match kind {
-                    ActorKindItem::Meta {
-                        last_name,
-                        first_name,
-                    } => Ok(Actor {
-                        last_name,
-                        first_name,
-                    }),
-                },
-                _ => Err("nope".into()),
-            }
+    /// Checks that an item exists without writing it, `missing` describes the item.
+    fn check(key: Item, missing: String) -> Self {
+        Write {
+            request: WriteRequest::default(),
+            update: None,
+            check: Some(key),
+            expected_version: None,
+            conflict: None,
+            missing: Some(missing),
         }
-        Err(e) => Err(e.to_string().into()),
     }
-}
 
-pub async fn read_movie_roles(title: &str, published_at: &DateTime<Utc>) -> Result<Vec<Role>> {
-    let client = DynamoDbClient::new(get_region().await?);
-    let input = QueryInput {
-        table_name: get_table_name().await?,
-        expression_attribute_values: Some(map! {
-            ":pk".to_owned() => AttributeValue {
-                s: Some(format!("{}{}#{}", DynamoTableRowKind::MovieActor.get_prefixes().0, title, published_at.year()).to_owned()),
+    fn unconditioned(self) -> Self {
+        Write {
+            expected_version: None,
+            conflict: None,
+            ..self
+        }
+    }
+
+    fn key_starts_with(&self, pk_prefix: &str) -> bool {
+        let pk = match (&self.request.put_request, &self.request.delete_request) {
+            (Some(put), _) => put.item.get("pk"),
+            (_, Some(delete)) => delete.key.get("pk"),
+            (None, None) => self
+                .update
+                .as_ref()
+                .map(|update| &update.key)
+                .or(self.check.as_ref())
+                .and_then(|key| key.get("pk")),
+        };
+        pk.and_then(|pk| pk.s.as_ref())
+            .is_some_and(|pk| pk.starts_with(pk_prefix))
+    }
+
+    /// Whether the write has to be part of a transaction, updates cannot be batched either.
+    fn is_conditioned(&self) -> bool {
+        self.expected_version.is_some()
+            || self.conflict.is_some()
+            || self.missing.is_some()
+            || self.update.is_some()
+    }
+
+    /// The error reported if the condition of the write fails, `None` if that is a
+    /// concurrent modification.
+    fn failure(&self) -> Option<Error> {
+        match (&self.conflict, &self.missing) {
+            (Some(conflict), _) => Some(Error::Conflict(conflict.clone())),
+            (_, Some(missing)) => Some(Error::NotFound(missing.clone())),
+            (None, None) => None,
+        }
+    }
+
+    fn condition(&self) -> (Option<String>, Option<Item>) {
+        match self.expected_version {
+            None if self.conflict.is_some() => (Some("attribute_not_exists(pk)".to_owned()), None),
+            None if self.missing.is_some() => (Some("attribute_exists(pk)".to_owned()), None),
+            None => (None, None),
+            Some(0) => (Some("attribute_not_exists(version)".to_owned()), None),
+            Some(version) => (
+                Some("version = :version".to_owned()),
+                Some(map! {
+                    ":version".to_owned() => AttributeValue {
+                        n: Some(version.to_string()),
+                        ..Default::default()
+                    }
+                }),
+            ),
+        }
+    }
+
+    fn into_transact_write_item(self, table_name: &str) -> TransactWriteItem {
+        let (condition_expression, expression_attribute_values) = self.condition();
+        match (
+            self.request.put_request,
+            self.request.delete_request,
+            self.update,
+        ) {
+            (Some(PutRequest { item }), _, _) => TransactWriteItem {
+                put: Some(Put {
+                    table_name: table_name.to_owned(),
+                    item,
+                    condition_expression,
+                    expression_attribute_values,
+                    ..Default::default()
+                }),
                 ..Default::default()
             },
-            ":sk".to_owned() => AttributeValue {
-                s: Some(DynamoTableRowKind::MovieActor.get_prefixes().1),
+            (_, Some(DeleteRequest { key }), _) => TransactWriteItem {
+                delete: Some(Delete {
+                    table_name: table_name.to_owned(),
+                    key,
+                    condition_expression,
+                    expression_attribute_values,
+                    ..Default::default()
+                }),
                 ..Default::default()
-            }
-        }),
-        key_condition_expression: Some("pk = :pk AND begins_with(sk, :sk)".to_owned()),
-        ..Default::default()
-    };
-    let mut result = vec![];
-    match client.query(input).await {
-        Ok(o) => match o.items {
-            Some(items) => {
-                for item in items {
-                    let role: DynamoTableItem = serde_dynamodb::from_hashmap(item)?;
-                    match role.kind {
-                        DynamoTableItemKind::Movie { kind } => match kind {
-                            MovieKindItem::Actor { characters } => result.push(Role {
-                                actor: read_actor_metadata(role.sk.trim_start_matches(
-                                    &DynamoTableRowKind::MovieActor.get_prefixes().1,
-                                ))
-                                .await?,
-                                characters: characters
-                                    .iter()
-                                    .map(|x| Character { name: x.clone() })
-                                    .collect(),
-                            }),
-                            _ => {
-                                return Err("nope".into());
-                            }
-                        },
-                        _ => {
-                            return Err("nope".into());
-                        }
-                    }
+            },
+            (_, _, Some(update)) => {
+                let key = update.key.clone();
+                let (update_expression, names, mut values) = update.expression();
+                values.extend(expression_attribute_values.unwrap_or_default());
+                TransactWriteItem {
+                    update: Some(Update {
+                        table_name: table_name.to_owned(),
+                        key,
+                        update_expression,
+                        condition_expression,
+                        expression_attribute_names: Some(names),
+                        expression_attribute_values: Some(values),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
                 }
             }
-            None => {}
-        },
-        Err(e) => return Err(e.to_string().into()),
-    };
-    Ok(result)
+            (None, None, None) => TransactWriteItem {
+                condition_check: self.check.map(|key| ConditionCheck {
+                    table_name: table_name.to_owned(),
+                    key,
+                    condition_expression: condition_expression.unwrap_or_default(),
+                    expression_attribute_values,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        }
+    }
 }
 
-pub async fn store_movie(movie: Movie) -> Result<()> {
-    let client = DynamoDbClient::new(get_region().await?);
-    let items = DynamoTableItem::new_movie(&movie);
-    for item in items {
-        let input = PutItemInput {
-            table_name: get_table_name().await?,
-            item: serde_dynamodb::to_hashmap(&item)?,
-            ..Default::default()
-        };
-        client.put_item(input).await.unwrap();
+/// Describes an existing role of an actor in a movie that is added again.
+pub fn role_conflict(movie_id: &Uuid, actor_id: &Uuid) -> String {
+    format!(
+        "actor {} already has a role in movie {}",
+        actor_id, movie_id
+    )
+}
+
+/// Conditions on the cursors of a query of the rows of a partition whose sk starts with
+/// the prefix.
+pub fn partition_cursor(pk: String, sk_prefix: String) -> Vec<KeyCondition<'static>> {
+    vec![
+        KeyCondition::Equals("pk", pk),
+        KeyCondition::BeginsWith("sk", sk_prefix),
+    ]
+}
+
+/// Conditions on the cursors of a query of the inverted index.
+pub fn inverted_cursor(sk: String, pk_prefix: String) -> Vec<KeyCondition<'static>> {
+    vec![
+        KeyCondition::Equals("sk", sk),
+        KeyCondition::BeginsWith("pk", pk_prefix),
+    ]
+}
+
+/// Conditions on the cursors of a scan of the movie meta rows.
+pub fn movies_cursor() -> Vec<KeyCondition<'static>> {
+    let (pk_prefix, sk) = DynamoTableRowKind::MovieMeta.get_prefixes();
+    vec![
+        KeyCondition::Equals("sk", sk),
+        KeyCondition::BeginsWith("pk", pk_prefix),
+    ]
+}
+
+/// Conditions on the cursors of a query of the title index.
+pub fn title_cursor(title: &str) -> Vec<KeyCondition<'static>> {
+    vec![KeyCondition::Equals("title", title.to_owned())]
+}
+
+/// Conditions on the cursors of a query of the year index.
+pub fn year_cursor(year: i32) -> Vec<KeyCondition<'static>> {
+    vec![KeyCondition::Equals("published_year", year.to_string())]
+}
+
+/// Decodes a cursor of the movies released between both dates, returning the year the
+/// cursor points into together with its key.
+pub fn decode_between_cursor(
+    cursor: &str,
+    from: &DateTime<Utc>,
+    to: &DateTime<Utc>,
+) -> Result<(i32, Key)> {
+    let key = decode_cursor(cursor, YEAR_INDEX_KEY, &[])?;
+    let year = key
+        .get("published_year")
+        .and_then(|year| year.n.as_ref())
+        .and_then(|year| year.parse::<i32>().ok())
+        .filter(|year| (from.year()..=to.year()).contains(year))
+        .ok_or_else(|| Error::Validation("invalid cursor".to_owned()))?;
+    Ok((year, key))
+}
+
+/// Conditions on the cursors of a query of the search index.
+pub fn search_cursor(prefix: &str) -> Vec<KeyCondition<'static>> {
+    vec![
+        KeyCondition::Equals("search_prefix", search_prefix(prefix).unwrap_or_default()),
+        KeyCondition::BeginsWith("search_title", prefix.to_owned()),
+    ]
+}
+
+/// Splits the message of a canceled transaction into the reasons of its items, e.g.
+/// `Transaction cancelled, please refer cancellation reasons for specific reasons
+/// [None, ConditionalCheckFailed]`.
+fn cancellation_reasons(message: &str) -> Vec<&str> {
+    message
+        .rfind('[')
+        .and_then(|start| {
+            let reasons = &message[start + 1..];
+            reasons.find(']').map(|end| &reasons[..end])
+        })
+        .map(|reasons| reasons.split(',').map(str::trim).collect())
+        .unwrap_or_default()
+}
+
+fn number(value: i32) -> AttributeValue {
+    AttributeValue {
+        n: Some(value.to_string()),
+        ..Default::default()
+    }
+}
+
+fn key(pk: String, sk: String) -> Item {
+    map! {
+        "pk".to_owned() => AttributeValue {
+            s: Some(pk),
+            ..Default::default()
+        },
+        "sk".to_owned() => AttributeValue {
+            s: Some(sk),
+            ..Default::default()
+        }
+    }
+}
+
+impl DynamoMovieStore {
+    fn parse(&self, item: Item) -> Result<DynamoTableItem> {
+        DynamoTableItem::from_item(item, self.unknown_attributes)
+    }
+
+    /// Reads an item, only the given attributes if there is a projection.
+    async fn get_item(
+        &self,
+        pk: String,
+        sk: String,
+        projection: Option<&str>,
+    ) -> Result<Option<Item>> {
+        let input = GetItemInput {
+            table_name: self.table_name.clone(),
+            key: key(pk, sk),
+            projection_expression: projection.map(str::to_owned),
+            ..Default::default()
+        };
+        Ok(self.client.get_item(input).await?.item)
+    }
+
+    fn lookup_query(&self, lookup: String) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            index_name: Some(LOOKUP_INDEX.to_owned()),
+            expression_attribute_values: Some(map! {
+                ":lookup".to_owned() => AttributeValue {
+                    s: Some(lookup),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("lookup = :lookup".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn title_query(&self, title: &str, published_at: Option<&DateTime<Utc>>) -> QueryInput {
+        let mut values = map! {
+            ":title".to_owned() => AttributeValue {
+                s: Some(title.to_owned()),
+                ..Default::default()
+            }
+        };
+        let mut condition = "#title = :title".to_owned();
+        if let Some(published_at) = published_at {
+            values.insert(
+                ":published_at".to_owned(),
+                AttributeValue {
+                    s: Some(DynamoTableItem::published_at_value(published_at)),
+                    ..Default::default()
+                },
+            );
+            condition.push_str(" AND published_at = :published_at");
+        }
+        QueryInput {
+            table_name: self.table_name.clone(),
+            index_name: Some(TITLE_INDEX.to_owned()),
+            expression_attribute_names: Some(map! { "#title".to_owned() => "title".to_owned() }),
+            expression_attribute_values: Some(values),
+            key_condition_expression: Some(condition),
+            ..Default::default()
+        }
+    }
+
+    /// Queries the movies of a year, only those released between both dates if given.
+    fn year_query(
+        &self,
+        year: i32,
+        between: Option<(&DateTime<Utc>, &DateTime<Utc>)>,
+    ) -> QueryInput {
+        let mut values = map! {
+            ":year".to_owned() => AttributeValue {
+                n: Some(year.to_string()),
+                ..Default::default()
+            }
+        };
+        let mut condition = "published_year = :year".to_owned();
+        if let Some((from, to)) = between {
+            for (name, date) in &[(":from", from), (":to", to)] {
+                values.insert(
+                    (*name).to_owned(),
+                    AttributeValue {
+                        s: Some(DynamoTableItem::published_at_value(date)),
+                        ..Default::default()
+                    },
+                );
+            }
+            condition.push_str(" AND published_at BETWEEN :from AND :to");
+        }
+        QueryInput {
+            table_name: self.table_name.clone(),
+            index_name: Some(YEAR_INDEX.to_owned()),
+            expression_attribute_values: Some(values),
+            key_condition_expression: Some(condition),
+            ..Default::default()
+        }
+    }
+
+    /// Queries the movies whose normalized title starts with the normalized prefix, the
+    /// prefix must not be empty.
+    fn search_query(&self, prefix: &str) -> Result<QueryInput> {
+        let partition = search_prefix(prefix)
+            .ok_or_else(|| Error::Validation("the search query must not be empty".to_owned()))?;
+        Ok(QueryInput {
+            table_name: self.table_name.clone(),
+            index_name: Some(SEARCH_INDEX.to_owned()),
+            expression_attribute_values: Some(map! {
+                ":prefix".to_owned() => AttributeValue {
+                    s: Some(partition),
+                    ..Default::default()
+                },
+                ":title".to_owned() => AttributeValue {
+                    s: Some(prefix.to_owned()),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some(
+                "search_prefix = :prefix AND begins_with(search_title, :title)".to_owned(),
+            ),
+            ..Default::default()
+        })
+    }
+
+    /// Scans the table for movie meta rows without a search prefix.
+    fn unsearchable_movies_scan(&self) -> ScanInput {
+        ScanInput {
+            table_name: self.table_name.clone(),
+            expression_attribute_values: Some(map! {
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableRowKind::MovieMeta.get_prefixes().0),
+                    ..Default::default()
+                },
+                ":sk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableRowKind::MovieMeta.get_prefixes().1),
+                    ..Default::default()
+                }
+            }),
+            filter_expression: Some(
+                "begins_with(pk, :pk) AND sk = :sk AND attribute_not_exists(search_prefix)"
+                    .to_owned(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn imdb_id_query(&self, imdb_id: &str) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            index_name: Some(IMDB_ID_INDEX.to_owned()),
+            expression_attribute_values: Some(map! {
+                ":imdb_id".to_owned() => AttributeValue {
+                    s: Some(imdb_id.to_owned()),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("imdb_id = :imdb_id".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Queries all rows of a movie, the meta row included.
+    fn movie_partition_query(&self, id: &Uuid) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            expression_attribute_values: Some(map! {
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableItem::movie_pk(id)),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("pk = :pk".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Queries the portrayal rows of a character, one for every title and actor portraying it.
+    fn character_portrayals_query(&self, id: &Uuid) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            expression_attribute_values: Some(map! {
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableItem::character_pk(id)),
+                    ..Default::default()
+                },
+                ":sk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableItem::character_portrayals_sk()),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("pk = :pk AND begins_with(sk, :sk)".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Queries the rows of a movie of the given kind, e.g. its roles.
+    fn movie_rows_query(&self, id: &Uuid, kind: DynamoTableRowKind) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            expression_attribute_values: Some(map! {
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableItem::movie_pk(id)),
+                    ..Default::default()
+                },
+                ":sk".to_owned() => AttributeValue {
+                    s: Some(kind.get_prefixes().1),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("pk = :pk AND begins_with(sk, :sk)".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Queries the roles of an actor of the given kind, i.e. in movies or in episodes.
+    fn actor_roles_query(&self, id: &Uuid, kind: DynamoTableRowKind) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            index_name: Some(INVERTED_INDEX.to_owned()),
+            expression_attribute_values: Some(map! {
+                ":sk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableItem::movie_actor_sk(id)),
+                    ..Default::default()
+                },
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(kind.get_prefixes().0),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("sk = :sk AND begins_with(pk, :pk)".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Queries the rows of a series whose sort key starts with the given prefix, e.g. the
+    /// episodes of a season.
+    fn series_rows_query(&self, id: &Uuid, sk: String) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            expression_attribute_values: Some(map! {
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableItem::series_pk(id)),
+                    ..Default::default()
+                },
+                ":sk".to_owned() => AttributeValue {
+                    s: Some(sk),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("pk = :pk AND begins_with(sk, :sk)".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Queries the rows of an episode, its roles.
+    fn episode_partition_query(&self, pk: String) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            expression_attribute_values: Some(map! {
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(pk),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("pk = :pk".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Queries the movie rows of a collection.
+    fn collection_movies_query(&self, id: &Uuid) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            expression_attribute_values: Some(map! {
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableItem::collection_pk(id)),
+                    ..Default::default()
+                },
+                ":sk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableRowKind::CollectionMovie.get_prefixes().1),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("pk = :pk AND begins_with(sk, :sk)".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn movie_collections_query(&self, id: &Uuid) -> QueryInput {
+        QueryInput {
+            table_name: self.table_name.clone(),
+            index_name: Some(INVERTED_INDEX.to_owned()),
+            expression_attribute_values: Some(map! {
+                ":sk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableItem::collection_movie_sk(id)),
+                    ..Default::default()
+                },
+                ":pk".to_owned() => AttributeValue {
+                    s: Some(DynamoTableRowKind::CollectionMeta.get_prefixes().0),
+                    ..Default::default()
+                }
+            }),
+            key_condition_expression: Some("sk = :sk AND begins_with(pk, :pk)".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Reads the ids and positions of the movies of a collection ordered by position.
+    async fn read_collection_positions(&self, id: &Uuid) -> Result<Vec<(Uuid, i32)>> {
+        let mut movies = self
+            .query_items(self.collection_movies_query(id), None)
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_collection_movie())
+            .collect::<Result<Vec<_>>>()?;
+        movies.sort_by_key(|(id, position)| (*position, *id));
+        Ok(movies)
+    }
+
+    /// Reads the collections of the collection movie rows read through the inverted index.
+    async fn read_membership_collections(&self, memberships: &[Item]) -> Result<Vec<Collection>> {
+        let keys = memberships
+            .iter()
+            .map(|item| {
+                let collection_id = DynamoTableItem::movie_collection_id(item)?;
+                Ok(key(
+                    DynamoTableItem::collection_pk(&collection_id),
+                    DynamoTableRowKind::CollectionMeta.get_prefixes().1,
+                ))
+            })
+            .collect::<Result<Vec<Item>>>()?;
+        self.batch_get_items(keys)
+            .await?
+            .into_iter()
+            .map(|item| self.parse(item)?.into_collection())
+            .collect()
+    }
+
+    /// Replaces the movie ids of the given pairs with the metadata of the movies.
+    async fn join_movies_metadata<T>(
+        &self,
+        pairs: Vec<(Uuid, T)>,
+    ) -> Result<Vec<(MovieMetadata, T)>> {
+        let mut ids = pairs.iter().map(|(id, _)| *id).collect::<Vec<Uuid>>();
+        ids.sort();
+        ids.dedup();
+        let movies = self.read_movies_metadata(&ids).await?;
+        pairs
+            .into_iter()
+            .map(|(id, value)| {
+                let meta = movies
+                    .get(&id)
+                    .cloned()
+                    .ok_or_else(|| Error::NotFound(format!("movie {}", id)))?;
+                Ok((meta, value))
+            })
+            .collect()
+    }
+
+    /// Reads the episodes with the given keys of their rows in the series partitions,
+    /// episodes that do not exist are missing in the result.
+    async fn read_episodes(
+        &self,
+        mut keys: Vec<(String, String)>,
+    ) -> Result<HashMap<(String, String), Episode>> {
+        keys.sort();
+        keys.dedup();
+        let mut episodes = HashMap::new();
+        let keys = keys.into_iter().map(|(pk, sk)| key(pk, sk)).collect();
+        for item in self.batch_get_items(keys).await? {
+            let item = self.parse(item)?;
+            let episode_key = (item.pk.clone(), item.sk.clone());
+            episodes.insert(episode_key, item.into_episode()?);
+        }
+        Ok(episodes)
+    }
+
+    /// Replaces the titles of the given pairs with the movies and episodes.
+    async fn join_productions<T>(
+        &self,
+        pairs: Vec<(PortrayalTitle, T)>,
+    ) -> Result<Vec<(Production, T)>> {
+        let mut ids = vec![];
+        let mut keys = vec![];
+        for (title, _) in &pairs {
+            match title {
+                PortrayalTitle::Movie(id) => ids.push(*id),
+                PortrayalTitle::Episode(k) => keys.push(k.clone()),
+            }
+        }
+        ids.sort();
+        ids.dedup();
+        let movies = self.read_movies_metadata(&ids).await?;
+        let episodes = self.read_episodes(keys).await?;
+        pairs
+            .into_iter()
+            .map(|(title, value)| {
+                let production = match title {
+                    PortrayalTitle::Movie(id) => movies
+                        .get(&id)
+                        .cloned()
+                        .map(Production::Movie)
+                        .ok_or_else(|| Error::NotFound(format!("movie {}", id)))?,
+                    PortrayalTitle::Episode(k) => episodes
+                        .get(&k)
+                        .cloned()
+                        .map(Production::Episode)
+                        .ok_or_else(|| Error::NotFound(format!("episode {}", k.1)))?,
+                };
+                Ok((production, value))
+            })
+            .collect()
+    }
+
+    /// Runs the query and follows the continuation keys until either the result is
+    /// exhausted or `limit` items have been read.
+    async fn query_items(&self, mut input: QueryInput, limit: Option<usize>) -> Result<Vec<Item>> {
+        let mut result = vec![];
+        loop {
+            if let Some(limit) = limit {
+                input.limit = Some((limit - result.len()) as i64);
+            }
+            let output = self.client.query(input.clone()).await?;
+            result.extend(without_legacy_rows(output.items));
+            match output.last_evaluated_key {
+                Some(key) if limit.is_none_or(|l| result.len() < l) => {
+                    input.exclusive_start_key = Some(key);
+                }
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    /// Scans the table until `limit` items passed the filter or the table is scanned
+    /// completely, a page of a scan may hold fewer items than its limit.
+    async fn scan_items(&self, mut input: ScanInput, limit: usize) -> Result<Vec<Item>> {
+        let mut result = vec![];
+        loop {
+            input.limit = Some((limit - result.len()) as i64);
+            let output = self.client.scan(input.clone()).await?;
+            result.extend(without_legacy_rows(output.items));
+            match output.last_evaluated_key {
+                Some(key) if result.len() < limit => input.exclusive_start_key = Some(key),
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    /// Reads the keys of every row of a movie, the character rows of its roles included.
+    async fn read_movie_keys(&self, id: &Uuid) -> Result<Vec<(String, String)>> {
+        let items = self
+            .query_items(self.movie_partition_query(id), None)
+            .await?;
+        DynamoTableItem::row_keys(items)
+    }
+
+    /// Reads the keys of the roles and crew credits of a movie with the character rows of
+    /// its roles, the rows a write of the movie replaces. Its reviews are not read.
+    async fn read_movie_credit_keys(&self, id: &Uuid) -> Result<Vec<(String, String)>> {
+        let mut items = self
+            .query_items(
+                self.movie_rows_query(id, DynamoTableRowKind::MovieActor),
+                None,
+            )
+            .await?;
+        items.extend(
+            self.query_items(
+                self.movie_rows_query(id, DynamoTableRowKind::MovieCrew),
+                None,
+            )
+            .await?,
+        );
+        DynamoTableItem::row_keys(items)
+    }
+
+    /// Carries the attributes unknown to this version of the stored rows over to the items
+    /// replacing them. Meta rows and credits keep them already, only the rows built without
+    /// them are read, nothing is read if unknown attributes are ignored.
+    async fn keep_unknown_attributes(&self, items: &mut [DynamoTableItem]) -> Result<()> {
+        if self.unknown_attributes == UnknownAttributes::Ignore {
+            return Ok(());
+        }
+        let keys = items
+            .iter()
+            .filter(|item| item.expected_version().is_none() && item.unknown.is_empty())
+            .map(|item| key(item.pk.clone(), item.sk.clone()))
+            .collect();
+        let mut stored = HashMap::new();
+        for item in self.batch_get_items(keys).await? {
+            let item = self.parse(item)?;
+            stored.insert((item.pk, item.sk), item.unknown);
+        }
+        for item in items.iter_mut() {
+            if let Some(unknown) = stored.remove(&(item.pk.clone(), item.sk.clone())) {
+                item.unknown = unknown;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the items with the given keys in chunks, retrying unprocessed keys with an
+    /// exponential backoff. Items that do not exist are missing in the result.
+    async fn batch_get_items(&self, keys: Vec<Item>) -> Result<Vec<Item>> {
+        let mut result = vec![];
+        for chunk in keys.chunks(BATCH_GET_ITEMS_LIMIT) {
+            let mut keys = chunk.to_vec();
+            let mut attempt = 0;
+            let mut last_error = None;
+            while !keys.is_empty() {
+                if attempt >= BATCH_MAX_ATTEMPTS {
+                    return Err(last_error.unwrap_or_else(|| {
+                        Error::Throttled(format!("{} keys could not be read", keys.len()))
+                    }));
+                }
+                if attempt > 0 {
+                    delay_for(Duration::from_millis(BATCH_BACKOFF_MS << attempt)).await;
+                }
+                attempt += 1;
+                let input = BatchGetItemInput {
+                    request_items: map! { self.table_name.clone() => KeysAndAttributes {
+                        keys: keys.clone(),
+                        ..Default::default()
+                    }},
+                    ..Default::default()
+                };
+                match self.client.batch_get_item(input).await {
+                    Ok(output) => {
+                        if let Some(items) = output
+                            .responses
+                            .and_then(|mut r| r.remove(&self.table_name))
+                        {
+                            result.extend(items);
+                        }
+                        keys = output
+                            .unprocessed_keys
+                            .and_then(|mut u| u.remove(&self.table_name))
+                            .map(|k| k.keys)
+                            .unwrap_or_default();
+                        last_error = None;
+                    }
+                    Err(e) => {
+                        warn!("batch get failed: {}", e);
+                        last_error = Some(e.into());
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Writes the items in a single transaction if they fit into one. Otherwise every
+    /// conditioned write goes into one transaction, filled up with other writes, and the
+    /// remaining writes are batched once it succeeded. Writes needing more than one
+    /// transaction are rejected.
+    async fn write_items(&self, mut writes: Vec<Write>) -> Result<()> {
+        if writes.len() == 1 && writes[0].update.is_some() {
+            return self.update_item(writes.remove(0)).await;
+        }
+        let (mut transaction, mut rest): (Vec<Write>, Vec<Write>) =
+            writes.into_iter().partition(Write::is_conditioned);
+        if transaction.len() > TRANSACT_WRITE_ITEMS_LIMIT {
+            return Err(Error::Validation(format!(
+                "at most {} versioned items can be written at once",
+                TRANSACT_WRITE_ITEMS_LIMIT
+            )));
+        }
+        let room = (TRANSACT_WRITE_ITEMS_LIMIT - transaction.len()).min(rest.len());
+        transaction.extend(rest.drain(..room));
+        if !transaction.is_empty() {
+            self.transact_write_items(transaction).await?;
+        }
+        if rest.is_empty() {
+            return Ok(());
+        }
+        self.batch_write_items(rest.into_iter().map(|w| w.request).collect())
+            .await
+    }
+
+    /// Writes a single update without a transaction, a failed condition is reported like
+    /// in a transaction.
+    async fn update_item(&self, write: Write) -> Result<()> {
+        let failure = write.failure();
+        let (condition_expression, condition_values) = write.condition();
+        let update = match write.update {
+            Some(update) => update,
+            None => return Ok(()),
+        };
+        let key = update.key.clone();
+        let (update_expression, names, mut values) = update.expression();
+        values.extend(condition_values.unwrap_or_default());
+        let input = UpdateItemInput {
+            table_name: self.table_name.clone(),
+            key,
+            update_expression: Some(update_expression),
+            condition_expression,
+            expression_attribute_names: Some(names),
+            expression_attribute_values: Some(values),
+            ..Default::default()
+        };
+        match self.client.update_item(input).await {
+            Ok(_) => Ok(()),
+            Err(RusotoError::Service(UpdateItemError::ConditionalCheckFailed(_))) => {
+                Err(failure.unwrap_or(Error::ConcurrentModification))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn transact_write_items(&self, writes: Vec<Write>) -> Result<()> {
+        let failures = writes.iter().map(Write::failure).collect();
+        let items = writes
+            .into_iter()
+            .map(|w| w.into_transact_write_item(&self.table_name))
+            .collect();
+        self.transact(items, failures).await
+    }
+
+    /// Writes the items in a transaction. A failed condition of an item with a failure at
+    /// the same position, like a conflict, is reported as that failure, other failed
+    /// conditions as concurrent modifications.
+    async fn transact(
+        &self,
+        transact_items: Vec<TransactWriteItem>,
+        failures: Vec<Option<Error>>,
+    ) -> Result<()> {
+        let input = TransactWriteItemsInput {
+            transact_items,
+            ..Default::default()
+        };
+        match self.client.transact_write_items(input).await {
+            Ok(_) => Ok(()),
+            Err(RusotoError::Service(TransactWriteItemsError::TransactionCanceled(reasons))) => {
+                let codes = cancellation_reasons(&reasons);
+                let failure = codes.iter().zip(failures).find_map(|(code, failure)| {
+                    failure.filter(|_| *code == "ConditionalCheckFailed")
+                });
+                let failed = |code: &str| codes.iter().any(|c| c.starts_with(code));
+                if let Some(failure) = failure {
+                    Err(failure)
+                } else if failed("ConditionalCheckFailed") {
+                    Err(Error::ConcurrentModification)
+                } else if failed("TransactionConflict") {
+                    // another transaction is writing one of the items right now
+                    Err(Error::Conflict(reasons))
+                } else if failed("Throttling") || failed("ProvisionedThroughputExceeded") {
+                    Err(Error::Throttled(reasons))
+                } else if failed("ValidationError") || failed("ItemCollectionSizeLimitExceeded") {
+                    Err(Error::Validation(reasons))
+                } else {
+                    Err(Error::StorageUnavailable(reasons))
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the items in chunks, retrying unprocessed items with an exponential backoff.
+    /// This is not atomic, an error reports how many items could not be written.
+    async fn batch_write_items(&self, requests: Vec<WriteRequest>) -> Result<()> {
+        let total = requests.len();
+        let mut failed = 0;
+        for chunk in requests.chunks(BATCH_WRITE_ITEMS_LIMIT) {
+            let mut requests = chunk.to_vec();
+            let mut attempt = 0;
+            while !requests.is_empty() && attempt < BATCH_MAX_ATTEMPTS {
+                if attempt > 0 {
+                    delay_for(Duration::from_millis(BATCH_BACKOFF_MS << attempt)).await;
+                }
+                attempt += 1;
+                let input = BatchWriteItemInput {
+                    request_items: map! { self.table_name.clone() => requests.clone() },
+                    ..Default::default()
+                };
+                match self.client.batch_write_item(input).await {
+                    Ok(output) => {
+                        requests = output
+                            .unprocessed_items
+                            .and_then(|mut u| u.remove(&self.table_name))
+                            .unwrap_or_default();
+                    }
+                    Err(e) => {
+                        warn!("batch write failed: {}", e);
+                    }
+                }
+            }
+            failed += requests.len();
+        }
+        if failed > 0 {
+            return Err(Error::Throttled(format!(
+                "{} of {} items could not be written",
+                failed, total
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Service errors of DynamoDB operations that may signal a throttled request.
+trait Throttling: std::error::Error + 'static {
+    fn is_throttling(&self) -> bool;
+}
+
+macro_rules! throttling {
+    ($($error:ident),*) => {
+        $(impl Throttling for $error {
+            fn is_throttling(&self) -> bool {
+                matches!(
+                    self,
+                    $error::ProvisionedThroughputExceeded(_) | $error::RequestLimitExceeded(_)
+                )
+            }
+        })*
+    };
+}
+
+throttling!(
+    GetItemError,
+    QueryError,
+    ScanError,
+    BatchGetItemError,
+    BatchWriteItemError,
+    TransactWriteItemsError,
+    UpdateItemError
+);
+
+impl<E: Throttling> From<RusotoError<E>> for Error {
+    fn from(e: RusotoError<E>) -> Self {
+        match e {
+            RusotoError::Service(ref service) if service.is_throttling() => {
+                Error::Throttled(e.to_string())
+            }
+            e => Error::StorageUnavailable(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::data::Production;
+    use crate::storage::{DynamoMovieStore, MovieStore};
+    use chrono::{DateTime, Utc};
+    use rusoto_core::{
+        request::{DispatchSignedRequest, DispatchSignedRequestFuture},
+        signature::SignedRequest,
+        signature::SignedRequestPayload,
+        Region,
+    };
+    use rusoto_dynamodb::DynamoDbClient;
+    use rusoto_mock::{MockCredentialsProvider, MockRequestDispatcher};
+    use std::{
+        collections::VecDeque,
+        str::FromStr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+    use uuid::Uuid;
+
+    /// Answers every request with the next dispatcher of the sequence.
+    struct SequenceDispatcher(Mutex<VecDeque<MockRequestDispatcher>>);
+
+    impl DispatchSignedRequest for SequenceDispatcher {
+        fn dispatch(
+            &self,
+            request: SignedRequest,
+            timeout: Option<Duration>,
+        ) -> DispatchSignedRequestFuture {
+            let next = self
+                .0
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("unexpected request");
+            next.dispatch(request, timeout)
+        }
+    }
+
+    fn store<D>(dispatcher: D) -> DynamoMovieStore
+    where
+        D: DispatchSignedRequest + Send + Sync + 'static,
+    {
+        let config = Config {
+            region: Region::EuCentral1,
+            table_name: "aws-movies--movies".to_owned(),
+            unknown_attributes: crate::config::UnknownAttributes::Keep,
+        };
+        let client =
+            DynamoDbClient::new_with(dispatcher, MockCredentialsProvider, config.region.clone());
+        DynamoMovieStore::with_client(client, &config)
+    }
+
+    fn target(request: &SignedRequest) -> String {
+        String::from_utf8(request.headers()["x-amz-target"][0].clone()).unwrap()
+    }
+
+    fn body(request: &SignedRequest) -> serde_json::Value {
+        match &request.payload {
+            Some(SignedRequestPayload::Buffer(b)) => serde_json::from_slice(b).unwrap(),
+            _ => panic!("missing payload"),
+        }
+    }
+
+    fn movie(cast: usize) -> crate::data::Movie {
+        crate::data::Movie {
+            meta: crate::data::MovieMetadata {
+                imdb_id: Some("tt0113277".to_owned()),
+                ..crate::data::MovieMetadata::new(
+                    Uuid::from_u128(1),
+                    "Heat".to_owned(),
+                    DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap(),
+                )
+            },
+            crew: vec![],
+            roles: (0..cast)
+                .map(|i| crate::data::Role {
+                    actor: crate::data::Actor {
+                        id: Uuid::from_u128(100 + i as u128),
+                        version: 0,
+                        last_name: format!("Actor{}", i),
+                        first_name: "Some".to_owned(),
+                        ..Default::default()
+                    },
+                    credit: crate::data::Credit {
+                        characters: vec![crate::data::Character {
+                            id: Uuid::from_u128(1000 + i as u128),
+                            version: 1,
+                            name: format!("Character{}", i),
+                        }],
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_read_movie_metadata_with_injected_client() {
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body(
+                r#"{"Item":{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"},"lookup":{"S":"movie::Heat#1995"},"title":{"S":"Heat"},"imdb_id":{"S":"tt0113277"},"published_at":{"S":"1995-12-15T00:00:00Z"},"published_year":{"N":"1995"}}}"#,
+            )
+            .with_request_checker(|request| {
+                let body = body(request);
+                assert_eq!("aws-movies--movies", body["TableName"]);
+                assert_eq!(
+                    "movie::00000000-0000-0000-0000-000000000001",
+                    body["Key"]["pk"]["S"]
+                );
+            });
+        let store = store(dispatcher);
+
+        let published_at = DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let meta = runtime
+            .block_on(store.read_movie_metadata(&Uuid::from_u128(1)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(Uuid::from_u128(1), meta.id);
+        assert_eq!("Heat", meta.title);
+        assert_eq!(Some("tt0113277".to_owned()), meta.imdb_id);
+        assert_eq!(published_at, meta.published_at);
+    }
+
+    #[test]
+    fn test_read_errors_are_classified() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let throttled = MockRequestDispatcher::with_status(400).with_body(
+            r#"{"__type":"com.amazonaws.dynamodb.v20120810#ProvisionedThroughputExceededException","message":"rate exceeded"}"#,
+        );
+        match runtime.block_on(store(throttled).read_movie_metadata(&Uuid::from_u128(1))) {
+            Err(crate::error::Error::Throttled(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        let missing = MockRequestDispatcher::with_status(200).with_body("{}");
+        match runtime.block_on(store(missing).read_movie_metadata(&Uuid::from_u128(1))) {
+            Ok(None) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        let unavailable = MockRequestDispatcher::with_status(500).with_body("");
+        match runtime.block_on(store(unavailable).read_movie_metadata(&Uuid::from_u128(1))) {
+            Err(crate::error::Error::StorageUnavailable(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_find_movies_queries_lookup_index() {
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body(
+                r#"{"Items":[{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"},"lookup":{"S":"movie::Heat#1995"},"title":{"S":"Heat"},"imdb_id":{"S":"tt0113277"},"published_at":{"S":"1995-12-15T00:00:00Z"},"published_year":{"N":"1995"}}]}"#,
+            )
+            .with_request_checker(|request| {
+                let body = body(request);
+                assert_eq!("GSI-5", body["IndexName"]);
+                assert_eq!(
+                    "movie::Heat#1995",
+                    body["ExpressionAttributeValues"][":lookup"]["S"]
+                );
+            });
+        let published_at = DateTime::<Utc>::from_str("1995-01-01T00:00:00Z").unwrap();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let movies = runtime
+            .block_on(store(dispatcher).find_movies("Heat", &published_at))
+            .unwrap();
+        assert_eq!(
+            vec![Uuid::from_u128(1)],
+            movies.iter().map(|m| m.id).collect::<Vec<Uuid>>()
+        );
+    }
+
+    #[test]
+    fn test_find_movies_by_title_queries_title_index() {
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body(
+                r#"{"Items":[{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"},"title":{"S":"Heat"},"published_at":{"S":"1995-12-15T00:00:00Z"}}]}"#,
+            )
+            .with_request_checker(|request| {
+                let body = body(request);
+                assert_eq!("GSI-2", body["IndexName"]);
+                assert_eq!(
+                    "#title = :title AND published_at = :published_at",
+                    body["KeyConditionExpression"]
+                );
+                assert_eq!(
+                    "1995-12-15T00:00:00Z",
+                    body["ExpressionAttributeValues"][":published_at"]["S"]
+                );
+            });
+        let published_at = DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let movies = runtime
+            .block_on(store(dispatcher).find_movies_by_title("Heat", Some(&published_at)))
+            .unwrap();
+        assert_eq!(None, movies[0].imdb_id);
+    }
+
+    #[test]
+    fn test_read_movies_by_title_page_continues_after_cursor() {
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body(r#"{"Items":[]}"#)
+            .with_request_checker(|request| {
+                let body = body(request);
+                assert_eq!("GSI-2", body["IndexName"]);
+                assert_eq!("#title = :title", body["KeyConditionExpression"]);
+                assert_eq!(3, body["Limit"]);
+                assert_eq!(
+                    "1960-08-10T00:00:00Z",
+                    body["ExclusiveStartKey"]["published_at"]["S"]
+                );
+            });
+        let after = crate::pagination::encode_cursor(&map! {
+            "pk".to_owned() => rusoto_dynamodb::AttributeValue {
+                s: Some("movie::00000000-0000-0000-0000-000000000001".to_owned()),
+                ..Default::default()
+            },
+            "sk".to_owned() => rusoto_dynamodb::AttributeValue {
+                s: Some("meta".to_owned()),
+                ..Default::default()
+            },
+            "title".to_owned() => rusoto_dynamodb::AttributeValue {
+                s: Some("Ocean's Eleven".to_owned()),
+                ..Default::default()
+            },
+            "published_at".to_owned() => rusoto_dynamodb::AttributeValue {
+                s: Some("1960-08-10T00:00:00Z".to_owned()),
+                ..Default::default()
+            }
+        })
+        .unwrap();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let store = store(dispatcher);
+        let page = runtime
+            .block_on(store.read_movies_by_title_page("Ocean's Eleven", 2, Some(after.clone())))
+            .unwrap();
+        assert!(page.items.is_empty());
+        // the cursor of another title is rejected
+        let result = runtime.block_on(store.read_movies_by_title_page("Heat", 2, Some(after)));
+        assert_eq!("VALIDATION", result.unwrap_err().code());
+    }
+
+    #[test]
+    fn test_read_unsearchable_movies_scans_until_page_is_full() {
+        let scan = |items: &str| {
+            MockRequestDispatcher::with_status(200)
+                .with_body(items)
+                .with_request_checker(|request| {
+                    let body = body(request);
+                    assert_eq!("DynamoDB_20120810.Scan", target(request));
+                    assert_eq!(
+                        "begins_with(pk, :pk) AND sk = :sk AND attribute_not_exists(search_prefix)",
+                        body["FilterExpression"]
+                    );
+                })
+        };
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                // the first page of the scan holds no unsearchable movie
+                scan(
+                    r#"{"Items":[],"LastEvaluatedKey":{"pk":{"S":"actor::00000000-0000-0000-0000-000000000002"},"sk":{"S":"meta"}}}"#,
+                ),
+                scan(
+                    r#"{"Items":[{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"},"title":{"S":"Heat"},"published_at":{"S":"1995-12-15T00:00:00Z"}}]}"#,
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let page = runtime
+            .block_on(store(dispatcher).read_unsearchable_movies_page(1, None))
+            .unwrap();
+        assert_eq!("Heat", page.items[0].1.title);
+        assert!(!page.has_next_page);
+    }
+
+    #[test]
+    fn test_search_movies_queries_search_index() {
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body(r#"{"Items":[]}"#)
+            .with_request_checker(|request| {
+                let body = body(request);
+                assert_eq!("GSI-6", body["IndexName"]);
+                assert_eq!("i", body["ExpressionAttributeValues"][":prefix"]["S"]);
+                assert_eq!("irish", body["ExpressionAttributeValues"][":title"]["S"]);
+                assert_eq!(3, body["Limit"]);
+            });
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let page = runtime
+            .block_on(store(dispatcher).search_movies_page("irish", 2, None))
+            .unwrap();
+        assert!(page.items.is_empty());
+    }
+
+    #[test]
+    fn test_movies_between_query_years_until_the_page_is_full() {
+        let year = |year: &'static str, items: &str| {
+            MockRequestDispatcher::with_status(200)
+                .with_body(&format!(r#"{{"Items":[{}]}}"#, items))
+                .with_request_checker(move |request| {
+                    let body = body(request);
+                    assert_eq!("GSI-4", body["IndexName"]);
+                    assert_eq!(
+                        "published_year = :year AND published_at BETWEEN :from AND :to",
+                        body["KeyConditionExpression"]
+                    );
+                    assert_eq!(year, body["ExpressionAttributeValues"][":year"]["N"]);
+                })
+        };
+        let movie = |id: u128, published_at: &str| {
+            format!(
+                r#"{{"pk":{{"S":"movie::{}"}},"sk":{{"S":"meta"}},"version":{{"N":"1"}},"title":{{"S":"Movie"}},"published_at":{{"S":"{}"}},"published_year":{{"N":"{}"}}}}"#,
+                Uuid::from_u128(id),
+                published_at,
+                &published_at[..4]
+            )
+        };
+        // 1997 is not queried, the page is full with the surplus movie of 1996
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                year("1995", ""),
+                year(
+                    "1996",
+                    &format!(
+                        "{},{}",
+                        movie(1, "1996-03-08T00:00:00Z"),
+                        movie(2, "1996-06-21T00:00:00Z")
+                    ),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let from = DateTime::<Utc>::from_str("1995-12-01T00:00:00Z").unwrap();
+        let to = DateTime::<Utc>::from_str("1997-01-31T00:00:00Z").unwrap();
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let page = runtime
+            .block_on(store(dispatcher).read_movies_between_page(&from, &to, 1, None))
+            .unwrap();
+        assert_eq!(1, page.items.len());
+        assert!(page.has_next_page);
+    }
+
+    #[test]
+    fn test_read_movie_roles_follows_continuation_keys() {
+        let role = |id: u128| {
+            format!(
+                r#"{{"pk":{{"S":"movie::00000000-0000-0000-0000-000000000001"}},"sk":{{"S":"actor::{}"}},"characters":{{"L":[{{"S":"Someone"}}]}},"character_ids":{{"L":[{{"S":"00000000-0000-0000-0000-000000000003"}}]}}}}"#,
+                Uuid::from_u128(id)
+            )
+        };
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                MockRequestDispatcher::with_status(200).with_body(&format!(
+                    r#"{{"Items":[{}],"LastEvaluatedKey":{{"pk":{{"S":"movie::00000000-0000-0000-0000-000000000001"}},"sk":{{"S":"actor::00000000-0000-0000-0000-000000000002"}}}}}}"#,
+                    role(2)
+                )),
+                MockRequestDispatcher::with_status(200)
+                    .with_body(&format!(r#"{{"Items":[{}]}}"#, role(3)))
+                    .with_request_checker(|request| {
+                        assert_eq!(
+                            "actor::00000000-0000-0000-0000-000000000002",
+                            body(request)["ExclusiveStartKey"]["sk"]["S"]
+                        );
+                    }),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let roles = runtime
+            .block_on(store(dispatcher).read_movie_roles(&Uuid::from_u128(1)))
+            .unwrap();
+        assert_eq!(
+            vec![Uuid::from_u128(2), Uuid::from_u128(3)],
+            roles.iter().map(|(id, _)| *id).collect::<Vec<Uuid>>()
+        );
+    }
+
+    #[test]
+    fn test_read_character_portrayals_queries_character_partition() {
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                MockRequestDispatcher::with_status(200)
+                    .with_body(
+                        r#"{"Items":[{"pk":{"S":"character::00000000-0000-0000-0000-000000000003"},"sk":{"S":"portrayal::episode::00000000-0000-0000-0000-000000000004#0001#0002#00000000-0000-0000-0000-000000000002"}},
+                        {"pk":{"S":"character::00000000-0000-0000-0000-000000000003"},"sk":{"S":"portrayal::movie::00000000-0000-0000-0000-000000000001#00000000-0000-0000-0000-000000000002"}}]}"#,
+                    )
+                    .with_request_checker(|request| {
+                        assert_eq!(
+                            "character::00000000-0000-0000-0000-000000000003",
+                            body(request)["ExpressionAttributeValues"][":pk"]["S"]
+                        );
+                        assert_eq!(
+                            "portrayal::",
+                            body(request)["ExpressionAttributeValues"][":sk"]["S"]
+                        );
+                    }),
+                MockRequestDispatcher::with_status(200)
+                    .with_body(
+                        r#"{"Responses":{"aws-movies--movies":[{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"},"title":{"S":"Heat"},"published_at":{"S":"1995-12-15T00:00:00Z"}}]}}"#,
+                    )
+                    .with_request_checker(|request| {
+                        assert_eq!("DynamoDB_20120810.BatchGetItem", target(request));
+                    }),
+                MockRequestDispatcher::with_status(200)
+                    .with_body(
+                        r#"{"Responses":{"aws-movies--movies":[{"pk":{"S":"series::00000000-0000-0000-0000-000000000004"},"sk":{"S":"episode::0001#0002"},"episode_title":{"S":"Pilot"},"published_at":{"S":"1999-01-10T00:00:00Z"}}]}}"#,
+                    )
+                    .with_request_checker(|request| {
+                        assert_eq!("DynamoDB_20120810.BatchGetItem", target(request));
+                    }),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let portrayals = runtime
+            .block_on(store(dispatcher).read_character_portrayals(&Uuid::from_u128(3)))
+            .unwrap();
+        match &portrayals[0].0 {
+            Production::Episode(episode) => assert_eq!("Pilot", episode.title),
+            production => panic!("unexpected production {:?}", production),
+        }
+        match &portrayals[1].0 {
+            Production::Movie(meta) => assert_eq!("Heat", meta.title),
+            production => panic!("unexpected production {:?}", production),
+        }
+        assert_eq!(Uuid::from_u128(2), portrayals[1].1);
+    }
+
+    #[test]
+    fn test_memberships_are_read_from_keys_projected_by_inverted_index() {
+        // the inverted index projects the collection movie rows without their position
+        let memberships = || {
+            MockRequestDispatcher::with_status(200)
+                .with_body(
+                    r#"{"Items":[{"pk":{"S":"collection::00000000-0000-0000-0000-000000000005"},"sk":{"S":"movie::00000000-0000-0000-0000-000000000001"}}]}"#,
+                )
+                .with_request_checker(|request| {
+                    assert_eq!("GSI-1", body(request)["IndexName"]);
+                })
+        };
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                memberships(),
+                MockRequestDispatcher::with_status(200).with_body(
+                    r#"{"Responses":{"aws-movies--movies":[{"pk":{"S":"collection::00000000-0000-0000-0000-000000000005"},"sk":{"S":"meta"},"version":{"N":"1"},"collection_name":{"S":"Heat and More"}}]}}"#,
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let collections = runtime
+            .block_on(store(dispatcher).read_movie_collections(&Uuid::from_u128(1)))
+            .unwrap();
+        assert_eq!("Heat and More", collections[0].name);
+
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                memberships(),
+                MockRequestDispatcher::with_status(200).with_body(
+                    r#"{"Responses":{"aws-movies--movies":[{"pk":{"S":"collection::00000000-0000-0000-0000-000000000005"},"sk":{"S":"meta"},"version":{"N":"1"},"collection_name":{"S":"Heat and More"}}]}}"#,
+                ),
+                roles_query(
+                    r#"{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"},"version":{"N":"1"},"title":{"S":"Heat"},"published_at":{"S":"1995-12-15T00:00:00Z"}}"#,
+                ),
+                MockRequestDispatcher::with_status(200)
+                    .with_body("{}")
+                    .with_request_checker(|request| {
+                        assert_eq!("DynamoDB_20120810.TransactWriteItems", target(request));
+                        let items = body(request)["TransactItems"].as_array().unwrap().clone();
+                        assert!(items.iter().any(|item| {
+                            item["Delete"]["Key"]["pk"]["S"]
+                                == "collection::00000000-0000-0000-0000-000000000005"
+                        }));
+                        // the collection loses a movie, so its version is incremented
+                        let collection = items
+                            .iter()
+                            .find(|item| item["Put"]["Item"]["sk"]["S"] == "meta")
+                            .unwrap();
+                        assert_eq!("2", collection["Put"]["Item"]["version"]["N"]);
+                        assert_eq!(
+                            "version = :version",
+                            collection["Put"]["ConditionExpression"]
+                        );
+                    }),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let mut meta = crate::data::MovieMetadata::new(
+            Uuid::from_u128(1),
+            "Heat".to_owned(),
+            DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap(),
+        );
+        meta.version = 1;
+        runtime
+            .block_on(store(dispatcher).delete_movie(meta))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_store_episode_checks_season_in_transaction() {
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                stored_rows(""),
+                roles_query(""),
+                MockRequestDispatcher::with_status(400)
+                    .with_body(
+                        r#"{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled, please refer cancellation reasons for specific reasons [ConditionalCheckFailed, None]"}"#,
+                    )
+                    .with_request_checker(|request| {
+                        assert_eq!("DynamoDB_20120810.TransactWriteItems", target(request));
+                        let items = body(request)["TransactItems"].as_array().unwrap().clone();
+                        // the season is checked instead of writing the series
+                        assert_eq!(2, items.len());
+                        let check = &items[0]["ConditionCheck"];
+                        assert_eq!(
+                            "series::00000000-0000-0000-0000-000000000007",
+                            check["Key"]["pk"]["S"]
+                        );
+                        assert_eq!("season::0001", check["Key"]["sk"]["S"]);
+                        assert_eq!("attribute_exists(pk)", check["ConditionExpression"]);
+                        assert_eq!("episode::0001#0001", items[1]["Put"]["Item"]["sk"]["S"]);
+                    }),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let series = crate::data::Series {
+            id: Uuid::from_u128(7),
+            version: 2,
+            title: "The Sopranos".to_owned(),
+            published_at: DateTime::<Utc>::from_str("1999-01-10T00:00:00Z").unwrap(),
+            unknown: Default::default(),
+        };
+        let episode = crate::data::Episode {
+            series_id: series.id,
+            season_number: 1,
+            episode_number: 1,
+            title: "The Sopranos".to_owned(),
+            published_at: series.published_at,
+        };
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(store(dispatcher).store_episode(series, episode, vec![]));
+        assert!(
+            matches!(result, Err(crate::error::Error::NotFound(_))),
+            "{:?}",
+            result
+        );
+    }
+
+    fn roles_query(items: &str) -> MockRequestDispatcher {
+        MockRequestDispatcher::with_status(200)
+            .with_body(&format!(r#"{{"Items":[{}]}}"#, items))
+            .with_request_checker(|request| {
+                assert_eq!("DynamoDB_20120810.Query", target(request));
+            })
+    }
+
+    /// Answers the query of the rows of a movie whose sk starts with the prefix.
+    fn movie_rows(prefix: &'static str, items: &str) -> MockRequestDispatcher {
+        MockRequestDispatcher::with_status(200)
+            .with_body(&format!(r#"{{"Items":[{}]}}"#, items))
+            .with_request_checker(move |request| {
+                assert_eq!("DynamoDB_20120810.Query", target(request));
+                let input = body(request);
+                assert_eq!(
+                    "pk = :pk AND begins_with(sk, :sk)",
+                    input["KeyConditionExpression"]
+                );
+                assert_eq!(prefix, input["ExpressionAttributeValues"][":sk"]["S"]);
+            })
+    }
+
+    /// Answers the read of the stored rows whose unknown attributes are kept.
+    fn stored_rows(items: &str) -> MockRequestDispatcher {
+        MockRequestDispatcher::with_status(200)
+            .with_body(&format!(
+                r#"{{"Responses":{{"aws-movies--movies":[{}]}}}}"#,
+                items
+            ))
+            .with_request_checker(|request| {
+                assert_eq!("DynamoDB_20120810.BatchGetItem", target(request));
+            })
+    }
+
+    #[test]
+    fn test_store_movie_in_transaction() {
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                stored_rows(
+                    r#"{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"actor::00000000-0000-0000-0000-000000000064"},"characters":{"L":[{"S":"Character0"}]},"character_ids":{"L":[{"S":"00000000-0000-0000-0000-000000000901"}]},"popularity":{"N":"5"}}"#,
+                ),
+                // the reviews of the movie are not read
+                movie_rows(
+                    "actor::",
+                    r#"{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"actor::00000000-0000-0000-0000-000000000999"},"characters":{"L":[{"S":"Someone"}]},"character_ids":{"L":[{"S":"00000000-0000-0000-0000-000000000902"}]}}"#,
+                ),
+                movie_rows(
+                    "crew::",
+                    r#"{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"crew::directing#Mann"},"crew_name":{"S":"Mann"},"department":{"S":"directing"}}"#,
+                ),
+                MockRequestDispatcher::with_status(200)
+                    .with_body("{}")
+                    .with_request_checker(|request| {
+                        assert_eq!("DynamoDB_20120810.TransactWriteItems", target(request));
+                        let items = body(request)["TransactItems"].as_array().unwrap().clone();
+                        assert_eq!(10, items.len());
+                        assert_eq!(
+                            "attribute_not_exists(version)",
+                            items[0]["Update"]["ConditionExpression"]
+                        );
+                        assert!(items[1]["Put"]["ConditionExpression"].is_null());
+                        // the stored role keeps the attribute this version does not know
+                        let role = items
+                            .iter()
+                            .find(|item| {
+                                item["Put"]["Item"]["sk"]["S"]
+                                    == "actor::00000000-0000-0000-0000-000000000064"
+                            })
+                            .unwrap();
+                        assert_eq!("5", role["Put"]["Item"]["popularity"]["N"]);
+                        // the role of the actor missing in the new cast is deleted
+                        // together with the row of its character
+                        assert_eq!(
+                            "character::00000000-0000-0000-0000-000000000902",
+                            items[7]["Delete"]["Key"]["pk"]["S"]
+                        );
+                        assert_eq!(
+                            "actor::00000000-0000-0000-0000-000000000999",
+                            items[8]["Delete"]["Key"]["sk"]["S"]
+                        );
+                        // so is the crew credit missing in the new crew
+                        assert_eq!("crew::directing#Mann", items[9]["Delete"]["Key"]["sk"]["S"]);
+                    }),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime
+            .block_on(store(dispatcher).store_movie(movie(2)))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_store_movie_review_counts_rating_in_transaction() {
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body("{}")
+            .with_request_checker(|request| {
+                assert_eq!("DynamoDB_20120810.TransactWriteItems", target(request));
+                let items = body(request)["TransactItems"].as_array().unwrap().clone();
+                assert_eq!(2, items.len());
+                assert_eq!("version = :version", items[0]["Put"]["ConditionExpression"]);
+                assert_eq!("review::moviegoer", items[0]["Put"]["Item"]["sk"]["S"]);
+                let update = &items[1]["Update"];
+                assert_eq!("meta", update["Key"]["sk"]["S"]);
+                assert_eq!("attribute_exists(pk)", update["ConditionExpression"]);
+                // the rating changes from 6 to 8, the number of ratings stays the same
+                assert_eq!(
+                    "ADD rating_count :count, rating_sum :sum, rating_8 :one, rating_6 :minus_one",
+                    update["UpdateExpression"]
+                );
+                let values = &update["ExpressionAttributeValues"];
+                assert_eq!("0", values[":count"]["N"]);
+                assert_eq!("2", values[":sum"]["N"]);
+                assert_eq!("-1", values[":minus_one"]["N"]);
+            });
+        let review = crate::data::Review {
+            movie_id: Uuid::from_u128(1),
+            user_id: "moviegoer".to_owned(),
+            version: 1,
+            rating: 8,
+            text: None,
+            reviewed_at: DateTime::<Utc>::from_str("2020-01-01T00:00:00Z").unwrap(),
+        };
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime
+            .block_on(store(dispatcher).store_movie_review(review, Some(6)))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_store_movie_review_reports_missing_movie() {
+        let dispatcher = MockRequestDispatcher::with_status(400).with_body(
+            r#"{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled, please refer cancellation reasons for specific reasons [None, ConditionalCheckFailed]"}"#,
+        );
+        let review = crate::data::Review {
+            movie_id: Uuid::from_u128(1),
+            user_id: "moviegoer".to_owned(),
+            version: 0,
+            rating: 8,
+            text: None,
+            reviewed_at: DateTime::<Utc>::from_str("2020-01-01T00:00:00Z").unwrap(),
+        };
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(store(dispatcher).store_movie_review(review, None));
+        match result.unwrap_err() {
+            crate::error::Error::NotFound(message) => {
+                assert_eq!("movie 00000000-0000-0000-0000-000000000001", message)
+            }
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn test_store_movie_detects_concurrent_modification() {
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                stored_rows(""),
+                movie_rows("actor::", ""),
+                movie_rows("crew::", ""),
+                MockRequestDispatcher::with_status(400).with_body(
+                    r#"{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled, please refer cancellation reasons for specific reasons [ConditionalCheckFailed, None, None]"}"#,
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(store(dispatcher).store_movie(movie(1)));
+        match result.unwrap_err() {
+            crate::error::Error::ConcurrentModification => {}
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn test_canceled_transactions_are_classified() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        for (reasons, code) in &[
+            ("ConditionalCheckFailed, None", "CONCURRENT_MODIFICATION"),
+            ("None, TransactionConflict", "CONFLICT"),
+            ("ThrottlingError, None", "THROTTLED"),
+            ("None, ValidationError", "VALIDATION"),
+            ("ItemCollectionSizeLimitExceeded, None", "VALIDATION"),
+            ("InternalServerError, None", "STORAGE_UNAVAILABLE"),
+        ] {
+            let dispatcher = MockRequestDispatcher::with_status(400).with_body(&format!(
+                r#"{{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled, please refer cancellation reasons for specific reasons [{}]"}}"#,
+                reasons
+            ));
+            let result = runtime.block_on(store(dispatcher).add_movie_role(
+                movie(0).meta,
+                &Uuid::from_u128(7),
+                crate::data::Credit::default(),
+            ));
+            assert_eq!(*code, result.unwrap_err().code(), "{}", reasons);
+        }
+    }
+
+    #[test]
+    fn test_backfill_scan_skips_rows_with_legacy_keys() {
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body(
+                r#"{"Items":[
+                    {"pk":{"S":"movie::The Irishman#2019"},"sk":{"S":"meta"},"title":{"S":"The Irishman"},"imdb_id":{"S":"tt1302006"},"published_at":{"S":"2019-09-27T00:00:00Z"},"published_year":{"N":"2019"}},
+                    {"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"},"version":{"N":"1"},"title":{"S":"Heat"},"published_at":{"S":"1995-12-15T00:00:00Z"}}
+                ]}"#,
+            )
+            .with_request_checker(|request| {
+                assert_eq!("DynamoDB_20120810.Scan", target(request));
+            });
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let page = runtime
+            .block_on(store(dispatcher).read_unsearchable_movies_page(10, None))
+            .unwrap();
+        assert_eq!(
+            vec!["Heat"],
+            page.items
+                .iter()
+                .map(|(_, meta)| meta.title.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert!(!page.has_next_page);
+    }
+
+    #[test]
+    fn test_store_movie_metadata_keeps_ratings() {
+        let dispatcher = MockRequestDispatcher::with_status(400)
+            .with_body(
+                r#"{"__type":"com.amazonaws.dynamodb.v20120810#ConditionalCheckFailedException","message":"The conditional request failed"}"#,
+            )
+            .with_request_checker(|request| {
+                assert_eq!("DynamoDB_20120810.UpdateItem", target(request));
+                let input = body(request);
+                assert_eq!("meta", input["Key"]["sk"]["S"]);
+                assert_eq!("version = :version", input["ConditionExpression"]);
+                let expression = input["UpdateExpression"].as_str().unwrap();
+                // the cleared synopsis is removed, the rating counters are left alone
+                assert!(expression.contains(" REMOVE "), "{}", expression);
+                let names = input["ExpressionAttributeNames"].as_object().unwrap();
+                assert!(names.values().any(|name| name == "synopsis"));
+                assert!(names.values().any(|name| name == "title"));
+                assert!(!names.values().any(|name| name
+                    .as_str()
+                    .unwrap()
+                    .starts_with("rating_")));
+            });
+        let mut meta = movie(0).meta;
+        meta.version = 2;
+        meta.ratings.count = 1;
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(store(dispatcher).store_movie_metadata(meta));
+        match result.unwrap_err() {
+            crate::error::Error::ConcurrentModification => {}
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn test_add_movie_role_reports_existing_role() {
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![MockRequestDispatcher::with_status(400).with_body(
+                r#"{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled, please refer cancellation reasons for specific reasons [None, ConditionalCheckFailed]"}"#,
+            )]
+            .into_iter()
+            .collect(),
+        ));
+        let meta = movie(0).meta;
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(store(dispatcher).add_movie_role(
+            meta,
+            &Uuid::from_u128(7),
+            crate::data::Credit::default(),
+        ));
+        match result.unwrap_err() {
+            crate::error::Error::Conflict(message) => {
+                assert!(message.starts_with("actor 00000000-0000-0000-0000-000000000007"))
+            }
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn test_store_movie_in_batches() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let batch = || {
+            let counter = calls.clone();
+            MockRequestDispatcher::with_status(200)
+                .with_body(r#"{"UnprocessedItems":{}}"#)
+                .with_request_checker(move |request| {
+                    assert_eq!("DynamoDB_20120810.BatchWriteItem", target(request));
+                    let requests = body(request)["RequestItems"]["aws-movies--movies"]
+                        .as_array()
+                        .unwrap()
+                        .len();
+                    assert!(requests <= 25);
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+        };
+        let transaction = || {
+            MockRequestDispatcher::with_status(200)
+                .with_body("{}")
+                .with_request_checker(|request| {
+                    assert_eq!("DynamoDB_20120810.TransactWriteItems", target(request));
+                    let items = body(request)["TransactItems"].as_array().unwrap().clone();
+                    assert_eq!(25, items.len());
+                    let conditions = items
+                        .iter()
+                        .filter_map(|item| item["Update"]["ConditionExpression"].as_str())
+                        .collect::<Vec<&str>>();
+                    assert_eq!(vec!["attribute_not_exists(version)"], conditions);
+                })
+        };
+        let dispatcher = SequenceDispatcher(Mutex::new(
+            vec![
+                stored_rows(""),
+                movie_rows("actor::", ""),
+                movie_rows("crew::", ""),
+                transaction(),
+                batch(),
+                batch(),
+                batch(),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        // the meta item is guarded by its version and written in a transaction together with
+        // the first 24 new actors, the other 6 actors, the 30 movie actor and the 30
+        // character items follow in batches
+        runtime
+            .block_on(store(dispatcher).store_movie(movie(30)))
+            .unwrap();
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_store_movie_reports_unprocessed_items() {
+        let dispatcher = MockRequestDispatcher::with_status(200).with_body(
+            r#"{"UnprocessedItems":{"aws-movies--movies":[{"PutRequest":{"Item":{"pk":{"S":"movie::00000000-0000-0000-0000-000000000001"},"sk":{"S":"meta"}}}}]}}"#,
+        );
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(store(dispatcher).store_movie(movie(30)));
+        match result.unwrap_err() {
+            crate::error::Error::Throttled(message) => {
+                assert_eq!("3 of 66 items could not be written", message)
+            }
+            e => panic!("unexpected error {}", e),
+        }
     }
-    Ok(())
 }
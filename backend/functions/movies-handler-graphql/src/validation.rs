@@ -0,0 +1,250 @@
+use crate::data::MAX_RATING;
+use crate::dynamo::{DynamoTableItem, MAX_EPISODE_NUMBER};
+use crate::error::{Error, Result};
+use crate::search::normalize_title;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+
+/// Maximum size of a partition key in DynamoDB, titles and names end up in index keys.
+const MAX_PARTITION_KEY_BYTES: usize = 2048;
+/// Maximum size of a sort key in DynamoDB, normalized titles are the sort key of the
+/// search index.
+const MAX_SORT_KEY_BYTES: usize = 1024;
+/// Reviews are stored in a single item, which DynamoDB limits to 400 KB.
+const MAX_REVIEW_CHARS: usize = 20_000;
+/// The first motion pictures date from the 1870s.
+const MIN_YEAR: i32 = 1870;
+/// Movies are announced a few years ahead of their release at most.
+const MAX_YEARS_AHEAD: i32 = 10;
+
+/// A rule an input field breaks, `field` is the path of the field in the request like
+/// `roles[1].actor_last_name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub field: String,
+    pub message: String,
+}
+
+/// Collects the violations of a request, so that all of them are reported at once.
+#[derive(Debug, Default)]
+pub struct Validator {
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn violation(&mut self, field: &str, message: impl Into<String>) {
+        self.violations.push(Violation {
+            field: field.to_owned(),
+            message: message.into(),
+        });
+    }
+
+    /// Checks that a value is not blank, returns whether it is not.
+    pub fn required(&mut self, field: &str, value: &str) -> bool {
+        let present = !value.trim().is_empty();
+        if !present {
+            self.violation(field, "must not be empty");
+        }
+        present
+    }
+
+    /// Checks that a title is not blank and fits into the keys of the indexes.
+    pub fn title(&mut self, field: &str, title: &str, published_at: &DateTime<Utc>) {
+        if self.required(field, title)
+            && (DynamoTableItem::movie_lookup(title, published_at).len() > MAX_PARTITION_KEY_BYTES
+                || normalize_title(title).len() > MAX_SORT_KEY_BYTES)
+        {
+            self.violation(field, "is too long");
+        }
+    }
+
+    /// Checks that the names of an actor are not blank and fit into the lookup key.
+    /// The fields are the ones of the last and the first name.
+    pub fn actor_name(&mut self, fields: (&str, &str), last_name: &str, first_name: &str) {
+        let last_name_present = self.required(fields.0, last_name);
+        let first_name_present = self.required(fields.1, first_name);
+        if last_name_present
+            && first_name_present
+            && DynamoTableItem::actor_lookup(last_name, first_name).len() > MAX_PARTITION_KEY_BYTES
+        {
+            self.violation(fields.0, "together with the first name is too long");
+        }
+    }
+
+    /// Checks that an IMDb id of a title looks like `tt0111161`.
+    pub fn imdb_id(&mut self, field: &str, value: &str) {
+        self.imdb("tt", "tt0111161", field, value);
+    }
+
+    /// Checks that an IMDb id of a person looks like `nm0000199`.
+    pub fn imdb_name_id(&mut self, field: &str, value: &str) {
+        self.imdb("nm", "nm0000199", field, value);
+    }
+
+    fn imdb(&mut self, prefix: &str, example: &str, field: &str, value: &str) {
+        let digits = value.strip_prefix(prefix).unwrap_or_default();
+        if digits.len() < 7 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            self.violation(field, format!("must be an IMDb id like {}", example));
+        } else if value.len() > MAX_PARTITION_KEY_BYTES {
+            self.violation(field, "is too long");
+        }
+    }
+
+    pub fn positive(&mut self, field: &str, value: i32) {
+        if value <= 0 {
+            self.violation(field, "must be positive");
+        }
+    }
+
+    /// Checks that a season or episode number fits into the keys of the episodes.
+    pub fn episode_number(&mut self, field: &str, value: i32) {
+        if !(1..=MAX_EPISODE_NUMBER).contains(&value) {
+            self.violation(
+                field,
+                format!("must be between 1 and {}", MAX_EPISODE_NUMBER),
+            );
+        }
+    }
+
+    pub fn rating(&mut self, field: &str, value: i32) {
+        if !(1..=MAX_RATING).contains(&value) {
+            self.violation(field, format!("must be between 1 and {}", MAX_RATING));
+        }
+    }
+
+    /// Checks that a user id is not blank and fits into the sort key of the reviews.
+    pub fn user_id(&mut self, field: &str, value: &str) {
+        if self.required(field, value)
+            && DynamoTableItem::movie_review_sk(value).len() > MAX_SORT_KEY_BYTES
+        {
+            self.violation(field, "is too long");
+        }
+    }
+
+    pub fn review_text(&mut self, field: &str, value: &str) {
+        if self.required(field, value) && value.chars().count() > MAX_REVIEW_CHARS {
+            self.violation(
+                field,
+                format!("must not be longer than {} characters", MAX_REVIEW_CHARS),
+            );
+        }
+    }
+
+    /// Checks that a language code looks like an ISO 639-1 code like `en`.
+    pub fn language(&mut self, field: &str, value: &str) {
+        if value.len() != 2 || !value.bytes().all(|b| b.is_ascii_lowercase()) {
+            self.violation(field, "must be an ISO 639-1 language code like en");
+        }
+    }
+
+    /// Checks that a country code looks like an ISO 3166-1 alpha-2 code like `US`.
+    pub fn country(&mut self, field: &str, value: &str) {
+        if value.len() != 2 || !value.bytes().all(|b| b.is_ascii_uppercase()) {
+            self.violation(field, "must be an ISO 3166-1 country code like US");
+        }
+    }
+
+    pub fn url(&mut self, field: &str, value: &str) {
+        if !value.starts_with("https://") && !value.starts_with("http://") {
+            self.violation(field, "must be an http or https URL");
+        }
+    }
+
+    /// Checks that a birth date is not in the future.
+    pub fn birth_date(&mut self, field: &str, value: &NaiveDate) {
+        if *value > Utc::now().naive_utc().date() {
+            self.violation(field, "must not be in the future");
+        }
+    }
+
+    /// Checks that a release date lies between the first movies and a few years from now.
+    pub fn published_at(&mut self, field: &str, value: &DateTime<Utc>) {
+        let max_year = Utc::now().year() + MAX_YEARS_AHEAD;
+        if value.year() < MIN_YEAR || value.year() > max_year {
+            self.violation(
+                field,
+                format!("must be between the years {} and {}", MIN_YEAR, max_year),
+            );
+        }
+    }
+
+    /// Fails with all violations found, if any.
+    pub fn finish(self) -> Result<()> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidInput(self.violations))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::validation::{Validator, Violation};
+    use chrono::{DateTime, Utc};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_validator_collects_violations() {
+        let published_at = DateTime::<Utc>::from_str("1995-12-15T00:00:00Z").unwrap();
+        let mut validator = Validator::new();
+        validator.title("title", "Heat", &published_at);
+        validator.actor_name(("last_name", "first_name"), "Pacino", "Al");
+        validator.imdb_id("imdb_id", "tt0113277");
+        validator.published_at("published_at", &published_at);
+        validator.episode_number("season_number", 9999);
+        validator.rating("rating", 10);
+        validator.user_id("user_id", "moviegoer#42");
+        assert!(validator.finish().is_ok());
+
+        let mut validator = Validator::new();
+        validator.title("title", " \t", &published_at);
+        validator.actor_name(("last_name", "first_name"), "Pacino", "");
+        validator.actor_name(("last_name", "first_name"), &"Pacino".repeat(400), "Al");
+        validator.imdb_id("imdb_id", "tt123");
+        validator.imdb_id("imdb_id", "nm0000199");
+        validator.published_at(
+            "published_at",
+            &DateTime::<Utc>::from_str("0995-12-15T00:00:00Z").unwrap(),
+        );
+        validator.episode_number("season_number", 0);
+        validator.episode_number("episode_number", 10000);
+        validator.rating("rating", 11);
+        validator.user_id("user_id", &"#".repeat(1024));
+        validator.review_text("text", &"a".repeat(20_001));
+        let violations = match validator.finish() {
+            Err(Error::InvalidInput(violations)) => violations,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!(
+            Violation {
+                field: "title".to_owned(),
+                message: "must not be empty".to_owned()
+            },
+            violations[0]
+        );
+        assert_eq!(
+            vec![
+                "title",
+                "first_name",
+                "last_name",
+                "imdb_id",
+                "imdb_id",
+                "published_at",
+                "season_number",
+                "episode_number",
+                "rating",
+                "user_id",
+                "text"
+            ],
+            violations
+                .iter()
+                .map(|v| v.field.as_str())
+                .collect::<Vec<&str>>()
+        );
+    }
+}
@@ -0,0 +1,36 @@
+import boto3
+import json
+
+DEFAULT_CLIENT = boto3.client('lambda')
+DEFAULT_FUNCTION = "aws-movies--movies-handler-graphql"
+
+def invoke(payload, *, client = DEFAULT_CLIENT, function = DEFAULT_FUNCTION):
+    response = client.invoke(
+        FunctionName=function,
+        InvocationType='RequestResponse',
+        Payload=json.dumps(payload))
+    return json.loads(json.loads(response['Payload'].read()))
+
+# Movies written before searching was introduced lack the attributes of the search index,
+# they are written again page by page until the scan of the table is complete.
+after = None
+written = 0
+while True:
+    response = invoke({
+        "query": """mutation ($after: String)
+        {
+            backfill_search(first: 50, after: $after) {
+                edges { node { id } }
+                page_info { has_next_page, end_cursor }
+            }
+        }""",
+        "variables": { "after": after }
+    })
+    if response.get("errors"):
+        raise SystemExit(response["errors"])
+    page = response["data"]["backfill_search"]
+    written += len(page["edges"])
+    if not page["page_info"]["has_next_page"]:
+        break
+    after = page["page_info"]["end_cursor"]
+print("{} movies written".format(written))
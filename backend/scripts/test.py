@@ -107,7 +107,7 @@ pprint(response)
 response = invoke({
     "query": """query 
     {
-        get_movie(title: \"21 Jump Street\", published: 2012) { 
+        get_movie(title: \"21 Jump Street\", published_at: \"2012-03-16T00:00:00Z\") { 
             meta { 
                 title, 
                 published_at 
@@ -125,3 +125,25 @@ response = invoke({
     }"""
 })
 pprint(response)
+
+response = invoke({
+    "query": """query 
+    {
+        get_actor(last_name: \"Deniro\", first_name: \"Robert\") { 
+            last_name, 
+            first_name, 
+            movies { 
+                movie { 
+                    meta { 
+                        title, 
+                        published_at 
+                    } 
+                }, 
+                characters { 
+                    name 
+                } 
+            } 
+        }
+    }"""
+})
+pprint(response)
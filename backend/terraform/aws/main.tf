@@ -83,49 +83,70 @@ resource "aws_dynamodb_table" "movies-table" {
     name = "sk"
     type = "S"
   }
-  // attribute {
-  //   name = "published_at"
-  //   type = "S"
-  // }
-  // attribute {
-  //   name = "published_year"
-  //   type = "N"
-  // }
-  // attribute {
-  //   name = "title"
-  //   type = "S"
-  // }
-  // attribute {
-  //   name = "imdb_id"
-  //   type = "S"
-  // }
+  attribute {
+    name = "lookup"
+    type = "S"
+  }
+  attribute {
+    name = "published_at"
+    type = "S"
+  }
+  attribute {
+    name = "published_year"
+    type = "N"
+  }
+  attribute {
+    name = "title"
+    type = "S"
+  }
+  attribute {
+    name = "imdb_id"
+    type = "S"
+  }
+  attribute {
+    name = "search_prefix"
+    type = "S"
+  }
+  attribute {
+    name = "search_title"
+    type = "S"
+  }
 
   global_secondary_index {
     name               = "GSI-1"
     hash_key           = "sk"
     range_key          = "pk"
     projection_type    = "INCLUDE"
-    non_key_attributes = ["pk", "sk", "characters"]
-  }
-  // global_secondary_index {
-  //   name               = "GSI-2"
-  //   hash_key           = "title"
-  //   range_key          = "published_at"
-  //   projection_type    = "INCLUDE"
-  //   non_key_attributes = ["pk", "sk", "imdb_id"]
-  // }
-  // global_secondary_index {
-  //   name               = "GSI-3"
-  //   hash_key           = "imdb_id"
-  //   projection_type    = "INCLUDE"
-  //   non_key_attributes = ["pk", "sk", "imdb_id", "published_at", "title"]
-  // }
-  // global_secondary_index {
-  //   name               = "GSI-4"
-  //   hash_key           = "published_year"
-  //   projection_type    = "INCLUDE"
-  //   non_key_attributes = ["pk", "sk", "published_year", "published_at", "title"]
-  // }
+    non_key_attributes = ["pk", "sk", "characters", "character_ids", "position"]
+  }
+  global_secondary_index {
+    name            = "GSI-2"
+    hash_key        = "title"
+    range_key       = "published_at"
+    projection_type = "ALL"
+  }
+  global_secondary_index {
+    name            = "GSI-3"
+    hash_key        = "imdb_id"
+    projection_type = "ALL"
+  }
+  global_secondary_index {
+    name            = "GSI-4"
+    hash_key        = "published_year"
+    range_key       = "published_at"
+    projection_type = "ALL"
+  }
+  global_secondary_index {
+    name               = "GSI-5"
+    hash_key           = "lookup"
+    projection_type    = "ALL"
+  }
+  global_secondary_index {
+    name            = "GSI-6"
+    hash_key        = "search_prefix"
+    range_key       = "search_title"
+    projection_type = "ALL"
+  }
 }
 
 resource "aws_iam_policy" "dynamodb" {
@@ -138,8 +159,15 @@ resource "aws_iam_policy" "dynamodb" {
       "Effect": "Allow",
       "Action": [
           "dynamodb:Query",
+          "dynamodb:Scan",
           "dynamodb:PutItem",
-          "dynamodb:GetItem"
+          "dynamodb:GetItem",
+          "dynamodb:UpdateItem",
+          "dynamodb:DeleteItem",
+          "dynamodb:BatchGetItem",
+          "dynamodb:BatchWriteItem",
+          "dynamodb:ConditionCheckItem",
+          "dynamodb:TransactWriteItems"
       ],
       "Resource": "*"
     }